@@ -1,10 +1,44 @@
 #ifndef LZ4_MESSAGEPACK_H
 #define LZ4_MESSAGEPACK_H
 
+#include <stddef.h>
+#include <stdint.h>
+
 #ifdef __cplusplus
 extern "C" {
 #endif
 
+/* Compression modes, numbered like MessagePack-CSharp's MessagePackCompression */
+#define LZ4MP_COMPRESSION_LZ4_BLOCK 1
+#define LZ4MP_COMPRESSION_LZ4_BLOCK_ARRAY 2
+
+/*
+ * Status and error codes. The lz4mp_* functions return LZ4MP_OK or one of the
+ * codes below; the process_lz4_messagepack* functions return a string reading
+ * "Error: <message>" instead, with the code in lz4mp_last_error_code(). Codes
+ * 1-10 and 13 are also the exit codes of the command-line tool. No call ever lets a Rust panic unwind into the
+ * caller: it is reported as LZ4MP_ERROR_PANIC.
+ *
+ * Decoding applies the library's default limits for untrusted input (256 MiB
+ * decompressed in total, 16 MiB per block, 65536 blocks, nesting depth 128,
+ * 16 Mi elements per array or map) and fails with LZ4MP_ERROR_LIMIT_EXCEEDED
+ * beyond them, before the memory is allocated.
+ */
+#define LZ4MP_OK 0
+#define LZ4MP_ERROR_IO 1
+#define LZ4MP_ERROR_INVALID_INPUT 2
+#define LZ4MP_ERROR_INVALID_HEADER 3
+#define LZ4MP_ERROR_BLOCK_COUNT_MISMATCH 4
+#define LZ4MP_ERROR_BLOCK_LENGTH_MISMATCH 5
+#define LZ4MP_ERROR_CORRUPT_BLOCK 6
+#define LZ4MP_ERROR_UNSUPPORTED_EXTENSION 7
+#define LZ4MP_ERROR_UNSUPPORTED_COMPRESSION 8
+#define LZ4MP_ERROR_INVALID_MESSAGEPACK 9
+#define LZ4MP_ERROR_ENCODE 10
+#define LZ4MP_ERROR_PANIC 11
+#define LZ4MP_ERROR_OUTPUT_TOO_SMALL 12
+#define LZ4MP_ERROR_LIMIT_EXCEEDED 13
+
 /**
  * Process JSON input and return LZ4 compressed MessagePack
  * @param input_json JSON string to process
@@ -13,7 +47,108 @@ extern "C" {
 const char* process_lz4_messagepack(const char* input_json);
 
 /**
- * Free memory allocated by process_lz4_messagepack
+ * Process JSON input and return LZ4 compressed MessagePack in the given mode
+ * @param input_json JSON string to process
+ * @param compression LZ4MP_COMPRESSION_LZ4_BLOCK or LZ4MP_COMPRESSION_LZ4_BLOCK_ARRAY
+ * @return Pointer to the result string (must be freed with free_string)
+ */
+const char* process_lz4_messagepack_with_compression(const char* input_json, int compression);
+
+/**
+ * Options for process_lz4_messagepack_with_options. The flags are booleans:
+ * zero is off, anything else is on.
+ */
+typedef struct {
+    /* LZ4MP_COMPRESSION_LZ4_BLOCK or LZ4MP_COMPRESSION_LZ4_BLOCK_ARRAY */
+    int compression;
+    /* Write floats as float 32 when that keeps their value exactly */
+    int prefer_f32;
+    /* Read {"$bin": ...} and {"$ext": type, "data": ...} objects as binary and ext values */
+    int tagged_values;
+} lz4mp_encode_options;
+
+/**
+ * Process JSON input and return LZ4 compressed MessagePack using the given options
+ * @param input_json JSON string to process
+ * @param options Encoding options, or NULL for the defaults (Lz4BlockArray, no flags)
+ * @return Pointer to the result string (must be freed with free_string)
+ */
+const char* process_lz4_messagepack_with_options(const char* input_json, const lz4mp_encode_options* options);
+
+/**
+ * Encode UTF-8 JSON into a binary Lz4BlockArray envelope
+ * @param json JSON bytes (not NUL-terminated)
+ * @param len Number of bytes in json
+ * @param out Receives the envelope bytes (must be freed with lz4mp_free_buffer)
+ * @param out_len Receives the number of bytes in *out
+ * @return LZ4MP_OK, or an LZ4MP_ERROR_* code; *out is untouched on failure
+ */
+int lz4mp_encode(const uint8_t* json, size_t len, uint8_t** out, size_t* out_len);
+
+/**
+ * Decode a binary Lz4BlockArray or Lz4Block envelope into plain MessagePack
+ * @param input Envelope bytes
+ * @param len Number of bytes in input
+ * @param out Receives the MessagePack bytes (must be freed with lz4mp_free_buffer)
+ * @param out_len Receives the number of bytes in *out
+ * @return LZ4MP_OK, or an LZ4MP_ERROR_* code; *out is untouched on failure
+ */
+int lz4mp_decode(const uint8_t* input, size_t len, uint8_t** out, size_t* out_len);
+
+/**
+ * Decode a binary Lz4BlockArray or Lz4Block envelope into a caller-owned buffer
+ * @param input Envelope bytes
+ * @param len Number of bytes in input
+ * @param out Buffer that receives the MessagePack bytes (may be NULL when capacity is 0)
+ * @param capacity Number of bytes available in out
+ * @param out_len Receives the exact decoded length, also when out is too small
+ * @return LZ4MP_OK, LZ4MP_ERROR_OUTPUT_TOO_SMALL when capacity < *out_len,
+ *         or another LZ4MP_ERROR_* code
+ */
+int lz4mp_decode_into(const uint8_t* input, size_t len, uint8_t* out, size_t capacity, size_t* out_len);
+
+/**
+ * Free a buffer returned by lz4mp_encode or lz4mp_decode
+ * @param ptr Pointer to the buffer to free
+ * @param len Length the buffer was returned with
+ */
+void lz4mp_free_buffer(uint8_t* ptr, size_t len);
+
+/**
+ * Encode JSON into the Node-style JSON envelope, reporting errors as a status
+ * @param input_json JSON string to process
+ * @param options Encoding options, or NULL for the defaults
+ * @param out Receives the result string (must be freed with free_string)
+ * @return LZ4MP_OK, or an LZ4MP_ERROR_* code; *out is untouched on failure
+ */
+int lz4mp_encode_to_json(const char* input_json, const lz4mp_encode_options* options, char** out);
+
+/**
+ * Decode an LZ4 response into JSON
+ * @param input Raw response bytes (binary envelope or uncompressed MessagePack),
+ *              the Node-style [ext-header, {"type":"Buffer","data":[...]}, ...] JSON envelope,
+ *              or a lone {"type":"Buffer","data":[...]} object holding the raw bytes
+ * @param len Number of bytes in input
+ * @param out Receives the JSON string (must be freed with free_string)
+ * @return LZ4MP_OK, or an LZ4MP_ERROR_* code; *out is untouched on failure
+ */
+int lz4mp_decode_to_json(const uint8_t* input, size_t len, char** out);
+
+/**
+ * Error code of the most recent call on this thread
+ * @return LZ4MP_OK when the most recent call succeeded, otherwise its LZ4MP_ERROR_* code
+ */
+int lz4mp_last_error_code(void);
+
+/**
+ * Error message of the most recent call on this thread
+ * @return The message, or NULL when the most recent call succeeded. Owned by the
+ *         library and valid until the next call on this thread; do not free it.
+ */
+const char* lz4mp_last_error_message(void);
+
+/**
+ * Free a string returned by process_lz4_messagepack*, lz4mp_encode_to_json or lz4mp_decode_to_json
  * @param ptr Pointer to the string to free
  */
 void free_string(char* ptr);
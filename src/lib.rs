@@ -1,129 +1,375 @@
+use std::any::Any;
+use std::cell::RefCell;
 use std::ffi::{CStr, CString};
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
 use serde_json::Value as JsonValue;
-use rmpv::Value;
 use rmpv::encode::write_value;
-use lz4::block::compress;
-use std::io::Cursor;
+use codec::{Envelope, Error, JsonOptions, MsgpackOptions};
 
+#[path = "lz4_messagepack/src/lib.rs"]
+pub mod codec;
+
+pub use codec::Compression;
+
+/// Status returned by the `lz4mp_*` functions on success
+pub const LZ4MP_OK: c_int = 0;
+
+/// Status returned when a call panicked; the panic is caught and never unwinds into the caller
+pub const LZ4MP_ERROR_PANIC: c_int = 11;
+
+thread_local! {
+    /// Outcome of the most recent FFI call on this thread
+    static LAST_ERROR: RefCell<Option<(c_int, CString)>> = const { RefCell::new(None) };
+}
+
+/// Encode a JSON string into a MessagePack-CSharp `Lz4BlockArray` envelope.
+///
+/// On failure the returned string reads `Error: <message>` and the code is
+/// available from [`lz4mp_last_error_code`]; prefer [`lz4mp_encode_to_json`],
+/// which returns the code as a status instead.
+///
+/// # Safety
+///
+/// `input_json` must be null or point to a NUL-terminated string that stays
+/// valid for the duration of the call. The returned string must be released
+/// with [`free_string`].
 #[no_mangle]
-pub extern "C" fn process_lz4_messagepack(input_json: *const c_char) -> *mut c_char {
-    let input_str = unsafe {
-        if input_json.is_null() {
-            return CString::new("Error: Null input").unwrap().into_raw();
-        }
-        match CStr::from_ptr(input_json).to_str() {
-            Ok(s) => s,
-            Err(_) => return CString::new("Error: Invalid UTF-8").unwrap().into_raw(),
+pub unsafe extern "C" fn process_lz4_messagepack(input_json: *const c_char) -> *mut c_char {
+    process_lz4_messagepack_with_options(input_json, ptr::null())
+}
+
+/// Encode a JSON string using the given MessagePack-CSharp compression mode
+/// (`1` = `Lz4Block`, `2` = `Lz4BlockArray`).
+///
+/// # Safety
+///
+/// Same contract as [`process_lz4_messagepack`].
+#[no_mangle]
+pub unsafe extern "C" fn process_lz4_messagepack_with_compression(
+    input_json: *const c_char,
+    compression: c_int,
+) -> *mut c_char {
+    let options = EncodeOptions { compression, ..EncodeOptions::default() };
+    process_lz4_messagepack_with_options(input_json, &options)
+}
+
+/// Options for [`process_lz4_messagepack_with_options`] and [`lz4mp_encode_to_json`]
+///
+/// The flags are C booleans: zero is off, anything else is on.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct EncodeOptions {
+    /// MessagePack-CSharp compression mode (`1` = `Lz4Block`, `2` = `Lz4BlockArray`)
+    pub compression: c_int,
+    /// Write floats as `float 32` when that keeps their value exactly
+    pub prefer_f32: c_int,
+    /// Read `{"$bin": ...}` and `{"$ext": type, "data": ...}` objects as binary and extension values
+    pub tagged_values: c_int,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions { compression: Compression::Lz4BlockArray as c_int, prefer_f32: 0, tagged_values: 0 }
+    }
+}
+
+/// Encode a JSON string with the given [`EncodeOptions`]; a null `options`
+/// behaves like [`process_lz4_messagepack`].
+///
+/// # Safety
+///
+/// Same contract as [`process_lz4_messagepack`]. `options` must be null or
+/// point to a valid `EncodeOptions` for the duration of the call.
+#[no_mangle]
+pub unsafe extern "C" fn process_lz4_messagepack_with_options(
+    input_json: *const c_char,
+    options: *const EncodeOptions,
+) -> *mut c_char {
+    let result = guard(|| {
+        let options = options.as_ref().copied().unwrap_or_default();
+        output_string(process_json(read_input(input_json)?, &options)?)
+    });
+    match result {
+        Ok(output) => output.into_raw(),
+        Err(_) => last_error_string().into_raw(),
+    }
+}
+
+/// Encode a JSON string into the Node-style JSON envelope, like
+/// [`process_lz4_messagepack_with_options`], returning a status code.
+///
+/// Returns [`LZ4MP_OK`] on success or an error code on failure; the error is
+/// also available from [`lz4mp_last_error_code`] and
+/// [`lz4mp_last_error_message`]. On success `*out` receives a string that
+/// must be released with [`free_string`]; on failure it is left untouched.
+///
+/// # Safety
+///
+/// `input_json` must be null or point to a NUL-terminated string, `options`
+/// must be null or point to a valid `EncodeOptions`, and `out` must be valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn lz4mp_encode_to_json(
+    input_json: *const c_char,
+    options: *const EncodeOptions,
+    out: *mut *mut c_char,
+) -> c_int {
+    status(guard(|| {
+        write_string(out, || {
+            let options = options.as_ref().copied().unwrap_or_default();
+            output_string(process_json(read_input(input_json)?, &options)?)
+        })
+    }))
+}
+
+/// Encode a UTF-8 JSON buffer into a binary MessagePack-CSharp `Lz4BlockArray`
+/// envelope, the bytes a C# `MessagePackSerializer` reads directly.
+///
+/// Returns [`LZ4MP_OK`] on success or an error code on failure. On success
+/// `*out` and `*out_len` describe a buffer that must be released with
+/// [`lz4mp_free_buffer`]; on failure they are left untouched.
+///
+/// # Safety
+///
+/// `json` must point to `len` readable bytes (or be null with `len == 0`),
+/// and `out` / `out_len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn lz4mp_encode(json: *const u8, len: usize, out: *mut *mut u8, out_len: *mut usize) -> c_int {
+    status(guard(|| {
+        write_buffer(out, out_len, || {
+            let input = std::str::from_utf8(read_buffer(json, len)?)
+                .map_err(|_| Error::InvalidInput("Invalid UTF-8".to_string()))?;
+            let json_value: JsonValue = serde_json::from_str(input)?;
+            codec::encode_value(&codec::json_to_value(&json_value)?)
+        })
+    }))
+}
+
+/// Decode a binary `Lz4BlockArray` or `Lz4Block` envelope into the plain
+/// MessagePack it wraps.
+///
+/// Same return value and ownership contract as [`lz4mp_encode`].
+///
+/// # Safety
+///
+/// Same contract as [`lz4mp_encode`].
+#[no_mangle]
+pub unsafe extern "C" fn lz4mp_decode(input: *const u8, len: usize, out: *mut *mut u8, out_len: *mut usize) -> c_int {
+    status(guard(|| write_buffer(out, out_len, || codec::decode(read_buffer(input, len)?))))
+}
+
+/// Decode a binary `Lz4BlockArray` or `Lz4Block` envelope into a buffer owned
+/// by the caller, so a gateway can reuse one buffer across calls.
+///
+/// `*out_len` always receives the exact decoded length, read from the
+/// envelope header, unless the envelope itself is malformed. When `capacity`
+/// is smaller the call returns `LZ4MP_ERROR_OUTPUT_TOO_SMALL` (12) without
+/// writing to `out`, so passing a null `out` with `capacity == 0` just
+/// queries the size.
+///
+/// # Safety
+///
+/// `input` must point to `len` readable bytes (or be null with `len == 0`),
+/// `out` must point to `capacity` writable bytes (or be null with
+/// `capacity == 0`), and `out_len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn lz4mp_decode_into(
+    input: *const u8,
+    len: usize,
+    out: *mut u8,
+    capacity: usize,
+    out_len: *mut usize,
+) -> c_int {
+    status(guard(|| {
+        if out_len.is_null() {
+            return Err(Error::InvalidInput("Null output pointer".to_string()));
         }
+        let input = read_buffer(input, len)?;
+        *out_len = codec::decoded_len(input)?;
+        codec::decode_into_slice(input, borrow_output(out, capacity)?).map(|_| ())
+    }))
+}
+
+/// Release a buffer returned by [`lz4mp_encode`] or [`lz4mp_decode`].
+///
+/// # Safety
+///
+/// `ptr` must be null or a buffer previously returned by this library, with
+/// the length it was returned with, that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn lz4mp_free_buffer(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(ptr, len)));
+    }
+}
+
+/// Decode an LZ4 response into a JSON string.
+///
+/// `input` holds the raw response bytes (a binary `Lz4BlockArray` or `Lz4Block`
+/// envelope, or uncompressed MessagePack) or the Node-style
+/// `{"type": "Buffer", "data": [...]}` JSON form. On success `*out` receives
+/// a string that must be released with [`free_string`].
+///
+/// Same return value as [`lz4mp_encode_to_json`].
+///
+/// # Safety
+///
+/// `input` must point to `len` readable bytes (or be null with `len == 0`),
+/// and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn lz4mp_decode_to_json(input: *const u8, len: usize, out: *mut *mut c_char) -> c_int {
+    status(guard(|| {
+        write_string(out, || {
+            let json = codec::decode_any_to_json(read_buffer(input, len)?, &JsonOptions::default())?;
+            output_string(json.to_string())
+        })
+    }))
+}
+
+/// Error code of the most recent `lz4mp_*` or `process_lz4_messagepack*` call
+/// on this thread, or [`LZ4MP_OK`] when it succeeded.
+#[no_mangle]
+pub extern "C" fn lz4mp_last_error_code() -> c_int {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(LZ4MP_OK, |(code, _)| *code))
+}
+
+/// Message of the most recent failed call on this thread, or null when it succeeded.
+///
+/// The string is owned by the library and stays valid until the next call on
+/// this thread; it must not be passed to [`free_string`].
+#[no_mangle]
+pub extern "C" fn lz4mp_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |(_, message)| message.as_ptr()))
+}
+
+/// Run the body of an FFI call, recording its outcome as this thread's last
+/// error and catching panics so they never unwind into C or Dart code
+fn guard<T>(body: impl FnOnce() -> Result<T, Error>) -> Result<T, c_int> {
+    let (code, message) = match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(value)) => {
+            LAST_ERROR.with(|last| *last.borrow_mut() = None);
+            return Ok(value);
+        },
+        Ok(Err(e)) => (e.code(), e.to_string()),
+        Err(payload) => (LZ4MP_ERROR_PANIC, format!("Internal error: {}", panic_message(payload.as_ref()))),
     };
+    // Messages can quote the input, so keep any NUL byte from truncating them
+    let message = CString::new(message.replace('\0', "\\0")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some((code, message)));
+    Err(code)
+}
 
-    let result = process_json(input_str);
-    match result {
-        Ok(output) => CString::new(output).unwrap().into_raw(),
-        Err(e) => CString::new(format!("Error: {}", e)).unwrap().into_raw(),
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload.downcast_ref::<&str>().copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+fn status(result: Result<(), c_int>) -> c_int {
+    result.err().unwrap_or(LZ4MP_OK)
+}
+
+/// Format the last error as `Error: <message>`, the string the legacy functions have always returned
+fn last_error_string() -> CString {
+    LAST_ERROR.with(|last| match last.borrow().as_ref() {
+        Some((_, message)) => CString::new(format!("Error: {}", message.to_string_lossy())),
+        None => CString::new("Error"),
+    }).unwrap_or_default()
+}
+
+/// Borrow the caller's input buffer
+unsafe fn read_buffer<'a>(ptr: *const u8, len: usize) -> Result<&'a [u8], Error> {
+    match (ptr.is_null(), len) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(Error::InvalidInput("Null input".to_string())),
+        (false, _) => Ok(std::slice::from_raw_parts(ptr, len)),
+    }
+}
+
+/// Borrow the caller's output buffer
+unsafe fn borrow_output<'a>(ptr: *mut u8, capacity: usize) -> Result<&'a mut [u8], Error> {
+    match (ptr.is_null(), capacity) {
+        (true, 0) => Ok(&mut []),
+        (true, _) => Err(Error::InvalidInput("Null output pointer".to_string())),
+        (false, _) => Ok(std::slice::from_raw_parts_mut(ptr, capacity)),
+    }
+}
+
+/// Produce an output buffer and hand it to the caller, checking the output pointers first
+unsafe fn write_buffer(
+    out: *mut *mut u8,
+    out_len: *mut usize,
+    produce: impl FnOnce() -> Result<Vec<u8>, Error>,
+) -> Result<(), Error> {
+    if out.is_null() || out_len.is_null() {
+        return Err(Error::InvalidInput("Null output pointer".to_string()));
+    }
+    let bytes = produce()?.into_boxed_slice();
+    *out_len = bytes.len();
+    *out = Box::into_raw(bytes) as *mut u8;
+    Ok(())
+}
+
+/// Produce an output string and hand it to the caller, checking the output pointer first
+unsafe fn write_string(out: *mut *mut c_char, produce: impl FnOnce() -> Result<CString, Error>) -> Result<(), Error> {
+    if out.is_null() {
+        return Err(Error::InvalidInput("Null output pointer".to_string()));
+    }
+    *out = produce()?.into_raw();
+    Ok(())
+}
+
+fn output_string(output: String) -> Result<CString, Error> {
+    CString::new(output).map_err(|_| Error::Encode("Output contains a NUL byte".to_string()))
+}
+
+/// Borrow the caller's NUL-terminated UTF-8 input
+unsafe fn read_input<'a>(input_json: *const c_char) -> Result<&'a str, Error> {
+    if input_json.is_null() {
+        return Err(Error::InvalidInput("Null input".to_string()));
     }
+    CStr::from_ptr(input_json).to_str()
+        .map_err(|_| Error::InvalidInput("Invalid UTF-8".to_string()))
 }
 
-fn process_json(input: &str) -> Result<String, String> {
+fn process_json(input: &str, options: &EncodeOptions) -> Result<String, Error> {
+    let compression = Compression::try_from(options.compression)?;
+    let msgpack_options = MsgpackOptions {
+        prefer_f32: options.prefer_f32 != 0,
+        tagged_values: options.tagged_values != 0,
+        ..Default::default()
+    };
+
     // Parse input JSON
-    let json_value: JsonValue = serde_json::from_str(input)
-        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    let json_value: JsonValue = serde_json::from_str(input)?;
 
     // Convert JSON to MessagePack Value
-    let msgpack_value = convert_json_to_msgpack(&json_value)?;
+    let msgpack_value = codec::json_to_value_with(&json_value, &msgpack_options)?;
 
     // Serialize to MessagePack
     let mut buffer = Vec::new();
     write_value(&mut buffer, &msgpack_value)
-        .map_err(|e| format!("Failed to serialize MessagePack: {}", e))?;
-
-    // Compress with LZ4
-    let compressed_data = compress(&buffer, None, false)
-        .map_err(|e| format!("Failed to compress with LZ4: {}", e))?;
+        .map_err(|e| Error::Encode(format!("Failed to serialize MessagePack: {}", e)))?;
 
-    // Create output JSON structure
-    let output_json = create_output_json(&buffer, &compressed_data)?;
+    // Compress and create output JSON structure
+    let output_json = Envelope::compress(&buffer, compression)?.to_json();
 
     // Serialize to JSON string
     serde_json::to_string_pretty(&output_json)
-        .map_err(|e| format!("Failed to serialize output JSON: {}", e))
-}
-
-fn convert_json_to_msgpack(json: &JsonValue) -> Result<Value, String> {
-    match json {
-        JsonValue::Null => Ok(Value::Nil),
-        JsonValue::Bool(b) => Ok(Value::Boolean(*b)),
-        JsonValue::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                Ok(Value::Integer(i.into()))
-            } else if let Some(f) = n.as_f64() {
-                Ok(Value::F64(f))
-            } else {
-                Err("Invalid number".to_string())
-            }
-        }
-        JsonValue::String(s) => Ok(Value::String(s.into())),
-        JsonValue::Array(arr) => {
-            let mut result = Vec::new();
-            for item in arr {
-                result.push(convert_json_to_msgpack(item)?);
-            }
-            Ok(Value::Array(result))
-        }
-        JsonValue::Object(obj) => {
-            let mut result = Vec::new();
-            for (key, value) in obj {
-                result.push((Value::String(key.into()), convert_json_to_msgpack(value)?));
-            }
-            Ok(Value::Map(result))
-        }
-    }
-}
-
-fn create_output_json(uncompressed: &[u8], compressed: &[u8]) -> Result<JsonValue, String> {
-    // Create header data
-    let mut header_data = Vec::new();
-    header_data.push(204); // Type byte
-    
-    // Encode size in big-endian
-    let size = uncompressed.len();
-    if size <= 0xFF {
-        header_data.push(size as u8);
-    } else if size <= 0xFFFF {
-        header_data.push((size >> 8) as u8);
-        header_data.push(size as u8);
-    } else if size <= 0xFFFFFF {
-        header_data.push((size >> 16) as u8);
-        header_data.push((size >> 8) as u8);
-        header_data.push(size as u8);
-    } else {
-        header_data.push((size >> 24) as u8);
-        header_data.push((size >> 16) as u8);
-        header_data.push((size >> 8) as u8);
-        header_data.push(size as u8);
-    }
-
-    Ok(json!([
-        {
-            "buffer": {
-                "type": "Buffer",
-                "data": header_data
-            },
-            "type": 98
-        },
-        {
-            "type": "Buffer",
-            "data": compressed
-        }
-    ]))
+        .map_err(|e| Error::Encode(format!("Failed to serialize output JSON: {}", e)))
 }
 
+/// Release a string returned by this library.
+///
+/// # Safety
+///
+/// `ptr` must be null or a pointer previously returned by this library that
+/// has not been freed yet.
 #[no_mangle]
-pub extern "C" fn free_string(ptr: *mut c_char) {
-    unsafe {
-        if !ptr.is_null() {
-            let _ = CString::from_raw(ptr);
-        }
+pub unsafe extern "C" fn free_string(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        let _ = CString::from_raw(ptr);
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,327 @@
+//! Generate Rust models from a [`Schema`].
+//!
+//! Contracts keyed by `[Key(n)]` become structs that derive `Serialize` and
+//! `Deserialize` with their fields in key order, the array layout
+//! MessagePack-CSharp writes and [`to_vec_lz4`](super::to_vec_lz4) uses by
+//! default; gaps in the numbering are `Option<()>` fields. Contracts keyed by
+//! `[Key("name")]` rename their fields and serialize as maps whatever the
+//! struct encoding. `[Union]` types become enums that use the
+//! [`unions`](super::unions) helpers: `[key, body]` in MessagePack and
+//! `{"$type": ...}` objects in JSON.
+//!
+//! Nullable types are `Option`s, arrays are `Vec`s, maps are `BTreeMap`s,
+//! `binary` is `serde_bytes::ByteBuf`, `timestamp` is
+//! [`Timestamp`](super::Timestamp), `datetimeoffset` is the local time and
+//! offset minutes as `(Timestamp, i16)`, `guid` and `decimal` are `String`s,
+//! `datetime` and `timespan` are their raw `i64` and `any` is `rmpv::Value`,
+//! so the
+//! generated code needs `serde`, plus `serde_bytes`, `rmpv` and this crate
+//! when used.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use super::schema::{Field, FieldKey, FieldType, Schema, TypeDef, UnionCase};
+use super::{Error, Result};
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "static", "struct",
+    "trait", "true", "type", "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "gen",
+    "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+/// Names the generated code uses unqualified, which a contract must not shadow
+const RESERVED_TYPES: &[&str] = &["Option", "Vec", "String", "Box", "Result", "Self", "Serialize", "Deserialize"];
+
+/// Generate a Rust module with one type per schema type, or per type reachable from the root when it has one
+pub fn generate_rust(schema: &Schema) -> Result<String> {
+    let generator = Generator { schema };
+    let types = match &schema.root {
+        Some(root) => referenced_types(schema, root),
+        None => schema.types.clone(),
+    };
+    let mut out = String::new();
+
+    out.push_str("// Generated from MessagePack-CSharp contracts by `lz4_messagepack generate-rust`.\n");
+    out.push_str("// Positional contracts use the array layout: encode with `to_vec_lz4`, decode with `from_slice_lz4`.\n\n");
+    out.push_str("use serde::{Deserialize, Serialize};\n");
+    if types.values().any(|def| matches!(def, TypeDef::Union { .. })) {
+        out.push_str("use lz4_messagepack::unions::{serialize_case, RawUnion};\n");
+    }
+
+    for (name, def) in &types {
+        out.push('\n');
+        match def {
+            TypeDef::Object { fields } => generator.write_struct(&mut out, name, fields)?,
+            TypeDef::Union { cases } => generator.write_union(&mut out, name, cases),
+        }
+    }
+    Ok(out)
+}
+
+struct Generator<'a> {
+    schema: &'a Schema,
+}
+
+impl Generator<'_> {
+    fn write_struct(&self, out: &mut String, name: &str, fields: &[Field]) -> Result<()> {
+        let ident = type_ident(name);
+        let mut field_idents = BTreeSet::new();
+        let mut idents = Vec::new();
+        for field in fields {
+            let mut candidate = field_ident(&field.name);
+            while !field_idents.insert(candidate.clone()) {
+                candidate.push('_');
+            }
+            idents.push(candidate);
+        }
+
+        let indexed = fields.iter().all(|field| matches!(field.key, FieldKey::Index(_)));
+        let named = fields.iter().all(|field| matches!(field.key, FieldKey::Name(_)));
+
+        if indexed {
+            let _ = writeln!(out, "/// `{}`, written as an array in key order", name);
+            let _ = writeln!(out, "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]");
+            let _ = writeln!(out, "pub struct {} {{", ident);
+
+            let mut by_index: Vec<_> = fields.iter().zip(&idents).collect();
+            by_index.sort_by_key(|(field, _)| match field.key {
+                FieldKey::Index(index) => index,
+                FieldKey::Name(_) => u32::MAX,
+            });
+            let mut next = 0;
+            for (field, field_ident) in by_index {
+                let FieldKey::Index(index) = field.key else { continue };
+                for gap in next..index {
+                    let _ = writeln!(out, "    /// Unused key {}", gap);
+                    let _ = writeln!(out, "    #[serde(default)]");
+                    let _ = writeln!(out, "    pub _key{}: Option<()>,", gap);
+                }
+                next = index + 1;
+                let _ = writeln!(out, "    /// `[Key({})]`", index);
+                self.write_field(out, name, field, field_ident, None);
+            }
+            let _ = writeln!(out, "}}");
+        } else if named {
+            let _ = writeln!(out, "/// `{}`, written as a map keyed by name", name);
+            let _ = writeln!(out, "#[derive(Debug, Clone, PartialEq, Deserialize)]");
+            let _ = writeln!(out, "pub struct {} {{", ident);
+            for (field, field_ident) in fields.iter().zip(&idents) {
+                let FieldKey::Name(key) = &field.key else { continue };
+                let _ = writeln!(out, "    /// `[Key({:?})]`", key);
+                self.write_field(out, name, field, field_ident, Some(key));
+            }
+            let _ = writeln!(out, "}}\n");
+
+            // Derived Serialize would follow the serializer's struct encoding, which is arrays by default
+            let _ = writeln!(out, "impl Serialize for {} {{", ident);
+            let _ = writeln!(out, "    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {{");
+            let _ = writeln!(out, "        use serde::ser::SerializeMap;");
+            let _ = writeln!(out, "        let mut map = serializer.serialize_map(Some({}))?;", fields.len());
+            for (field, field_ident) in fields.iter().zip(&idents) {
+                if let FieldKey::Name(key) = &field.key {
+                    let _ = writeln!(out, "        map.serialize_entry({:?}, &self.{})?;", key, field_ident);
+                }
+            }
+            let _ = writeln!(out, "        map.end()");
+            let _ = writeln!(out, "    }}");
+            let _ = writeln!(out, "}}");
+        } else {
+            return Err(Error::InvalidSchema(format!("{} mixes integer and string keys", name)));
+        }
+        Ok(())
+    }
+
+    fn write_field(&self, out: &mut String, owner: &str, field: &Field, ident: &str, rename: Option<&str>) {
+        let mut attributes = Vec::new();
+        if let Some(key) = rename {
+            if key != ident.trim_start_matches("r#") {
+                attributes.push(format!("rename = {:?}", key));
+            }
+        }
+        if matches!(field.field_type, FieldType::Nullable(_)) {
+            attributes.push("default".to_string());
+        }
+        if !attributes.is_empty() {
+            let _ = writeln!(out, "    #[serde({})]", attributes.join(", "));
+        }
+        let _ = writeln!(out, "    pub {}: {},", ident, self.rust_type(owner, &field.field_type));
+    }
+
+    fn write_union(&self, out: &mut String, name: &str, cases: &[UnionCase]) {
+        let ident = type_ident(name);
+        let variants: Vec<_> = cases.iter().map(|case| type_ident(&case.type_name)).collect();
+
+        let _ = writeln!(out, "/// `{}` union, written as `[key, body]`", name);
+        let _ = writeln!(out, "#[derive(Debug, Clone, PartialEq)]");
+        let _ = writeln!(out, "pub enum {} {{", ident);
+        for (case, variant) in cases.iter().zip(&variants) {
+            let body = self.boxed(name, &case.type_name, variant.clone());
+            let _ = writeln!(out, "    /// `[Union({}, typeof({}))]`", case.key, case.type_name);
+            let _ = writeln!(out, "    {}({}),", variant, body);
+        }
+        let _ = writeln!(out, "}}\n");
+
+        let _ = writeln!(out, "impl Serialize for {} {{", ident);
+        let _ = writeln!(out, "    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {{");
+        let _ = writeln!(out, "        match self {{");
+        for (case, variant) in cases.iter().zip(&variants) {
+            let _ = writeln!(
+                out,
+                "            {}::{}(body) => serialize_case(serializer, {}, {:?}, body),",
+                ident, variant, case.key, case.type_name
+            );
+        }
+        let _ = writeln!(out, "        }}");
+        let _ = writeln!(out, "    }}");
+        let _ = writeln!(out, "}}\n");
+
+        let _ = writeln!(out, "impl<'de> Deserialize<'de> for {} {{", ident);
+        let _ = writeln!(out, "    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {{");
+        let _ = writeln!(out, "        let union = RawUnion::deserialize(deserializer)?;");
+        for (case, variant) in cases.iter().zip(&variants) {
+            let _ = writeln!(out, "        if union.is({}, {:?}) {{", case.key, case.type_name);
+            let _ = writeln!(out, "            return union.into_body().map({}::{});", ident, variant);
+            let _ = writeln!(out, "        }}");
+        }
+        let _ = writeln!(out, "        Err(union.unknown({:?}))", name);
+        let _ = writeln!(out, "    }}");
+        let _ = writeln!(out, "}}");
+    }
+
+    fn rust_type(&self, owner: &str, field_type: &FieldType) -> String {
+        self.rust_type_in(owner, field_type, true)
+    }
+
+    /// `direct` is false inside a `Vec` or map, which already holds its items on the heap
+    fn rust_type_in(&self, owner: &str, field_type: &FieldType, direct: bool) -> String {
+        match field_type {
+            FieldType::Any => "rmpv::Value".to_string(),
+            FieldType::Bool => "bool".to_string(),
+            FieldType::Int => "i64".to_string(),
+            FieldType::Float => "f64".to_string(),
+            FieldType::String => "String".to_string(),
+            FieldType::Binary => "serde_bytes::ByteBuf".to_string(),
+            FieldType::Timestamp => "lz4_messagepack::Timestamp".to_string(),
+            // The raw DateTime.ToBinary() value; Timestamp::from_datetime_binary reads it
+            FieldType::DateTime => "i64".to_string(),
+            FieldType::Guid | FieldType::Decimal => "String".to_string(),
+            FieldType::TimeSpan => "i64".to_string(),
+            FieldType::DateTimeOffset => "(lz4_messagepack::Timestamp, i16)".to_string(),
+            FieldType::Named(name) if direct => self.boxed(owner, name, type_ident(name)),
+            FieldType::Named(name) => type_ident(name),
+            FieldType::Array(item) => format!("Vec<{}>", self.rust_type_in(owner, item, false)),
+            FieldType::Map(key, value) => format!(
+                "std::collections::BTreeMap<{}, {}>",
+                self.rust_type_in(owner, key, false),
+                self.rust_type_in(owner, value, false)
+            ),
+            FieldType::Nullable(inner) => format!("Option<{}>", self.rust_type_in(owner, inner, direct)),
+        }
+    }
+
+    /// Box a reference that leads back to its owner without going through a `Vec` or map
+    fn boxed(&self, owner: &str, target: &str, ident: String) -> String {
+        let mut seen = BTreeSet::new();
+        if self.reaches(target, owner, &mut seen) {
+            format!("Box<{}>", ident)
+        } else {
+            ident
+        }
+    }
+
+    fn reaches(&self, from: &str, to: &str, seen: &mut BTreeSet<String>) -> bool {
+        if from == to {
+            return true;
+        }
+        if !seen.insert(from.to_string()) {
+            return false;
+        }
+        let targets: Vec<&str> = match self.schema.types.get(from) {
+            Some(TypeDef::Object { fields }) => fields.iter().filter_map(|field| direct_reference(&field.field_type)).collect(),
+            Some(TypeDef::Union { cases }) => cases.iter().map(|case| case.type_name.as_str()).collect(),
+            None => Vec::new(),
+        };
+        targets.into_iter().any(|target| self.reaches(target, to, seen))
+    }
+}
+
+/// The type a field embeds by value, if any
+fn direct_reference(field_type: &FieldType) -> Option<&str> {
+    match field_type {
+        FieldType::Named(name) => Some(name),
+        FieldType::Nullable(inner) => direct_reference(inner),
+        _ => None,
+    }
+}
+
+fn type_ident(name: &str) -> String {
+    let mut ident: String = name.chars().map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    if RESERVED_TYPES.contains(&ident.as_str()) || KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+/// `WeightName` and `weightName` become `weight_name`; keywords become raw identifiers
+fn field_ident(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut ident = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let after_lower = i > 0 && (chars[i - 1].is_lowercase() || chars[i - 1].is_ascii_digit());
+            let before_lower = i > 0 && chars[i - 1].is_uppercase() && chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if (after_lower || before_lower) && !ident.ends_with('_') {
+                ident.push('_');
+            }
+            ident.extend(c.to_lowercase());
+        } else if c.is_alphanumeric() || c == '_' {
+            ident.push(c);
+        } else if !ident.ends_with('_') {
+            ident.push('_');
+        }
+    }
+
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    match ident.as_str() {
+        "self" | "super" | "crate" | "_" => ident + "_",
+        keyword if KEYWORDS.contains(&keyword) => format!("r#{}", ident),
+        _ => ident,
+    }
+}
+
+/// The root and every type it references
+fn referenced_types(schema: &Schema, root: &str) -> BTreeMap<String, TypeDef> {
+    let mut types = BTreeMap::new();
+    let mut pending = vec![root.to_string()];
+    while let Some(name) = pending.pop() {
+        let Some(def) = schema.types.get(&name) else { continue };
+        if types.insert(name, def.clone()).is_some() {
+            continue;
+        }
+        match def {
+            TypeDef::Object { fields } => {
+                for field in fields {
+                    collect_names(&field.field_type, &mut pending);
+                }
+            },
+            TypeDef::Union { cases } => pending.extend(cases.iter().map(|case| case.type_name.clone())),
+        }
+    }
+    types
+}
+
+fn collect_names(field_type: &FieldType, names: &mut Vec<String>) {
+    match field_type {
+        FieldType::Named(name) => names.push(name.clone()),
+        FieldType::Array(inner) | FieldType::Nullable(inner) => collect_names(inner, names),
+        FieldType::Map(key, value) => {
+            collect_names(key, names);
+            collect_names(value, names);
+        },
+        _ => {},
+    }
+}
@@ -0,0 +1,376 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rmpv::decode::read_value;
+use rmpv::encode::write_value;
+use rmpv::{Integer, Value};
+use serde_json::{json, Value as JsonValue};
+use std::str::FromStr;
+
+use super::{Error, Limits, Result, Schema, Timestamp, TIMESTAMP_EXT, TYPELESS_EXT};
+
+/// Largest integer a JavaScript number (an IEEE double) holds exactly
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// How MessagePack `bin` values are written to JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BinaryFormat {
+    /// A standard base64 string, like MessagePack-CSharp's `ConvertToJson`
+    #[default]
+    Base64,
+    /// A lowercase hex string
+    Hex,
+    /// An array of byte values
+    Array,
+}
+
+impl FromStr for BinaryFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "base64" => Ok(BinaryFormat::Base64),
+            "hex" => Ok(BinaryFormat::Hex),
+            "array" => Ok(BinaryFormat::Array),
+            other => Err(Error::InvalidInput(format!("Unknown binary format: {}", other))),
+        }
+    }
+}
+
+/// How integers are written to JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BigIntFormat {
+    /// Always a JSON number; the full 64-bit range is kept exactly
+    #[default]
+    Number,
+    /// A string when the value is beyond ±2^53, where JavaScript loses precision
+    String,
+}
+
+impl FromStr for BigIntFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "number" => Ok(BigIntFormat::Number),
+            "string" => Ok(BigIntFormat::String),
+            other => Err(Error::InvalidInput(format!("Unknown big integer format: {}", other))),
+        }
+    }
+}
+
+/// How timestamp extensions (type -1) are written to JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampFormat {
+    /// An RFC 3339 string in UTC, such as `2024-05-01T12:30:00.250Z`
+    #[default]
+    Rfc3339,
+    /// `{"$timestamp": "2024-05-01T12:30:00.250Z"}`, which reads back as a timestamp with tagged values
+    Tagged,
+    /// Seconds since the Unix epoch; a float when there are nanoseconds
+    UnixSeconds,
+    /// Milliseconds since the Unix epoch; a float when there is a sub-millisecond part
+    UnixMillis,
+    /// The raw `{"$ext": -1, "data": ...}` form
+    Ext,
+}
+
+impl FromStr for TimestampFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "rfc3339" => Ok(TimestampFormat::Rfc3339),
+            "tagged" => Ok(TimestampFormat::Tagged),
+            "seconds" => Ok(TimestampFormat::UnixSeconds),
+            "millis" => Ok(TimestampFormat::UnixMillis),
+            "ext" => Ok(TimestampFormat::Ext),
+            other => Err(Error::InvalidInput(format!("Unknown timestamp format: {}", other))),
+        }
+    }
+}
+
+/// Policies for converting MessagePack to JSON
+///
+/// Map keys that aren't strings are stringified, and extensions are written
+/// as `{"$ext": type, "data": ...}` with the data in the binary format.
+/// Typeless values (ext 100) are written as the body's object with a
+/// `"$type"` member holding the assembly-qualified type name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonOptions {
+    pub binary: BinaryFormat,
+    pub big_integers: BigIntFormat,
+    pub timestamps: TimestampFormat,
+    /// Names the fields of positional `[Key(n)]` arrays, starting from the schema's root type
+    pub schema: Option<Schema>,
+    /// Decompress the blocks of an `Lz4BlockArray` envelope on all cores;
+    /// ignored unless the crate is built with the `parallel` feature
+    pub parallel: bool,
+    /// Bounds on the decoded size and structure; the defaults suit untrusted input
+    pub limits: Limits,
+}
+
+/// Convert a MessagePack value to a JSON value using the default options
+pub fn value_to_json(value: &Value) -> JsonValue {
+    value_to_json_with(value, &JsonOptions::default())
+}
+
+/// Convert a MessagePack value to a JSON value
+pub fn value_to_json_with(value: &Value, options: &JsonOptions) -> JsonValue {
+    match &options.schema {
+        Some(schema) => schema.value_to_json(value, options),
+        None => plain_value_to_json(value, options),
+    }
+}
+
+/// Convert a MessagePack value to a JSON value without applying the schema
+pub(crate) fn plain_value_to_json(value: &Value, options: &JsonOptions) -> JsonValue {
+    match value {
+        Value::Nil => JsonValue::Null,
+        Value::Boolean(b) => json!(*b),
+        Value::Integer(i) => integer_to_json(*i, options),
+        Value::F32(f) => json!(*f),
+        Value::F64(f) => json!(*f),
+        // Invalid UTF-8 is kept with replacement characters rather than dropped
+        Value::String(s) => json!(String::from_utf8_lossy(s.as_bytes())),
+        Value::Binary(b) => binary_to_json(b, options),
+        Value::Array(a) => {
+            json!(a.iter().map(|item| plain_value_to_json(item, options)).collect::<Vec<_>>())
+        },
+        Value::Map(m) => {
+            let mut obj = serde_json::Map::new();
+            for (k, v) in m {
+                obj.insert(map_key(k, options), plain_value_to_json(v, options));
+            }
+            JsonValue::Object(obj)
+        },
+        Value::Ext(typ, data) => {
+            if *typ == TIMESTAMP_EXT && options.timestamps != TimestampFormat::Ext {
+                if let Some(timestamp) = Timestamp::from_ext_data(data) {
+                    return timestamp_to_json(&timestamp, options);
+                }
+            }
+            if *typ == TYPELESS_EXT {
+                if let Some(json) = typeless_to_json(data, options) {
+                    return json;
+                }
+            }
+            json!({
+                "$ext": typ,
+                "data": binary_to_json(data, options)
+            })
+        }
+    }
+}
+
+/// JSON for a timestamp in the configured format; [`TimestampFormat::Ext`] is written as RFC 3339 here
+pub(crate) fn timestamp_to_json(timestamp: &Timestamp, options: &JsonOptions) -> JsonValue {
+    match options.timestamps {
+        TimestampFormat::Rfc3339 | TimestampFormat::Ext => json!(timestamp.to_string()),
+        TimestampFormat::Tagged => json!({ "$timestamp": timestamp.to_string() }),
+        TimestampFormat::UnixSeconds if timestamp.nanos() == 0 => json!(timestamp.seconds()),
+        TimestampFormat::UnixSeconds => json!(timestamp.as_seconds_f64()),
+        TimestampFormat::UnixMillis => {
+            let millis = i128::from(timestamp.seconds()) * 1000 + i128::from(timestamp.nanos() / 1_000_000);
+            match i64::try_from(millis) {
+                Ok(millis) if timestamp.nanos().is_multiple_of(1_000_000) => json!(millis),
+                _ => json!(timestamp.as_seconds_f64() * 1000.0),
+            }
+        },
+    }
+}
+
+/// `{"$type": name, ...body}` for the data of a Typeless extension: a type name string followed by the body
+///
+/// A body that is not a map goes under `"$value"`, so a nested Typeless body keeps its own `"$type"`.
+fn typeless_to_json(mut data: &[u8], options: &JsonOptions) -> Option<JsonValue> {
+    let type_name = read_value(&mut data).ok()?.as_str()?.to_string();
+    let body = read_value(&mut data).ok()?;
+    if !data.is_empty() {
+        return None;
+    }
+
+    let mut object = match (&body, plain_value_to_json(&body, options)) {
+        (Value::Map(_), JsonValue::Object(object)) => object,
+        (_, other) => serde_json::Map::from_iter([("$value".to_string(), other)]),
+    };
+    object.insert("$type".to_string(), json!(type_name));
+    Some(JsonValue::Object(object))
+}
+
+fn integer_to_json(i: Integer, options: &JsonOptions) -> JsonValue {
+    let is_big = match (i.as_u64(), i.as_i64()) {
+        (Some(u), _) => u > MAX_SAFE_INTEGER,
+        (None, Some(n)) => n.unsigned_abs() > MAX_SAFE_INTEGER,
+        (None, None) => false,
+    };
+
+    if is_big && options.big_integers == BigIntFormat::String {
+        return json!(i.to_string());
+    }
+    match i.as_u64() {
+        Some(u) => json!(u),
+        None => json!(i.as_i64()),
+    }
+}
+
+fn binary_to_json(data: &[u8], options: &JsonOptions) -> JsonValue {
+    match options.binary {
+        BinaryFormat::Base64 => json!(BASE64.encode(data)),
+        BinaryFormat::Hex => json!(data.iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+        BinaryFormat::Array => json!(data),
+    }
+}
+
+/// JSON object key for a MessagePack map key; non-string keys are stringified
+pub(crate) fn map_key(key: &Value, options: &JsonOptions) -> String {
+    match key {
+        Value::String(s) => String::from_utf8_lossy(s.as_bytes()).into_owned(),
+        Value::Integer(i) => i.to_string(),
+        other => match plain_value_to_json(other, options) {
+            JsonValue::String(s) => s,
+            json => json.to_string(),
+        },
+    }
+}
+
+/// Policies for converting JSON to MessagePack
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MsgpackOptions {
+    /// Write a float as `float 32` when that keeps its value exactly
+    pub prefer_f32: bool,
+    /// Read `{"$bin": ...}` as binary, `{"$ext": type, "data": ...}` as an extension,
+    /// `{"$timestamp": ...}` as a timestamp extension and objects whose `"$type"`
+    /// is an assembly-qualified type name as Typeless (ext 100) values
+    ///
+    /// The data is a base64 string or an array of bytes, so extensions written
+    /// by [`value_to_json_with`] in the base64 or array binary format read back.
+    /// A timestamp is an RFC 3339 string or a number of seconds since the Unix epoch.
+    /// A type name without a `,` is left alone, since union cases use `"$type"` too.
+    pub tagged_values: bool,
+    /// Turns named objects back into positional `[Key(n)]` arrays and `$type` objects into unions
+    pub schema: Option<Schema>,
+}
+
+/// Convert a JSON value to a MessagePack value using the default options
+pub fn json_to_value(json: &JsonValue) -> Result<Value> {
+    json_to_value_with(json, &MsgpackOptions::default())
+}
+
+/// Convert a JSON value to a MessagePack value
+pub fn json_to_value_with(json: &JsonValue, options: &MsgpackOptions) -> Result<Value> {
+    match &options.schema {
+        Some(schema) => schema.json_to_value(json, options),
+        None => plain_json_to_value(json, options),
+    }
+}
+
+/// Convert a JSON value to a MessagePack value without applying the schema
+pub(crate) fn plain_json_to_value(json: &JsonValue, options: &MsgpackOptions) -> Result<Value> {
+    match json {
+        JsonValue::Null => Ok(Value::Nil),
+        JsonValue::Bool(b) => Ok(Value::Boolean(*b)),
+        JsonValue::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Ok(Value::from(i))
+            } else if let Some(u) = n.as_u64() {
+                Ok(Value::from(u))
+            } else if let Some(f) = n.as_f64() {
+                Ok(float_to_value(f, options))
+            } else {
+                Err(Error::Encode("Unsupported number type".to_string()))
+            }
+        },
+        JsonValue::String(s) => Ok(Value::from(s.as_str())),
+        JsonValue::Array(a) => {
+            a.iter().map(|item| plain_json_to_value(item, options)).collect::<Result<Vec<_>>>().map(Value::Array)
+        },
+        JsonValue::Object(o) => {
+            if options.tagged_values {
+                if let Some(value) = tagged_value(o, options)? {
+                    return Ok(value);
+                }
+            }
+
+            let mut items = Vec::with_capacity(o.len());
+            for (k, v) in o {
+                items.push((Value::from(k.as_str()), plain_json_to_value(v, options)?));
+            }
+            Ok(Value::Map(items))
+        }
+    }
+}
+
+pub(crate) fn float_to_value(f: f64, options: &MsgpackOptions) -> Value {
+    let narrowed = f as f32;
+    if options.prefer_f32 && f64::from(narrowed) == f {
+        Value::F32(narrowed)
+    } else {
+        Value::F64(f)
+    }
+}
+
+/// Binary, extension, timestamp or Typeless value for a tagged object, or `None` for a plain map
+fn tagged_value(object: &serde_json::Map<String, JsonValue>, options: &MsgpackOptions) -> Result<Option<Value>> {
+    if let (1, Some(timestamp)) = (object.len(), object.get("$timestamp")) {
+        return json_to_timestamp(timestamp).map(|t| Some(t.to_value()));
+    }
+    if let Some(JsonValue::String(type_name)) = object.get("$type") {
+        if type_name.contains(',') {
+            return typeless_value(type_name, object, options).map(Some);
+        }
+    }
+    match (object.len(), object.get("$bin"), object.get("$ext"), object.get("data")) {
+        (1, Some(data), _, _) => Ok(Some(Value::Binary(tagged_bytes("$bin", data)?))),
+        (2, _, Some(ext_type), Some(data)) => {
+            let ext_type = ext_type.as_i64()
+                .and_then(|t| i8::try_from(t).ok())
+                .ok_or_else(|| Error::InvalidInput(format!("Invalid $ext type: {}", ext_type)))?;
+            Ok(Some(Value::Ext(ext_type, tagged_bytes("$ext", data)?)))
+        },
+        _ => Ok(None),
+    }
+}
+
+/// Typeless extension for `{"$type": name, ...body}` or `{"$type": name, "$value": body}`
+fn typeless_value(type_name: &str, object: &serde_json::Map<String, JsonValue>, options: &MsgpackOptions) -> Result<Value> {
+    let body = match (object.len(), object.get("$value")) {
+        (2, Some(body)) => plain_json_to_value(body, options)?,
+        _ => {
+            let fields = object.iter().filter(|(key, _)| *key != "$type");
+            let fields = fields.map(|(key, value)| Ok((Value::from(key.as_str()), plain_json_to_value(value, options)?)));
+            Value::Map(fields.collect::<Result<Vec<_>>>()?)
+        },
+    };
+
+    let mut data = Vec::new();
+    write_value(&mut data, &Value::from(type_name))
+        .and_then(|_| write_value(&mut data, &body))
+        .map_err(|e| Error::Encode(format!("Failed to write Typeless value: {}", e)))?;
+    Ok(Value::Ext(TYPELESS_EXT, data))
+}
+
+/// Bytes of a tagged value, given as a base64 string or an array of bytes
+pub(crate) fn tagged_bytes(tag: &str, data: &JsonValue) -> Result<Vec<u8>> {
+    match data {
+        JsonValue::String(text) => BASE64.decode(text)
+            .map_err(|e| Error::InvalidInput(format!("Invalid base64 in {} value: {}", tag, e))),
+        JsonValue::Array(items) => items.iter()
+            .map(|v| v.as_u64().and_then(|n| u8::try_from(n).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| Error::InvalidInput(format!("Invalid byte array in {} value", tag))),
+        _ => Err(Error::InvalidInput(format!("Expected a base64 string or a byte array in {} value", tag))),
+    }
+}
+
+/// Timestamp from an RFC 3339 string or a number of seconds since the Unix epoch
+pub(crate) fn json_to_timestamp(json: &JsonValue) -> Result<Timestamp> {
+    let timestamp = match json {
+        JsonValue::String(text) => return text.parse(),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(seconds) => Timestamp::new(seconds, 0),
+            None => n.as_f64().and_then(Timestamp::from_seconds_f64),
+        },
+        _ => None,
+    };
+    timestamp.ok_or_else(|| Error::InvalidInput(format!("Invalid timestamp: {}", json)))
+}
@@ -0,0 +1,697 @@
+//! Import schemas from C# MessagePack contracts.
+//!
+//! Scans C# sources for `[MessagePackObject]` classes, structs and records
+//! and for `[Union]` interfaces and base classes, and builds the [`Schema`]
+//! used for named decoding. This is a declaration scanner, not a compiler:
+//! it reads attributes, member types and names and skips everything else.
+//!
+//! Members are taken from `[Key(n)]` / `[Key("name")]` attributes, or from
+//! every public property and field when the contract uses
+//! `keyAsPropertyName: true`; `[IgnoreMember]` members are skipped. Keys of
+//! a base class that is itself a contract are included in derived classes.
+//!
+//! C# types map to schema types as follows: numeric types and enums are
+//! `int` (or `float`), `string` is `string`, `DateTime` is `timestamp`,
+//! `Guid`, `decimal`, `TimeSpan` and `DateTimeOffset` are the schema types
+//! of the same name in lowercase, `byte[]` is `binary`, `T[]` and
+//! `List<T>`-like collections are arrays, `Dictionary<K, V>`-like types are
+//! maps, `T?` is nullable and other contracts are referenced by name.
+//! Anything else is `any`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::schema::{Field, FieldKey, FieldType, Schema, TypeDef, UnionCase};
+use super::{Error, Result};
+
+/// Build a schema from the given C# source texts
+pub fn import_schema(sources: &[String], root: Option<&str>) -> Result<Schema> {
+    let mut declarations = Declarations::default();
+    for source in sources {
+        Parser::new(tokenize(source)?).parse_members(&mut declarations, None);
+    }
+    declarations.into_schema(root)
+}
+
+/// Build a schema from `.cs` files and directories searched recursively
+pub fn import_schema_from_paths(paths: &[PathBuf], root: Option<&str>) -> Result<Schema> {
+    let mut files = Vec::new();
+    for path in paths {
+        collect_sources(path, &mut files)?;
+    }
+
+    let sources = files.iter()
+        .map(|file| fs::read_to_string(file).map_err(|e| Error::io(format!("Failed to read {}", file.display()), e)))
+        .collect::<Result<Vec<_>>>()?;
+    import_schema(&sources, root)
+}
+
+fn collect_sources(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)
+        .map_err(|e| Error::io(format!("Failed to read directory {}", path.display()), e))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(|e| Error::io(format!("Failed to read directory {}", path.display()), e))?;
+    entries.sort();
+
+    for entry in entries {
+        let name = entry.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if entry.is_dir() {
+            // Build output holds generated copies of the sources
+            if name != "bin" && name != "obj" && !name.starts_with('.') {
+                collect_sources(&entry, files)?;
+            }
+        } else if name.ends_with(".cs") {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Punct(char),
+}
+
+/// Split C# source into tokens, dropping comments, preprocessor lines and character literals
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut line_start = true;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line_start = true;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if line_start && c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        line_start = false;
+
+        if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '"' || ((c == '@' || c == '$') && matches!(chars.get(i + 1), Some('"') | Some('@') | Some('$'))) {
+            // Regular, verbatim (@"") and interpolated ($"") strings
+            let start = i;
+            let mut verbatim = false;
+            while i < chars.len() && matches!(chars[i], '@' | '$') {
+                verbatim |= chars[i] == '@';
+                i += 1;
+            }
+            if chars.get(i) != Some(&'"') {
+                let prefix: String = chars[start..i].iter().collect();
+                return Err(Error::InvalidInput(format!("String prefix {:?} is not followed by a quote", prefix)));
+            }
+            i += 1;
+            let mut text = String::new();
+            while i < chars.len() {
+                match chars[i] {
+                    '"' if verbatim && chars.get(i + 1) == Some(&'"') => {
+                        text.push('"');
+                        i += 2;
+                    },
+                    '"' => break,
+                    '\\' if !verbatim => {
+                        if let Some(&escaped) = chars.get(i + 1) {
+                            text.push(escaped);
+                        }
+                        i += 2;
+                    },
+                    other => {
+                        text.push(other);
+                        i += 1;
+                    },
+                }
+            }
+            i += 1;
+            tokens.push(Token::Str(text));
+        } else if c == '\'' {
+            i += 1;
+            while i < chars.len() && chars[i] != '\'' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i += 1;
+        } else if c.is_alphabetic() || c == '_' || c == '@' {
+            let start = if c == '@' { i + 1 } else { i };
+            i += 1;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.' || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Number(chars[start..i].iter().collect()));
+        } else {
+            tokens.push(Token::Punct(c));
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+/// A C# type as written in the source
+#[derive(Debug, Clone)]
+enum CsType {
+    Named { name: String, args: Vec<CsType> },
+    Nullable(Box<CsType>),
+    Array(Box<CsType>),
+    Tuple,
+}
+
+/// An attribute such as `[Key(0)]`, with its arguments as token lists
+#[derive(Debug)]
+struct Attribute {
+    name: String,
+    args: Vec<Vec<Token>>,
+}
+
+impl Attribute {
+    fn is(&self, name: &str) -> bool {
+        self.name == name || self.name.strip_suffix("Attribute") == Some(name)
+    }
+}
+
+/// A member with the attributes and modifiers in front of it
+#[derive(Debug)]
+struct Member {
+    name: String,
+    cs_type: CsType,
+    key: Option<FieldKey>,
+    ignored: bool,
+    public: bool,
+}
+
+/// A class, struct, record or interface; only those marked `[MessagePackObject]` become contracts
+#[derive(Debug, Default)]
+struct Contract {
+    is_contract: bool,
+    key_as_property_name: bool,
+    base: Option<String>,
+    members: Vec<Member>,
+}
+
+#[derive(Debug, Default)]
+struct Declarations {
+    classes: BTreeMap<String, Contract>,
+    unions: BTreeMap<String, Vec<UnionCase>>,
+    enums: BTreeSet<String>,
+}
+
+impl Declarations {
+    fn into_schema(self, root: Option<&str>) -> Result<Schema> {
+        let mut types = BTreeMap::new();
+
+        for (name, contract) in self.classes.iter().filter(|(_, contract)| contract.is_contract) {
+            let mut members = Vec::new();
+            self.collect_members(name, &mut members, 0);
+
+            let fields = members.into_iter()
+                .filter(|member| !member.ignored)
+                .filter_map(|member| {
+                    let key = match (&member.key, contract.key_as_property_name) {
+                        (Some(key), _) => key.clone(),
+                        (None, true) if member.public => FieldKey::Name(member.name.clone()),
+                        (None, _) => return None,
+                    };
+                    Some(Field { key, name: member.name.clone(), field_type: self.resolve(&member.cs_type) })
+                })
+                .collect();
+            types.insert(name.clone(), TypeDef::Object { fields });
+        }
+        for (name, cases) in self.unions {
+            // A [Union] base class describes its cases rather than its own members
+            types.insert(name, TypeDef::Union { cases });
+        }
+
+        let schema = Schema { root: None, types };
+        match root {
+            Some(root) => schema.with_root(root),
+            None => schema.validate().map(|_| schema),
+        }
+    }
+
+    /// Members of a contract, after those of the contracts it derives from
+    fn collect_members<'a>(&'a self, name: &str, members: &mut Vec<&'a Member>, depth: usize) {
+        let Some(contract) = self.classes.get(name) else { return };
+        if depth < 32 {
+            if let Some(base) = &contract.base {
+                self.collect_members(base, members, depth + 1);
+            }
+        }
+        members.extend(&contract.members);
+    }
+
+    fn resolve(&self, cs_type: &CsType) -> FieldType {
+        match cs_type {
+            CsType::Nullable(inner) => FieldType::Nullable(Box::new(self.resolve(inner))),
+            CsType::Array(item) => match item.as_ref() {
+                CsType::Named { name, .. } if name == "byte" || name == "Byte" => FieldType::Binary,
+                item => FieldType::Array(Box::new(self.resolve(item))),
+            },
+            CsType::Tuple => FieldType::Any,
+            CsType::Named { name, args } => self.resolve_named(name, args),
+        }
+    }
+
+    fn resolve_named(&self, name: &str, args: &[CsType]) -> FieldType {
+        match (name, args) {
+            ("bool" | "Boolean", []) => FieldType::Bool,
+            ("sbyte" | "byte" | "short" | "ushort" | "int" | "uint" | "long" | "ulong" | "char"
+                | "SByte" | "Byte" | "Int16" | "UInt16" | "Int32" | "UInt32" | "Int64" | "UInt64" | "Char", []) => {
+                FieldType::Int
+            },
+            ("float" | "double" | "Single" | "Double" | "Half", []) => FieldType::Float,
+            ("string" | "String", []) => FieldType::String,
+            ("DateTime", []) => FieldType::Timestamp,
+            ("Guid", []) => FieldType::Guid,
+            ("decimal" | "Decimal", []) => FieldType::Decimal,
+            ("TimeSpan", []) => FieldType::TimeSpan,
+            ("DateTimeOffset", []) => FieldType::DateTimeOffset,
+            ("Nullable", [inner]) => FieldType::Nullable(Box::new(self.resolve(inner))),
+            ("Memory" | "ReadOnlyMemory" | "ArraySegment", [CsType::Named { name, .. }]) if name == "byte" => {
+                FieldType::Binary
+            },
+            ("List" | "IList" | "IReadOnlyList" | "ICollection" | "IReadOnlyCollection" | "IEnumerable"
+                | "Collection" | "ReadOnlyCollection" | "HashSet" | "ISet" | "IReadOnlySet" | "SortedSet"
+                | "LinkedList" | "Queue" | "Stack" | "ImmutableArray" | "ImmutableList" | "ImmutableHashSet"
+                | "ObservableCollection", [item]) => FieldType::Array(Box::new(self.resolve(item))),
+            ("Dictionary" | "IDictionary" | "IReadOnlyDictionary" | "SortedDictionary" | "SortedList"
+                | "ConcurrentDictionary" | "ImmutableDictionary", [key, value]) => {
+                FieldType::Map(Box::new(self.resolve(key)), Box::new(self.resolve(value)))
+            },
+            (name, []) if self.unions.contains_key(name) || self.classes.get(name).is_some_and(|c| c.is_contract) => {
+                FieldType::Named(name.to_string())
+            },
+            (name, []) if self.enums.contains(name) => FieldType::Int,
+            _ => FieldType::Any,
+        }
+    }
+}
+
+const MODIFIERS: &[&str] = &[
+    "public", "private", "protected", "internal", "static", "readonly", "virtual", "override", "abstract",
+    "sealed", "partial", "required", "new", "const", "volatile", "extern", "unsafe", "async", "file", "init",
+];
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>) -> Self {
+        Parser { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn is_punct(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Punct(c))
+    }
+
+    fn eat_punct(&mut self, c: char) -> bool {
+        let found = self.is_punct(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn peek_ident(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Ident(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Skip a balanced group starting at the current opening bracket
+    fn skip_group(&mut self) {
+        let (open, close) = match self.peek() {
+            Some(Token::Punct('(')) => ('(', ')'),
+            Some(Token::Punct('[')) => ('[', ']'),
+            Some(Token::Punct('{')) => ('{', '}'),
+            Some(Token::Punct('<')) => ('<', '>'),
+            _ => return,
+        };
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            if token == Token::Punct(open) {
+                depth += 1;
+            } else if token == Token::Punct(close) {
+                depth -= 1;
+                if depth == 0 {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Skip to the end of the current statement: past a `;` or a `{ ... }` block
+    fn skip_statement(&mut self) {
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Punct(';') => {
+                    self.pos += 1;
+                    return;
+                },
+                Token::Punct('{') => {
+                    self.skip_group();
+                    // Property initializers follow the accessors: `{ get; set; } = new();`
+                    if self.is_punct('=') {
+                        continue;
+                    }
+                    return;
+                },
+                Token::Punct('(') | Token::Punct('[') => self.skip_group(),
+                Token::Punct('}') => return,
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// Parse declarations until the end of the enclosing block
+    fn parse_members(&mut self, declarations: &mut Declarations, mut owner: Option<&mut Contract>) {
+        loop {
+            let attributes = self.parse_attributes();
+            let mut modifiers = Vec::new();
+            while let Some(name) = self.peek_ident() {
+                if !MODIFIERS.contains(&name) {
+                    break;
+                }
+                modifiers.push(name.to_string());
+                self.pos += 1;
+            }
+
+            match self.peek() {
+                None => return,
+                Some(Token::Punct('}')) => {
+                    self.pos += 1;
+                    return;
+                },
+                Some(Token::Punct('{')) => self.skip_group(),
+                Some(Token::Ident(word)) => match word.as_str() {
+                    "namespace" => {
+                        while !self.is_punct('{') && !self.is_punct(';') && self.peek().is_some() {
+                            self.pos += 1;
+                        }
+                        if self.eat_punct('{') {
+                            self.parse_members(declarations, None);
+                        } else {
+                            self.pos += 1;
+                        }
+                    },
+                    "using" => self.skip_statement(),
+                    "class" | "struct" | "interface" | "record" | "enum" => {
+                        self.parse_type_declaration(declarations, &attributes);
+                    },
+                    _ => self.parse_owned_member(owner.as_deref_mut(), &attributes, &modifiers),
+                },
+                // Tuple-typed members: `public (int, int) Size { get; set; }`
+                Some(Token::Punct('(')) => self.parse_owned_member(owner.as_deref_mut(), &attributes, &modifiers),
+                Some(_) => self.skip_statement(),
+            }
+        }
+    }
+
+    fn parse_attributes(&mut self) -> Vec<Attribute> {
+        let mut attributes = Vec::new();
+        while self.eat_punct('[') {
+            // Attribute target such as `property:` in record parameters
+            if matches!(self.peek_at(1), Some(Token::Punct(':'))) && !matches!(self.peek_at(2), Some(Token::Punct(':'))) {
+                self.pos += 2;
+            }
+            while let Some(name) = self.parse_qualified_name() {
+                let mut args = Vec::new();
+                if self.is_punct('(') {
+                    let start = self.pos + 1;
+                    self.skip_group();
+                    args = split_arguments(&self.tokens[start..self.pos - 1]);
+                }
+                attributes.push(Attribute { name, args });
+                if !self.eat_punct(',') {
+                    break;
+                }
+            }
+            while !self.eat_punct(']') && self.peek().is_some() {
+                self.pos += 1;
+            }
+        }
+        attributes
+    }
+
+    /// Last segment of a possibly qualified name such as `global::MessagePack.Key`
+    fn parse_qualified_name(&mut self) -> Option<String> {
+        let mut name = match self.next() {
+            Some(Token::Ident(name)) => name,
+            _ => {
+                self.pos -= 1;
+                return None;
+            },
+        };
+        loop {
+            if self.is_punct('.') && matches!(self.peek_at(1), Some(Token::Ident(_))) {
+                self.pos += 1;
+            } else if self.is_punct(':') && matches!(self.peek_at(1), Some(Token::Punct(':'))) {
+                self.pos += 2;
+            } else {
+                return Some(name);
+            }
+            if let Some(Token::Ident(segment)) = self.next() {
+                name = segment;
+            }
+        }
+    }
+
+    fn parse_type(&mut self) -> Option<CsType> {
+        let mut cs_type = if self.is_punct('(') {
+            self.skip_group();
+            CsType::Tuple
+        } else {
+            let name = self.parse_qualified_name()?;
+            let mut args = Vec::new();
+            if self.eat_punct('<') {
+                loop {
+                    args.push(self.parse_type()?);
+                    if self.eat_punct('>') {
+                        break;
+                    }
+                    if !self.eat_punct(',') {
+                        return None;
+                    }
+                }
+            }
+            CsType::Named { name, args }
+        };
+
+        loop {
+            if self.eat_punct('?') {
+                cs_type = CsType::Nullable(Box::new(cs_type));
+            } else if self.is_punct('[') && matches!(self.peek_at(1), Some(Token::Punct(']')) | Some(Token::Punct(','))) {
+                self.skip_group();
+                cs_type = CsType::Array(Box::new(cs_type));
+            } else {
+                return Some(cs_type);
+            }
+        }
+    }
+
+    fn parse_type_declaration(&mut self, declarations: &mut Declarations, attributes: &[Attribute]) {
+        let kind = self.peek_ident().unwrap_or_default().to_string();
+        self.pos += 1;
+        if kind == "record" && matches!(self.peek_ident(), Some("class") | Some("struct")) {
+            self.pos += 1;
+        }
+        let Some(Token::Ident(name)) = self.next() else { return };
+        if self.is_punct('<') {
+            self.skip_group();
+        }
+
+        if kind == "enum" {
+            declarations.enums.insert(name);
+            self.skip_statement();
+            return;
+        }
+
+        let cases: Vec<UnionCase> = attributes.iter().filter(|a| a.is("Union")).filter_map(union_case).collect();
+        let mut contract = Contract {
+            is_contract: attributes.iter().any(|a| a.is("MessagePackObject")),
+            key_as_property_name: attributes.iter().any(|a| a.is("MessagePackObject") && key_as_property_name(a)),
+            ..Contract::default()
+        };
+
+        // Record primary constructor: `record Point([property: Key(0)] int X, [property: Key(1)] int Y)`
+        if self.eat_punct('(') {
+            while !self.eat_punct(')') && self.peek().is_some() {
+                let attributes = self.parse_attributes();
+                if let Some(member) = self.parse_member_head(&attributes, true) {
+                    contract.members.push(member);
+                }
+                // Skip a default value up to the next parameter
+                while !self.is_punct(',') && !self.is_punct(')') && self.peek().is_some() {
+                    if self.is_punct('(') || self.is_punct('[') || self.is_punct('{') {
+                        self.skip_group();
+                    } else {
+                        self.pos += 1;
+                    }
+                }
+                self.eat_punct(',');
+            }
+        }
+
+        // Base class, then interfaces and constraints
+        if self.eat_punct(':') {
+            if let Some(CsType::Named { name, .. }) = self.parse_type() {
+                contract.base = Some(name);
+            }
+        }
+        while !self.is_punct('{') && !self.is_punct(';') && self.peek().is_some() {
+            self.pos += 1;
+        }
+
+        if self.eat_punct('{') {
+            self.parse_members(declarations, Some(&mut contract));
+        } else {
+            self.pos += 1;
+        }
+
+        if !cases.is_empty() {
+            declarations.unions.entry(name.clone()).or_default().extend(cases);
+        }
+        // Partial classes add their members to the same declaration
+        let existing = declarations.classes.entry(name).or_default();
+        existing.is_contract |= contract.is_contract;
+        existing.key_as_property_name |= contract.key_as_property_name;
+        existing.base = existing.base.take().or(contract.base);
+        existing.members.extend(contract.members);
+    }
+
+    fn parse_owned_member(&mut self, owner: Option<&mut Contract>, attributes: &[Attribute], modifiers: &[String]) {
+        match owner {
+            Some(contract) => {
+                if let Some(member) = self.parse_member(attributes, modifiers) {
+                    contract.members.push(member);
+                }
+            },
+            None => self.skip_statement(),
+        }
+    }
+
+    /// A property or field; methods, constructors, events and the like are skipped
+    fn parse_member(&mut self, attributes: &[Attribute], modifiers: &[String]) -> Option<Member> {
+        if modifiers.iter().any(|m| m == "static" || m == "const")
+            || matches!(self.peek_ident(), Some("event") | Some("delegate") | Some("operator") | Some("implicit") | Some("explicit"))
+        {
+            self.skip_statement();
+            return None;
+        }
+
+        let start = self.pos;
+        let public = modifiers.iter().any(|m| m == "public");
+        let member = self.parse_member_head(attributes, public);
+        let is_member = member.is_some()
+            && matches!(self.peek(), Some(Token::Punct('{')) | Some(Token::Punct(';')) | Some(Token::Punct('=')) | Some(Token::Punct(',')));
+        if !is_member {
+            self.pos = start;
+        }
+        self.skip_statement();
+        member.filter(|_| is_member)
+    }
+
+    fn parse_member_head(&mut self, attributes: &[Attribute], public: bool) -> Option<Member> {
+        let cs_type = self.parse_type()?;
+        let Some(Token::Ident(name)) = self.next() else { return None };
+
+        let key = attributes.iter().find(|a| a.is("Key")).and_then(|a| match a.args.first()?.as_slice() {
+            [Token::Number(n)] => n.parse().ok().map(FieldKey::Index),
+            [Token::Str(s)] => Some(FieldKey::Name(s.clone())),
+            _ => None,
+        });
+        let ignored = attributes.iter().any(|a| a.is("IgnoreMember") || a.is("IgnoreDataMember"));
+        Some(Member { name, cs_type, key, ignored, public })
+    }
+}
+
+/// Split attribute arguments at top-level commas
+fn split_arguments(tokens: &[Token]) -> Vec<Vec<Token>> {
+    let mut args = vec![Vec::new()];
+    let mut depth = 0;
+    for token in tokens {
+        match token {
+            Token::Punct('(') | Token::Punct('[') | Token::Punct('{') => depth += 1,
+            Token::Punct(')') | Token::Punct(']') | Token::Punct('}') => depth -= 1,
+            Token::Punct(',') if depth == 0 => {
+                args.push(Vec::new());
+                continue;
+            },
+            _ => {},
+        }
+        if let Some(arg) = args.last_mut() {
+            arg.push(token.clone());
+        }
+    }
+    args.retain(|arg| !arg.is_empty());
+    args
+}
+
+/// `[MessagePackObject(true)]` or `[MessagePackObject(keyAsPropertyName: true)]`
+fn key_as_property_name(attribute: &Attribute) -> bool {
+    attribute.args.first().is_some_and(|arg| matches!(arg.last(), Some(Token::Ident(value)) if value == "true"))
+}
+
+/// `[Union(0, typeof(CarRoute))]`
+fn union_case(attribute: &Attribute) -> Option<UnionCase> {
+    let key = match attribute.args.first()?.as_slice() {
+        [Token::Number(n)] => n.parse().ok()?,
+        [Token::Punct('-'), Token::Number(n)] => -n.parse::<i64>().ok()?,
+        _ => return None,
+    };
+    // The last identifier inside typeof(...) is the case's simple name
+    let type_name = attribute.args.get(1)?.iter().rev().find_map(|token| match token {
+        Token::Ident(name) if name != "typeof" => Some(name.clone()),
+        _ => None,
+    })?;
+    Some(UnionCase { key, type_name })
+}
@@ -0,0 +1,698 @@
+use rmpv::Value;
+use rmpv::decode::read_value;
+use rmp::encode::{write_array_len, write_ext_meta, write_uint};
+use serde_json::{json, Value as JsonValue};
+use lz4::block::{compress, decompress_to_buffer};
+use std::io::Cursor;
+
+use super::{Compression, Error, Limits, Result, LZ4_BLOCK_ARRAY_EXT, LZ4_BLOCK_EXT, LZ4_BLOCK_SIZE};
+
+/// A MessagePack-CSharp LZ4 envelope: the extension header followed by its `bin` blocks
+///
+/// For `Lz4BlockArray` (ext 98) the header holds one uncompressed length per
+/// block. For `Lz4Block` (ext 99) the header holds the length and the LZ4
+/// block itself, and no blocks follow it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope {
+    ext_type: i8,
+    header: Vec<u8>,
+    blocks: Vec<Vec<u8>>,
+}
+
+impl Envelope {
+    /// Extension type code of the envelope
+    pub fn ext_type(&self) -> i8 {
+        self.ext_type
+    }
+
+    /// Raw bytes of the extension payload
+    pub fn header(&self) -> &[u8] {
+        &self.header
+    }
+
+    /// Compressed `bin` blocks following the extension
+    pub fn blocks(&self) -> &[Vec<u8>] {
+        &self.blocks
+    }
+
+    /// Total size of the compressed blocks
+    pub fn compressed_len(&self) -> usize {
+        self.blocks.iter().map(Vec::len).sum()
+    }
+
+    /// Parse the Node-style JSON form: `[{"type": 98, "buffer": {...}}, {"type": "Buffer", "data": [...]}, ...]`
+    ///
+    /// Each extension object starts a new envelope and owns every `Buffer`
+    /// element that follows it, up to the next extension object. A lone
+    /// extension object (the `Lz4Block` shape) is accepted as well.
+    pub fn parse_json(json: &JsonValue) -> Result<Vec<Envelope>> {
+        let elements = match json {
+            JsonValue::Array(items) => items.as_slice(),
+            JsonValue::Object(_) => std::slice::from_ref(json),
+            _ => return Err(Error::InvalidEnvelope {
+                index: None,
+                message: "Expected a JSON array or extension object".to_string(),
+            }),
+        };
+
+        let mut result: Vec<Envelope> = Vec::new();
+
+        for (index, element) in elements.iter().enumerate() {
+            if let Some(ext_type) = element.get("type").and_then(|t| t.as_i64()) {
+                // Extension header: starts a new envelope
+                let ext_type = i8::try_from(ext_type)
+                    .map_err(|_| Error::envelope(index, format!("Extension type {} is out of range", ext_type)))?;
+                let buffer = element.get("buffer")
+                    .ok_or_else(|| Error::envelope(index, "Missing buffer in extension"))?;
+                let header = extract_byte_array(&buffer["data"])
+                    .map_err(|e| Error::envelope(index, format!("Invalid extension header: {}", e)))?;
+
+                result.push(Envelope {
+                    ext_type,
+                    header,
+                    blocks: Vec::new(),
+                });
+            } else if let Some(data_array) = element.get("data") {
+                // Binary element: belongs to the most recent extension header
+                let envelope = result.last_mut()
+                    .ok_or_else(|| Error::envelope(index, "Buffer is not preceded by an extension header"))?;
+                let data = extract_byte_array(data_array)
+                    .map_err(|e| Error::envelope(index, format!("Invalid buffer: {}", e)))?;
+                envelope.blocks.push(data);
+            } else {
+                return Err(Error::envelope(index, "Unrecognized element: expected an extension header or a buffer"));
+            }
+        }
+
+        if result.is_empty() {
+            return Err(Error::InvalidEnvelope {
+                index: None,
+                message: "No valid LZ4 blocks found in input".to_string(),
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// Parse a decoded MessagePack value shaped like `[ext, bin, bin, ...]` or a lone `ext`
+    pub fn parse_value(value: &Value) -> Result<Vec<Envelope>> {
+        let items = match value {
+            Value::Array(items) => items.as_slice(),
+            Value::Ext(..) => std::slice::from_ref(value),
+            _ => return Err(Error::InvalidEnvelope {
+                index: None,
+                message: "Expected a MessagePack array or extension".to_string(),
+            }),
+        };
+
+        let mut result: Vec<Envelope> = Vec::new();
+
+        for (index, item) in items.iter().enumerate() {
+            match item {
+                Value::Ext(ext_type, header) => result.push(Envelope {
+                    ext_type: *ext_type,
+                    header: header.clone(),
+                    blocks: Vec::new(),
+                }),
+                Value::Binary(data) => {
+                    let envelope = result.last_mut()
+                        .ok_or_else(|| Error::envelope(index, "Binary is not preceded by an extension header"))?;
+                    envelope.blocks.push(data.clone());
+                },
+                _ => return Err(Error::envelope(index, "Unexpected MessagePack value: expected ext or bin")),
+            }
+        }
+
+        if result.is_empty() {
+            return Err(Error::InvalidEnvelope {
+                index: None,
+                message: "No valid LZ4 blocks found in input".to_string(),
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// Check whether binary MessagePack data starts like an LZ4 envelope
+    ///
+    /// Only the leading markers are inspected: either an array whose first
+    /// element is an ext 98, or a lone ext 99. Uncompressed MessagePack
+    /// returns `false`.
+    pub fn detect(data: &[u8]) -> bool {
+        let ext_start = match data.first() {
+            Some(0x90..=0x9f) => 1,
+            Some(0xdc) => 3,
+            Some(0xdd) => 5,
+            _ => return ext_type_at(data, 0) == Some(LZ4_BLOCK_EXT),
+        };
+        ext_type_at(data, ext_start) == Some(LZ4_BLOCK_ARRAY_EXT)
+    }
+
+    /// Parse the binary MessagePack form of a single envelope
+    pub fn parse_msgpack(data: &[u8]) -> Result<Envelope> {
+        let mut cursor = Cursor::new(data);
+        let value = read_value(&mut cursor)
+            .map_err(|e| Error::InvalidMessagePack { offset: cursor.position(), message: e.to_string() })?;
+
+        if (cursor.position() as usize) < data.len() {
+            return Err(Error::TrailingData { offset: cursor.position() });
+        }
+
+        let mut envelopes = Self::parse_value(&value)?;
+        if envelopes.len() != 1 {
+            return Err(Error::InvalidEnvelope {
+                index: None,
+                message: format!("Expected a single LZ4 envelope, found {}", envelopes.len()),
+            });
+        }
+
+        Ok(envelopes.remove(0))
+    }
+
+    /// Compress a MessagePack stream into an envelope using the given mode
+    pub fn compress(msgpack: &[u8], compression: Compression) -> Result<Envelope> {
+        match compression {
+            Compression::Lz4BlockArray => {
+                // Split into chunks and compress each one as an independent LZ4 block; an empty
+                // stream is a single zero-length block, since a header without lengths is invalid
+                let mut header = Vec::new();
+                let mut blocks = Vec::new();
+                for chunk in msgpack.chunks(LZ4_BLOCK_SIZE).chain(msgpack.is_empty().then_some(msgpack)) {
+                    write_uint(&mut header, chunk.len() as u64)
+                        .map_err(|e| Error::Encode(format!("Failed to write Lz4BlockArray header: {}", e)))?;
+                    blocks.push(compress_block(chunk)?);
+                }
+
+                Ok(Envelope {
+                    ext_type: LZ4_BLOCK_ARRAY_EXT,
+                    header,
+                    blocks,
+                })
+            },
+            Compression::Lz4Block => {
+                let length = i32::try_from(msgpack.len())
+                    .map_err(|_| Error::Encode(format!("Payload of {} bytes is too large for an LZ4 block", msgpack.len())))?;
+
+                // The payload is the length as a MessagePack int32 followed by the LZ4 block
+                let mut header = vec![0xd2];
+                header.extend_from_slice(&length.to_be_bytes());
+                header.extend_from_slice(&compress_block(msgpack)?);
+
+                Ok(Envelope {
+                    ext_type: LZ4_BLOCK_EXT,
+                    header,
+                    blocks: Vec::new(),
+                })
+            },
+        }
+    }
+
+    /// Uncompressed length of every block, as declared by the extension header
+    pub fn block_lengths(&self) -> Result<Vec<usize>> {
+        match self.ext_type {
+            LZ4_BLOCK_ARRAY_EXT => read_block_lengths(&self.header),
+            LZ4_BLOCK_EXT => read_lz4_block(&self.header).map(|(length, _)| vec![length]),
+            other => Err(Error::UnsupportedExtension(other)),
+        }
+    }
+
+    /// Decompress the envelope into the original MessagePack stream, within the default [`Limits`]
+    pub fn decompress(&self) -> Result<Vec<u8>> {
+        self.decompress_with_limits(&Limits::default())
+    }
+
+    /// Decompress the envelope, checking the lengths its header declares against `limits` first
+    pub fn decompress_with_limits(&self, limits: &Limits) -> Result<Vec<u8>> {
+        match self.ext_type {
+            LZ4_BLOCK_ARRAY_EXT => {
+                let lengths = self.checked_block_lengths(limits)?;
+                let mut output = vec![0; lengths.iter().sum()];
+                for (index, (slice, block)) in split_blocks(&mut output, &lengths).into_iter().zip(&self.blocks).enumerate() {
+                    decompress_block_into(index, block, slice)?;
+                }
+
+                Ok(output)
+            },
+            LZ4_BLOCK_EXT => {
+                // The length prefix and the LZ4 block both live inside the extension
+                if !self.blocks.is_empty() {
+                    return Err(Error::InvalidEnvelope {
+                        index: None,
+                        message: format!(
+                            "Lz4Block extension must stand alone but {} binary block(s) follow it",
+                            self.blocks.len()
+                        ),
+                    });
+                }
+                let (length, block) = read_lz4_block(&self.header)?;
+                limits.check_blocks(&[length])?;
+                decompress_block(0, length, block)
+            },
+            other => Err(Error::UnsupportedExtension(other)),
+        }
+    }
+
+    /// Decompress the blocks of an `Lz4BlockArray` envelope on all cores
+    ///
+    /// Every block is decompressed straight into its own slice of the output,
+    /// whose size is known from the header. The result is the same as
+    /// [`decompress`](Self::decompress), including the error: when several
+    /// blocks are broken, the first one is reported.
+    #[cfg(feature = "parallel")]
+    pub fn decompress_parallel(&self) -> Result<Vec<u8>> {
+        self.decompress_parallel_with_limits(&Limits::default())
+    }
+
+    /// Decompress on all cores, checking the lengths the header declares against `limits` first
+    #[cfg(feature = "parallel")]
+    pub fn decompress_parallel_with_limits(&self, limits: &Limits) -> Result<Vec<u8>> {
+        use rayon::prelude::*;
+
+        if self.ext_type != LZ4_BLOCK_ARRAY_EXT {
+            return self.decompress_with_limits(limits);
+        }
+
+        let lengths = self.checked_block_lengths(limits)?;
+        let mut output = vec![0; lengths.iter().sum()];
+        let results: Vec<Result<()>> = split_blocks(&mut output, &lengths)
+            .into_par_iter()
+            .zip(self.blocks.par_iter())
+            .enumerate()
+            .map(|(index, (slice, block))| decompress_block_into(index, block, slice))
+            .collect();
+        results.into_iter().collect::<Result<()>>()?;
+
+        Ok(output)
+    }
+
+    /// Decompress on all cores when `parallel` is set and the crate is built with the `parallel` feature
+    pub(crate) fn decompress_with(&self, parallel: bool, limits: &Limits) -> Result<Vec<u8>> {
+        match parallel {
+            #[cfg(feature = "parallel")]
+            true => self.decompress_parallel_with_limits(limits),
+            _ => self.decompress_with_limits(limits),
+        }
+    }
+
+    /// Block lengths from the ext 98 header, checked against the number of blocks and the limits
+    fn checked_block_lengths(&self, limits: &Limits) -> Result<Vec<usize>> {
+        let lengths = read_block_lengths(&self.header)?;
+        if lengths.len() != self.blocks.len() {
+            return Err(Error::BlockCountMismatch {
+                declared: lengths.len(),
+                found: self.blocks.len(),
+            });
+        }
+        limits.check_blocks(&lengths)?;
+        Ok(lengths)
+    }
+
+    /// Convert the envelope back into a MessagePack value
+    pub fn to_value(&self) -> Value {
+        let ext_value = Value::Ext(self.ext_type, self.header.clone());
+
+        // Lz4Block is a lone extension; otherwise the extension header comes
+        // first, followed by one binary per block
+        if self.ext_type == LZ4_BLOCK_EXT {
+            return ext_value;
+        }
+
+        let mut items = Vec::with_capacity(self.blocks.len() + 1);
+        items.push(ext_value);
+        items.extend(self.blocks.iter().map(|block| Value::Binary(block.clone())));
+        Value::Array(items)
+    }
+
+    /// Serialize the envelope to MessagePack exactly as MessagePack-CSharp lays it out
+    ///
+    /// The extension header uses the compact format, blocks are always
+    /// written as `bin32` and the `Lz4Block` extension is always an `ext32`.
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(self.header.len() + self.compressed_len() + 16);
+
+        if self.ext_type == LZ4_BLOCK_EXT {
+            output.push(0xc9);
+            output.extend_from_slice(&len_u32(self.header.len())?.to_be_bytes());
+            output.push(self.ext_type as u8);
+            output.extend_from_slice(&self.header);
+            return Ok(output);
+        }
+
+        write_array_len(&mut output, len_u32(self.blocks.len() + 1)?)
+            .map_err(|e| Error::Encode(format!("Failed to write envelope array: {}", e)))?;
+        write_ext_meta(&mut output, len_u32(self.header.len())?, self.ext_type)
+            .map_err(|e| Error::Encode(format!("Failed to write extension header: {}", e)))?;
+        output.extend_from_slice(&self.header);
+
+        for block in &self.blocks {
+            output.push(0xc6);
+            output.extend_from_slice(&len_u32(block.len())?.to_be_bytes());
+            output.extend_from_slice(block);
+        }
+
+        Ok(output)
+    }
+
+    /// Convert the envelope to the Node-style JSON form with `Buffer` objects
+    pub fn to_json(&self) -> JsonValue {
+        let ext_json = json!({
+            "buffer": {
+                "type": "Buffer",
+                "data": self.header
+            },
+            "type": self.ext_type
+        });
+
+        if self.ext_type == LZ4_BLOCK_EXT {
+            return ext_json;
+        }
+
+        let mut elements = Vec::with_capacity(self.blocks.len() + 1);
+        elements.push(ext_json);
+        for block in &self.blocks {
+            elements.push(json!({
+                "type": "Buffer",
+                "data": block
+            }));
+        }
+        JsonValue::Array(elements)
+    }
+}
+
+/// Type code of the MessagePack extension starting at `pos`, if there is one
+fn ext_type_at(data: &[u8], pos: usize) -> Option<i8> {
+    let type_offset = match *data.get(pos)? {
+        0xd4..=0xd8 => 1,
+        0xc7 => 2,
+        0xc8 => 3,
+        0xc9 => 5,
+        _ => return None,
+    };
+    data.get(pos + type_offset).map(|&b| b as i8)
+}
+
+/// A binary envelope parsed in place: the header and blocks borrow the input
+///
+/// Used to decode into caller-provided buffers without copying the blocks
+/// out of the input the way [`Envelope::parse_msgpack`] does.
+pub(crate) struct BorrowedEnvelope<'a> {
+    ext_type: i8,
+    header: &'a [u8],
+    /// The `bin` elements that follow the extension, still framed
+    blocks: &'a [u8],
+    block_count: usize,
+}
+
+impl<'a> BorrowedEnvelope<'a> {
+    /// Parse the binary form of a single envelope, `[ext, bin, ...]` or a lone `ext`
+    pub(crate) fn parse(data: &'a [u8]) -> Result<Self> {
+        let mut reader = SliceReader { data, offset: 0 };
+        let marker = reader.read_u8()?;
+        let count = match marker {
+            0x90..=0x9f => usize::from(marker & 0x0f),
+            0xdc => reader.read_uint(2)?,
+            0xdd => reader.read_uint(4)?,
+            _ => {
+                let (ext_type, header) = reader.read_ext(marker)?.ok_or_else(|| Error::InvalidEnvelope {
+                    index: None,
+                    message: "Expected a MessagePack array or extension".to_string(),
+                })?;
+                reader.finish()?;
+                return Ok(BorrowedEnvelope { ext_type, header, blocks: &[], block_count: 0 });
+            },
+        };
+
+        if count == 0 {
+            return Err(Error::InvalidEnvelope { index: None, message: "No valid LZ4 blocks found in input".to_string() });
+        }
+        let marker = reader.read_u8()?;
+        let (ext_type, header) = reader.read_ext(marker)?.ok_or_else(|| unexpected_element(0, marker))?;
+
+        // Check the framing of every block now, so iterating them later cannot fail
+        let start = reader.offset;
+        for index in 1..count {
+            let marker = reader.read_u8()?;
+            reader.read_bin(marker)?.ok_or_else(|| unexpected_element(index, marker))?;
+        }
+        let blocks = &data[start..reader.offset];
+        reader.finish()?;
+
+        Ok(BorrowedEnvelope { ext_type, header, blocks, block_count: count - 1 })
+    }
+
+    /// Exact length of the MessagePack stream, from the header alone, checked against `limits`
+    pub(crate) fn decoded_len(&self, limits: &Limits) -> Result<usize> {
+        match self.ext_type {
+            LZ4_BLOCK_ARRAY_EXT => {
+                let mut total = 0usize;
+                let mut declared = 0;
+                for length in block_lengths(self.header)? {
+                    let length = length?;
+                    limits.check_block_len(length)?;
+                    total = total.saturating_add(length);
+                    declared += 1;
+                }
+                if declared != self.block_count {
+                    return Err(Error::BlockCountMismatch { declared, found: self.block_count });
+                }
+                limits.check_block_count(declared)?;
+                limits.check_uncompressed_len(total)?;
+                Ok(total)
+            },
+            LZ4_BLOCK_EXT => {
+                if self.block_count > 0 {
+                    return Err(Error::InvalidEnvelope {
+                        index: None,
+                        message: format!(
+                            "Lz4Block extension must stand alone but {} binary block(s) follow it",
+                            self.block_count
+                        ),
+                    });
+                }
+                let (length, _) = read_lz4_block(self.header)?;
+                limits.check_blocks(&[length])?;
+                Ok(length)
+            },
+            other => Err(Error::UnsupportedExtension(other)),
+        }
+    }
+
+    /// Decompress every block straight into the start of `output`, returning the decoded length
+    pub(crate) fn decompress_into(&self, output: &mut [u8], limits: &Limits) -> Result<usize> {
+        let required = self.decoded_len(limits)?;
+        let available = output.len();
+        let output = output.get_mut(..required).ok_or(Error::OutputTooSmall { required, available })?;
+
+        if self.ext_type == LZ4_BLOCK_EXT {
+            let (_, block) = read_lz4_block(self.header)?;
+            decompress_block_into(0, block, output)?;
+            return Ok(required);
+        }
+
+        let mut blocks = SliceReader { data: self.blocks, offset: 0 };
+        let mut position = 0;
+        for (index, length) in block_lengths(self.header)?.enumerate() {
+            let length = length?;
+            let marker = blocks.read_u8()?;
+            let block = blocks.read_bin(marker)?.ok_or_else(|| unexpected_element(index + 1, marker))?;
+            decompress_block_into(index, block, &mut output[position..position + length])?;
+            position += length;
+        }
+
+        Ok(required)
+    }
+}
+
+/// Error for an element of `[ext, bin, ...]` that is in the wrong place or of the wrong type
+fn unexpected_element(index: usize, marker: u8) -> Error {
+    let message = match marker {
+        0xc4..=0xc6 => "Binary is not preceded by an extension header",
+        0xc7..=0xc9 | 0xd4..=0xd8 => "Expected a single LZ4 envelope, found another extension",
+        _ => "Unexpected MessagePack value: expected ext or bin",
+    };
+    Error::envelope(index, message)
+}
+
+/// Reads MessagePack markers from a borrowed buffer without copying the data they frame
+struct SliceReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> SliceReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self.offset.checked_add(len)
+            .and_then(|end| self.data.get(self.offset..end))
+            .ok_or_else(|| Error::InvalidMessagePack {
+                offset: self.data.len() as u64,
+                message: format!("unexpected end of data, {} more byte(s) needed", len),
+            })?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    /// Read a big-endian unsigned integer of `size` bytes
+    fn read_uint(&mut self, size: usize) -> Result<usize> {
+        let mut bytes = [0; 4];
+        bytes[4 - size..].copy_from_slice(self.take(size)?);
+        Ok(u32::from_be_bytes(bytes) as usize)
+    }
+
+    /// Type and data of an extension starting with `marker`, or `None` when it is not an extension
+    fn read_ext(&mut self, marker: u8) -> Result<Option<(i8, &'a [u8])>> {
+        let len = match marker {
+            0xd4 => 1,
+            0xd5 => 2,
+            0xd6 => 4,
+            0xd7 => 8,
+            0xd8 => 16,
+            0xc7 => self.read_uint(1)?,
+            0xc8 => self.read_uint(2)?,
+            0xc9 => self.read_uint(4)?,
+            _ => return Ok(None),
+        };
+        let ext_type = self.read_u8()? as i8;
+        Ok(Some((ext_type, self.take(len)?)))
+    }
+
+    /// Data of a `bin` starting with `marker`, or `None` when it is not a `bin`
+    fn read_bin(&mut self, marker: u8) -> Result<Option<&'a [u8]>> {
+        let len = match marker {
+            0xc4 => self.read_uint(1)?,
+            0xc5 => self.read_uint(2)?,
+            0xc6 => self.read_uint(4)?,
+            _ => return Ok(None),
+        };
+        self.take(len).map(Some)
+    }
+
+    fn finish(&self) -> Result<()> {
+        match self.offset < self.data.len() {
+            true => Err(Error::TrailingData { offset: self.offset as u64 }),
+            false => Ok(()),
+        }
+    }
+}
+
+/// The bytes of a lone Node `Buffer` as `Buffer.toJSON()` writes it, `{"type": "Buffer", "data": [...]}`
+pub(crate) fn node_buffer(json: &JsonValue) -> Result<Option<Vec<u8>>> {
+    match (json.get("type").and_then(JsonValue::as_str), json.get("data")) {
+        (Some("Buffer"), Some(data)) => extract_byte_array(data)
+            .map(Some)
+            .map_err(|e| Error::InvalidEnvelope { index: None, message: format!("Invalid buffer: {}", e) }),
+        _ => Ok(None),
+    }
+}
+
+/// Helper function to extract a byte array from JSON
+fn extract_byte_array(json_array: &JsonValue) -> std::result::Result<Vec<u8>, &'static str> {
+    json_array.as_array()
+        .ok_or("Expected data to be an array")?
+        .iter()
+        .map(|v| {
+            v.as_u64()
+                .and_then(|n| u8::try_from(n).ok())
+                .ok_or("Expected data element to be a byte (0-255)")
+        })
+        .collect()
+}
+
+/// Read a MessagePack integer that must fit a non-negative C# `int`
+fn read_length(cursor: &mut Cursor<&[u8]>) -> Result<usize> {
+    let offset = cursor.position();
+    let value = read_value(cursor)
+        .map_err(|e| Error::InvalidHeader { offset, message: e.to_string() })?;
+
+    value.as_u64()
+        .filter(|&n| n <= i32::MAX as u64)
+        .map(|n| n as usize)
+        .ok_or_else(|| Error::InvalidHeader {
+            offset,
+            message: format!("expected a non-negative int32 length, found {}", value),
+        })
+}
+
+/// Read the uncompressed length of every LZ4 block from an ext 98 header
+///
+/// The header is a plain sequence of MessagePack integers, one per block.
+/// MessagePack-CSharp writes them in the compact format, but any integer
+/// encoding is accepted.
+pub(crate) fn read_block_lengths(header: &[u8]) -> Result<Vec<usize>> {
+    block_lengths(header)?.collect()
+}
+
+/// Iterate over the block lengths of an ext 98 header without collecting them
+fn block_lengths(header: &[u8]) -> Result<impl Iterator<Item = Result<usize>> + '_> {
+    if header.is_empty() {
+        return Err(Error::InvalidHeader { offset: 0, message: "Lz4BlockArray header is empty".to_string() });
+    }
+
+    let mut cursor = Cursor::new(header);
+    Ok(std::iter::from_fn(move || {
+        ((cursor.position() as usize) < header.len()).then(|| read_length(&mut cursor))
+    }))
+}
+
+/// Split an ext 99 payload into its uncompressed length and the raw LZ4 block
+///
+/// MessagePack-CSharp writes the length as a MessagePack int32 (`0xd2`),
+/// but any integer encoding is accepted.
+pub(crate) fn read_lz4_block(payload: &[u8]) -> Result<(usize, &[u8])> {
+    let mut cursor = Cursor::new(payload);
+    let length = read_length(&mut cursor)?;
+    Ok((length, &payload[cursor.position() as usize..]))
+}
+
+/// Decompress a single LZ4 block to exactly its declared length
+pub(crate) fn decompress_block(index: usize, length: usize, block: &[u8]) -> Result<Vec<u8>> {
+    let mut decompressed = vec![0; length];
+    decompress_block_into(index, block, &mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Decompress a single LZ4 block into `output`, which must be filled exactly
+pub(crate) fn decompress_block_into(index: usize, block: &[u8], output: &mut [u8]) -> Result<()> {
+    if block.is_empty() {
+        return Err(Error::CorruptBlock { block: index, message: "block is empty".to_string() });
+    }
+
+    let length = output.len();
+    let written = decompress_to_buffer(block, Some(length as i32), output)
+        .map_err(|e| Error::CorruptBlock { block: index, message: e.to_string() })?;
+
+    if written != length {
+        return Err(Error::BlockLengthMismatch {
+            block: index,
+            declared: length,
+            actual: written,
+        });
+    }
+
+    Ok(())
+}
+
+/// Split `output` into one slice per block, in order
+fn split_blocks<'a>(mut output: &'a mut [u8], lengths: &[usize]) -> Vec<&'a mut [u8]> {
+    lengths
+        .iter()
+        .map(|&length| {
+            let (block, rest) = std::mem::take(&mut output).split_at_mut(length);
+            output = rest;
+            block
+        })
+        .collect()
+}
+
+pub(crate) fn compress_block(data: &[u8]) -> Result<Vec<u8>> {
+    compress(data, None, false)
+        .map_err(|e| Error::Encode(format!("Failed to compress with LZ4: {}", e)))
+}
+
+pub(crate) fn len_u32(len: usize) -> Result<u32> {
+    u32::try_from(len)
+        .map_err(|_| Error::Encode(format!("Length {} does not fit a MessagePack 32-bit length", len)))
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+use std::io;
+
+use super::Limit;
+
+/// Everything that can go wrong while decoding or encoding an LZ4 envelope
+#[derive(Debug)]
+pub enum Error {
+    /// Reading the input or writing the output failed
+    Io { context: String, source: io::Error },
+    /// The input is not something this library can process (null pointer, invalid UTF-8, bad hex, ...)
+    InvalidInput(String),
+    /// The input is not valid JSON
+    InvalidJson(serde_json::Error),
+    /// A schema file is malformed or references a type it does not define
+    InvalidSchema(String),
+    /// The JSON or MessagePack envelope does not have the `[ext, bin, ...]` shape
+    ///
+    /// `index` is the position of the offending element, when there is one.
+    InvalidEnvelope { index: Option<usize>, message: String },
+    /// The extension header does not hold valid block lengths
+    ///
+    /// `offset` is the byte offset of the offending value within the header.
+    InvalidHeader { offset: u64, message: String },
+    /// The header declares a different number of blocks than the envelope holds
+    BlockCountMismatch { declared: usize, found: usize },
+    /// A block decompressed to a different size than its header declares
+    BlockLengthMismatch { block: usize, declared: usize, actual: usize },
+    /// A block is empty or is not valid LZ4 data
+    CorruptBlock { block: usize, message: String },
+    /// The envelope uses an extension type other than `Lz4BlockArray` or `Lz4Block`
+    UnsupportedExtension(i8),
+    /// The requested compression mode does not exist
+    UnsupportedCompression(i32),
+    /// The MessagePack data is truncated or malformed
+    ///
+    /// `offset` is the byte offset at which reading stopped.
+    InvalidMessagePack { offset: u64, message: String },
+    /// Bytes are left over after a complete envelope or value
+    TrailingData { offset: u64 },
+    /// Serializing or compressing the output failed
+    Encode(String),
+    /// A caller-provided buffer is shorter than the decoded data
+    ///
+    /// `required` is the exact length the envelope decodes to.
+    OutputTooSmall { required: usize, available: usize },
+    /// The input exceeds one of the configured [`Limits`](super::Limits)
+    LimitExceeded { limit: Limit, actual: usize, max: usize },
+}
+
+impl Error {
+    /// Stable numeric code for this error, used as the CLI exit code and by the FFI
+    ///
+    /// | Code | Errors |
+    /// |------|--------|
+    /// | 1    | `Io` |
+    /// | 2    | `InvalidInput`, `InvalidJson`, `InvalidSchema`, `InvalidEnvelope` |
+    /// | 3    | `InvalidHeader` |
+    /// | 4    | `BlockCountMismatch` |
+    /// | 5    | `BlockLengthMismatch` |
+    /// | 6    | `CorruptBlock` |
+    /// | 7    | `UnsupportedExtension` |
+    /// | 8    | `UnsupportedCompression` |
+    /// | 9    | `InvalidMessagePack`, `TrailingData` |
+    /// | 10   | `Encode` |
+    /// | 12   | `OutputTooSmall` |
+    /// | 13   | `LimitExceeded` |
+    pub fn code(&self) -> i32 {
+        match self {
+            Error::Io { .. } => 1,
+            Error::InvalidInput(_) | Error::InvalidJson(_) | Error::InvalidSchema(_) | Error::InvalidEnvelope { .. } => 2,
+            Error::InvalidHeader { .. } => 3,
+            Error::BlockCountMismatch { .. } => 4,
+            Error::BlockLengthMismatch { .. } => 5,
+            Error::CorruptBlock { .. } => 6,
+            Error::UnsupportedExtension(_) => 7,
+            Error::UnsupportedCompression(_) => 8,
+            Error::InvalidMessagePack { .. } | Error::TrailingData { .. } => 9,
+            Error::Encode(_) => 10,
+            Error::OutputTooSmall { .. } => 12,
+            Error::LimitExceeded { .. } => 13,
+        }
+    }
+
+    /// An `Io` error for a failed read or write, described by `context`
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        Error::Io { context: context.into(), source }
+    }
+
+    pub(crate) fn envelope(index: usize, message: impl Into<String>) -> Self {
+        Error::InvalidEnvelope { index: Some(index), message: message.into() }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { context, source } => write!(f, "{}: {}", context, source),
+            Error::InvalidInput(message) => write!(f, "{}", message),
+            Error::InvalidJson(e) => write!(f, "Failed to parse JSON: {}", e),
+            Error::InvalidSchema(message) => write!(f, "Invalid schema: {}", message),
+            Error::InvalidEnvelope { index: Some(index), message } => {
+                write!(f, "Invalid envelope at index {}: {}", index, message)
+            },
+            Error::InvalidEnvelope { index: None, message } => write!(f, "{}", message),
+            Error::InvalidHeader { offset, message } => {
+                write!(f, "Invalid extension header at byte {}: {}", offset, message)
+            },
+            Error::BlockCountMismatch { declared, found } => write!(
+                f,
+                "Lz4BlockArray header declares {} block(s) but {} binary block(s) follow it",
+                declared, found
+            ),
+            Error::BlockLengthMismatch { block, declared, actual } => write!(
+                f,
+                "LZ4 block {} decompressed to {} bytes but the header declares {}",
+                block, actual, declared
+            ),
+            Error::CorruptBlock { block, message } => write!(f, "LZ4 block {} is corrupt: {}", block, message),
+            Error::UnsupportedExtension(ext_type) => write!(f, "Unsupported extension type: {}", ext_type),
+            Error::UnsupportedCompression(mode) => write!(f, "Unsupported compression mode: {}", mode),
+            Error::InvalidMessagePack { offset, message } => {
+                write!(f, "Invalid MessagePack at byte {}: {}", offset, message)
+            },
+            Error::TrailingData { offset } => {
+                write!(f, "Unexpected trailing data at byte {} after the MessagePack value", offset)
+            },
+            Error::Encode(message) => write!(f, "{}", message),
+            Error::OutputTooSmall { required, available } => write!(
+                f,
+                "Output buffer holds {} bytes but the envelope decodes to {}",
+                available, required
+            ),
+            Error::LimitExceeded { limit, actual, max } => {
+                write!(f, "Input exceeds the {} limit: {} is more than the allowed {}", limit, actual, max)
+            },
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::InvalidJson(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::InvalidJson(e)
+    }
+}
+
+impl From<Error> for io::Error {
+    /// Keep the kind of I/O errors and report everything else as invalid data
+    fn from(e: Error) -> Self {
+        let kind = match &e {
+            Error::Io { source, .. } => source.kind(),
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, e)
+    }
+}
@@ -0,0 +1,292 @@
+//! Decode and encode MessagePack-CSharp LZ4 payloads.
+//!
+//! MessagePack-CSharp compresses with either `Lz4BlockArray` (an array of one
+//! ext 98 header followed by one `bin` per LZ4 block) or `Lz4Block` (a single
+//! ext 99 holding the length and one LZ4 block). [`decode`] turns either
+//! envelope back into the original MessagePack stream and [`encode`] builds
+//! the `Lz4BlockArray` envelope the way MessagePack-CSharp writes it.
+
+use rmpv::Value;
+use rmpv::decode::read_value;
+use rmpv::encode::write_value;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::io::Cursor;
+
+use envelope::{node_buffer, BorrowedEnvelope};
+
+pub mod codegen;
+mod convert;
+pub mod csharp;
+mod envelope;
+mod error;
+mod limits;
+pub mod models;
+mod primitives;
+mod processor;
+pub mod schema;
+mod stream;
+mod timestamp;
+pub mod unions;
+
+pub use convert::{
+    json_to_value, json_to_value_with, value_to_json, value_to_json_with, BigIntFormat, BinaryFormat, JsonOptions,
+    MsgpackOptions, TimestampFormat,
+};
+pub use envelope::Envelope;
+pub use models::StructEncoding;
+pub use error::Error;
+pub use limits::{Limit, Limits};
+pub use processor::{LZ4MessagePackProcessor, OutputFormat};
+pub use schema::Schema;
+pub use stream::{Lz4BlockArrayReader, Lz4BlockArrayWriter};
+pub use timestamp::Timestamp;
+
+/// Result type used throughout the library
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// MessagePack-CSharp extension type code for `Lz4BlockArray`
+pub const LZ4_BLOCK_ARRAY_EXT: i8 = 98;
+
+/// MessagePack-CSharp extension type code for `Lz4Block`
+pub const LZ4_BLOCK_EXT: i8 = 99;
+
+/// MessagePack extension type code for timestamps, which MessagePack-CSharp writes for `DateTime`
+pub const TIMESTAMP_EXT: i8 = -1;
+
+/// MessagePack-CSharp extension type code for Typeless values, a type name followed by the body
+///
+/// `TypelessContractlessStandardResolver` writes these for objects whose
+/// declared type is `object`, so the reader knows what to instantiate.
+pub const TYPELESS_EXT: i8 = 100;
+
+/// Largest uncompressed chunk stored in a single LZ4 block.
+///
+/// MessagePack-CSharp serializes into pooled 32 KiB segments and compresses
+/// each segment as its own block, so payloads are split the same way here.
+pub const LZ4_BLOCK_SIZE: usize = 32 * 1024;
+
+/// LZ4 compression mode, numbered like MessagePack-CSharp's `MessagePackCompression`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// A single ext 99 holding the int32 uncompressed length and one LZ4 block
+    Lz4Block = 1,
+    /// An array of one ext 98 header followed by one `bin` per LZ4 block
+    Lz4BlockArray = 2,
+}
+
+impl TryFrom<i32> for Compression {
+    type Error = Error;
+
+    fn try_from(value: i32) -> Result<Self> {
+        match value {
+            1 => Ok(Compression::Lz4Block),
+            2 => Ok(Compression::Lz4BlockArray),
+            other => Err(Error::UnsupportedCompression(other)),
+        }
+    }
+}
+
+/// Decode a binary `Lz4BlockArray` or `Lz4Block` envelope into the original MessagePack stream
+pub fn decode(payload: &[u8]) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    decode_into(payload, &mut output)?;
+    Ok(output)
+}
+
+/// Exact length of the MessagePack stream a binary envelope decodes to
+///
+/// The length comes from the ext 98 header (or the ext 99 length prefix), so
+/// nothing is decompressed. Use it to size the buffer for [`decode_into_slice`].
+/// Lengths beyond the default [`Limits`] are an error.
+pub fn decoded_len(payload: &[u8]) -> Result<usize> {
+    BorrowedEnvelope::parse(payload)?.decoded_len(&Limits::default())
+}
+
+/// Decode a binary envelope into `output`, replacing its contents
+///
+/// The envelope is read in place and every block is decompressed straight
+/// into `output`, which is resized to the exact decoded length up front.
+/// Decoding many payloads into the same `Vec` only allocates when one is
+/// larger than any before it. On error `output` is left empty.
+pub fn decode_into(payload: &[u8], output: &mut Vec<u8>) -> Result<()> {
+    decode_into_with_limits(payload, output, &Limits::default())
+}
+
+/// Decode a binary envelope into `output` like [`decode_into`], within the given limits
+///
+/// The lengths the header declares are checked before `output` is resized.
+pub fn decode_into_with_limits(payload: &[u8], output: &mut Vec<u8>, limits: &Limits) -> Result<()> {
+    output.clear();
+    let envelope = BorrowedEnvelope::parse(payload)?;
+    output.resize(envelope.decoded_len(limits)?, 0);
+    if let Err(e) = envelope.decompress_into(output, limits) {
+        output.clear();
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Decode a binary envelope into the start of `output`, returning the number of bytes written
+///
+/// Fails with [`Error::OutputTooSmall`], carrying the length the envelope
+/// decodes to, when `output` is shorter than that; nothing is decompressed
+/// in that case. Bytes of `output` past the returned length are untouched.
+pub fn decode_into_slice(payload: &[u8], output: &mut [u8]) -> Result<usize> {
+    BorrowedEnvelope::parse(payload)?.decompress_into(output, &Limits::default())
+}
+
+/// Decode a binary envelope, or return the input unchanged when it is uncompressed MessagePack
+///
+/// MessagePack-CSharp skips compression for small payloads, so the same
+/// endpoint can answer with either form. Uncompressed input must hold exactly
+/// one MessagePack value.
+pub fn decode_any(payload: &[u8]) -> Result<Vec<u8>> {
+    decode_any_with(payload, &JsonOptions::default())
+}
+
+fn decode_any_with(payload: &[u8], options: &JsonOptions) -> Result<Vec<u8>> {
+    if Envelope::detect(payload) {
+        if options.parallel {
+            return Envelope::parse_msgpack(payload)?.decompress_with(true, &options.limits);
+        }
+        let mut output = Vec::new();
+        decode_into_with_limits(payload, &mut output, &options.limits)?;
+        return Ok(output);
+    }
+
+    options.limits.check_structure(payload)?;
+    let mut cursor = Cursor::new(payload);
+    read_value(&mut cursor)
+        .map_err(|e| Error::InvalidMessagePack { offset: cursor.position(), message: e.to_string() })?;
+    if (cursor.position() as usize) < payload.len() {
+        return Err(Error::TrailingData { offset: cursor.position() });
+    }
+
+    Ok(payload.to_vec())
+}
+
+/// Decode a binary envelope and parse the MessagePack stream it holds
+pub fn decode_to_value(payload: &[u8]) -> Result<Value> {
+    read_msgpack_value(&decode(payload)?, &Limits::default())
+}
+
+/// Decode a binary envelope and convert the MessagePack stream it holds to JSON
+pub fn decode_to_json(payload: &[u8], options: &JsonOptions) -> Result<JsonValue> {
+    let msgpack = Envelope::parse_msgpack(payload)?.decompress_with(options.parallel, &options.limits)?;
+    let value = read_msgpack_value(&msgpack, &options.limits)?;
+    Ok(value_to_json_with(&value, options))
+}
+
+/// Decode a response in any form it arrives in and convert it to JSON
+///
+/// Accepts a binary envelope, uncompressed MessagePack, or the Node-style JSON
+/// form read by [`Envelope::parse_json`]. Input that parses as JSON is taken as
+/// the JSON form; when it holds several envelopes the result is a JSON array
+/// with one element per envelope. A lone `{"type": "Buffer", "data": [...]}`
+/// object, as Node's `Buffer.toJSON()` writes a response body, holds the raw
+/// bytes: a binary envelope or uncompressed MessagePack. A lone extension in
+/// the JSON form that is not an LZ4 envelope, such as a Typeless value, is
+/// converted as it is.
+pub fn decode_any_to_json(payload: &[u8], options: &JsonOptions) -> Result<JsonValue> {
+    if !Envelope::detect(payload) {
+        if let Ok(json) = serde_json::from_slice::<JsonValue>(payload) {
+            if let Some(bytes) = node_buffer(&json)? {
+                return msgpack_to_json(&bytes, options);
+            }
+            let mut values = Envelope::parse_json(&json)?
+                .iter()
+                .map(|envelope| envelope_value(envelope, options))
+                .map(|value| value.map(|value| value_to_json_with(&value, options)))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(match values.len() {
+                1 => values.remove(0),
+                _ => JsonValue::Array(values),
+            });
+        }
+    }
+
+    msgpack_to_json(payload, options)
+}
+
+/// Convert a binary envelope or uncompressed MessagePack to JSON
+fn msgpack_to_json(payload: &[u8], options: &JsonOptions) -> Result<JsonValue> {
+    let value = read_msgpack_value(&decode_any_with(payload, options)?, &options.limits)?;
+    Ok(value_to_json_with(&value, options))
+}
+
+/// Serialize a value with serde and compress it into a binary envelope using the given mode
+///
+/// Structs are written as MessagePack arrays, the way MessagePack-CSharp
+/// writes `[Key(n)]` models.
+pub fn to_vec_lz4<T: Serialize + ?Sized>(value: &T, compression: Compression) -> Result<Vec<u8>> {
+    to_vec_lz4_with(value, compression, StructEncoding::Array)
+}
+
+/// Serialize a value with serde using the given struct layout and compress it into a binary envelope
+pub fn to_vec_lz4_with<T: Serialize + ?Sized>(
+    value: &T,
+    compression: Compression,
+    encoding: StructEncoding,
+) -> Result<Vec<u8>> {
+    encode_with(&models::to_msgpack(value, encoding)?, compression)
+}
+
+/// Decode a binary envelope, or uncompressed MessagePack, and deserialize it with serde
+///
+/// Structs may be written either as arrays or as maps keyed by field name.
+pub fn from_slice_lz4<T: DeserializeOwned>(payload: &[u8]) -> Result<T> {
+    // decode_any already walks uncompressed input, so only a decompressed stream is checked here
+    let msgpack = match Envelope::detect(payload) {
+        true => {
+            let msgpack = decode(payload)?;
+            Limits::default().check_structure(&msgpack)?;
+            msgpack
+        },
+        false => decode_any(payload)?,
+    };
+    models::from_msgpack(&msgpack)
+}
+
+/// The value an envelope holds, or the extension itself when it is a lone non-LZ4 extension
+fn envelope_value(envelope: &Envelope, options: &JsonOptions) -> Result<Value> {
+    if envelope.blocks().is_empty() && ![LZ4_BLOCK_ARRAY_EXT, LZ4_BLOCK_EXT].contains(&envelope.ext_type()) {
+        // A Typeless extension is converted by parsing its payload, so it is walked like any other value
+        let ext = Value::Ext(envelope.ext_type(), envelope.header().to_vec());
+        let mut msgpack = Vec::new();
+        write_value(&mut msgpack, &ext).map_err(|e| Error::Encode(format!("Failed to write extension: {}", e)))?;
+        options.limits.check_structure(&msgpack)?;
+        return Ok(ext);
+    }
+    read_msgpack_value(&envelope.decompress_with(options.parallel, &options.limits)?, &options.limits)
+}
+
+/// Parse a single MessagePack value once its nesting and collection lengths are within `limits`
+fn read_msgpack_value(msgpack: &[u8], limits: &Limits) -> Result<Value> {
+    limits.check_structure(msgpack)?;
+    let mut cursor = Cursor::new(msgpack);
+    read_value(&mut cursor)
+        .map_err(|e| Error::InvalidMessagePack { offset: cursor.position(), message: e.to_string() })
+}
+
+/// Compress a MessagePack stream into a binary `Lz4BlockArray` envelope
+pub fn encode(msgpack: &[u8]) -> Result<Vec<u8>> {
+    encode_with(msgpack, Compression::Lz4BlockArray)
+}
+
+/// Compress a MessagePack stream into a binary envelope using the given mode
+pub fn encode_with(msgpack: &[u8], compression: Compression) -> Result<Vec<u8>> {
+    Envelope::compress(msgpack, compression)?.to_msgpack()
+}
+
+/// Serialize a MessagePack value and compress it into a binary `Lz4BlockArray` envelope
+///
+/// The value is compressed as it is serialized, so the uncompressed stream is
+/// never held in memory whole.
+pub fn encode_value(value: &Value) -> Result<Vec<u8>> {
+    let mut writer = Lz4BlockArrayWriter::new(Vec::new());
+    write_value(&mut writer, value)
+        .map_err(|e| Error::Encode(format!("Failed to serialize to MessagePack: {}", e)))?;
+    writer.finish()
+}
@@ -0,0 +1,199 @@
+//! Resource limits for decoding untrusted payloads.
+//!
+//! An LZ4 envelope declares how large every block decompresses to, and a few
+//! bytes of MessagePack can declare an array of four billion elements or nest
+//! arrays thousands of levels deep. [`Limits`] bounds all of these before the
+//! memory is allocated or the recursion happens. The defaults suit responses
+//! read from the network; [`Limits::unlimited`] restores the unchecked
+//! behaviour for trusted input.
+
+use std::fmt;
+
+use super::{Error, Result, TYPELESS_EXT};
+
+/// Bounds applied while decoding an envelope and the MessagePack it holds
+///
+/// The envelope limits are checked against the extension header before any
+/// block is decompressed. The structure limits are checked by walking the
+/// decompressed MessagePack before it is parsed into values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Total length of the decompressed MessagePack stream
+    pub max_uncompressed_len: usize,
+    /// Decompressed length of a single LZ4 block, including the one in an `Lz4Block` envelope
+    pub max_block_len: usize,
+    /// Number of blocks in an `Lz4BlockArray` envelope
+    pub max_blocks: usize,
+    /// Nesting depth of arrays, maps and Typeless values; a flat array is depth 1
+    pub max_depth: usize,
+    /// Elements of an array or entries of a map
+    pub max_collection_len: usize,
+}
+
+impl Limits {
+    /// No limits at all, for input from a trusted source
+    pub fn unlimited() -> Self {
+        Limits {
+            max_uncompressed_len: usize::MAX,
+            max_block_len: usize::MAX,
+            max_blocks: usize::MAX,
+            max_depth: usize::MAX,
+            max_collection_len: usize::MAX,
+        }
+    }
+
+    pub(crate) fn check_block_count(&self, count: usize) -> Result<()> {
+        check(Limit::BlockCount, count, self.max_blocks)
+    }
+
+    pub(crate) fn check_block_len(&self, length: usize) -> Result<()> {
+        check(Limit::BlockLen, length, self.max_block_len)
+    }
+
+    pub(crate) fn check_uncompressed_len(&self, length: usize) -> Result<()> {
+        check(Limit::UncompressedLen, length, self.max_uncompressed_len)
+    }
+
+    /// Check the block count, every block length and their total, as declared by a header
+    pub(crate) fn check_blocks(&self, lengths: &[usize]) -> Result<()> {
+        self.check_block_count(lengths.len())?;
+        let mut total = 0usize;
+        for &length in lengths {
+            self.check_block_len(length)?;
+            total = total.saturating_add(length);
+        }
+        self.check_uncompressed_len(total)
+    }
+
+    /// Walk a MessagePack stream without building it, checking nesting depth and collection lengths
+    ///
+    /// Typeless (ext 100) payloads are walked as well, each one a nesting
+    /// level, since converting them to JSON parses the body they hold. Only
+    /// the limits are reported: the walk stops quietly at malformed or
+    /// truncated data and leaves those errors to the parser.
+    pub(crate) fn check_structure(&self, data: &[u8]) -> Result<()> {
+        if self.max_depth == usize::MAX && self.max_collection_len == usize::MAX {
+            return Ok(());
+        }
+
+        // Values still to read at each open level, and where a Typeless payload level ends;
+        // the bottom entry is the top level
+        let mut pending: Vec<(usize, Option<usize>)> = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            match pending.last_mut() {
+                None => pending.push((1, None)),
+                Some((remaining, end)) if *remaining == 0 || end.is_some_and(|end| offset >= end) => {
+                    // A payload holding more or fewer values than expected ends at its length
+                    if let Some(end) = *end {
+                        offset = end;
+                    }
+                    pending.pop();
+                    continue;
+                },
+                Some((remaining, _)) => *remaining -= 1,
+            }
+
+            let marker = data[offset];
+            offset += 1;
+            let uint = |offset: usize, size: usize| -> Option<usize> {
+                let bytes = data.get(offset..offset + size)?;
+                Some(bytes.iter().fold(0usize, |n, &b| (n << 8) | usize::from(b)))
+            };
+            // Ext payloads: bytes holding the length, and the length itself
+            let ext = match marker {
+                0xd4..=0xd8 => Some((0, 1 << (marker - 0xd4))),
+                0xc7 => Some((1, uint(offset, 1).unwrap_or(0))),
+                0xc8 => Some((2, uint(offset, 2).unwrap_or(0))),
+                0xc9 => Some((4, uint(offset, 4).unwrap_or(0))),
+                _ => None,
+            };
+            if let Some((size, len)) = ext {
+                let payload = offset.saturating_add(size + 1);
+                if data.get(offset + size) == Some(&(TYPELESS_EXT as u8)) {
+                    check(Limit::Depth, pending.len(), self.max_depth)?;
+                    pending.push((2, Some(payload.saturating_add(len))));
+                    offset = payload;
+                } else {
+                    offset = payload.saturating_add(len);
+                }
+                continue;
+            }
+
+            // Bytes to skip after the marker, and for collections their length and values per entry
+            let (skip, collection) = match marker {
+                0x00..=0x7f | 0xc0 | 0xc2 | 0xc3 | 0xe0..=0xff => (0, None),
+                0xa0..=0xbf => (usize::from(marker & 0x1f), None),
+                0xcc | 0xd0 => (1, None),
+                0xcd | 0xd1 => (2, None),
+                0xca | 0xce | 0xd2 => (4, None),
+                0xcb | 0xcf | 0xd3 => (8, None),
+                0xc4 | 0xd9 => (1 + uint(offset, 1).unwrap_or(0), None),
+                0xc5 | 0xda => (2 + uint(offset, 2).unwrap_or(0), None),
+                0xc6 | 0xdb => (4 + uint(offset, 4).unwrap_or(0), None),
+                0x90..=0x9f => (0, Some((usize::from(marker & 0x0f), 1))),
+                0x80..=0x8f => (0, Some((usize::from(marker & 0x0f), 2))),
+                0xdc | 0xde => (2, uint(offset, 2).map(|len| (len, if marker == 0xdc { 1 } else { 2 }))),
+                0xdd | 0xdf => (4, uint(offset, 4).map(|len| (len, if marker == 0xdd { 1 } else { 2 }))),
+                // 0xc1 is never used, and the ext markers are handled above; the parser reports 0xc1
+                _ => return Ok(()),
+            };
+            offset = offset.saturating_add(skip);
+
+            if let Some((len, values_per_entry)) = collection {
+                check(Limit::CollectionLen, len, self.max_collection_len)?;
+                // The top-level entry is not a nesting level
+                check(Limit::Depth, pending.len(), self.max_depth)?;
+                pending.push((len.saturating_mul(values_per_entry), None));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Untrusted network input: 256 MiB in total, 16 MiB per block and a depth of 128
+///
+/// Parsing and converting a value recurses once per level, so the depth
+/// matches serde_json's recursion limit and stays well within the 2 MiB stack
+/// of a spawned thread, even in debug builds.
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_uncompressed_len: 256 * 1024 * 1024,
+            max_block_len: 16 * 1024 * 1024,
+            max_blocks: 65_536,
+            max_depth: 128,
+            max_collection_len: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// The limit an input exceeded, carried by [`Error::LimitExceeded`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    UncompressedLen,
+    BlockLen,
+    BlockCount,
+    Depth,
+    CollectionLen,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Limit::UncompressedLen => "uncompressed length",
+            Limit::BlockLen => "block length",
+            Limit::BlockCount => "block count",
+            Limit::Depth => "nesting depth",
+            Limit::CollectionLen => "collection length",
+        })
+    }
+}
+
+fn check(limit: Limit, actual: usize, max: usize) -> Result<()> {
+    match actual > max {
+        true => Err(Error::LimitExceeded { limit, actual, max }),
+        false => Ok(()),
+    }
+}
@@ -1,977 +1,98 @@
-use rmpv::Value;
-use rmpv::encode::write_value;
-use rmpv::decode::read_value;
-use std::io::{self, Read, Write, Cursor};
-use std::fs::File;
 use std::env;
-use serde_json::{json, Value as JsonValue};
-use lz4::block::decompress;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use lz4_messagepack::codegen::generate_rust;
+use lz4_messagepack::csharp::import_schema_from_paths;
+use lz4_messagepack::{Error, JsonOptions, LZ4MessagePackProcessor, Limits, OutputFormat, Schema};
 
-/// Represents output format options
-#[derive(Debug, Clone, PartialEq)]
-pub enum OutputFormat {
-    Json,
-    Hex,
-    Binary,
-    Human,
-}
-
-impl From<&str> for OutputFormat {
-    fn from(s: &str) -> Self {
-        match s.to_lowercase().as_str() {
-            "hex" => OutputFormat::Hex,
-            "binary" => OutputFormat::Binary,
-            "human" => OutputFormat::Human,
-            _ => OutputFormat::Json,
-        }
-    }
-}
-
-/// Represents a MessagePack extension block
-#[derive(Debug)]
-pub struct MessagePackExt {
-    ext_type: i8,
-    header_data: Vec<u8>,
-    data: Vec<u8>,
-}
-
-/// Core functionality for processing LZ4 MessagePack data
-pub struct LZ4MessagePackProcessor;
-
-impl LZ4MessagePackProcessor {
-    /// Parse input JSON into a vector of MessagePackExt structures
-    fn parse_input(input_json: &str) -> Result<Vec<MessagePackExt>, String> {
-        let parsed: JsonValue = serde_json::from_str(input_json)
-            .map_err(|e| format!("Failed to parse JSON: {}", e))?;
-        
-        let parsed_array = parsed.as_array()
-            .ok_or("Expected a JSON array")?;
-        
-        // Check if we have at least one block (which requires 2 elements)
-        if parsed_array.len() < 2 {
-            return Err("Input JSON must contain at least 2 elements".to_string());
-        }
-        
-        // Collect all the blocks
-        let mut result = Vec::new();
-        let mut current_index = 0;
-        
-        // Process blocks in pairs (header + data)
-        while current_index + 1 < parsed_array.len() {
-            let header = &parsed_array[current_index];
-            let data = &parsed_array[current_index + 1];
-            
-            // Check if this is an LZ4 block header
-            if let Some(ext_type) = header.get("type").and_then(|t| t.as_u64()) {
-                // Extract the header data
-                let header_data = if let Some(buffer) = header.get("buffer") {
-                    Self::extract_byte_array(&buffer["data"])?
-                } else {
-                    return Err(format!("Missing buffer in block at index {}", current_index));
-                };
-                
-                // Extract the data
-                let data_bytes = if let Some(data_array) = data.get("data") {
-                    Self::extract_byte_array(data_array)?
-                } else {
-                    return Err(format!("Missing data in block at index {}", current_index + 1));
-                };
-                
-                // Add to our result
-                result.push(MessagePackExt {
-                    ext_type: ext_type as i8,
-                    header_data,
-                    data: data_bytes,
-                });
-                
-                // Move to the next block
-                current_index += 2;
-            } else {
-                // Not a valid block header, skip this element
-                current_index += 1;
-            }
-        }
-        
-        if result.is_empty() {
-            return Err("No valid LZ4 blocks found in input".to_string());
-        }
-        
-        Ok(result)
-    }
-    
-    /// Helper function to extract a byte array from JSON
-    fn extract_byte_array(json_array: &JsonValue) -> Result<Vec<u8>, String> {
-        json_array.as_array()
-            .ok_or("Expected data to be an array")?
-            .iter()
-            .map(|v| {
-                v.as_u64()
-                    .ok_or("Expected data element to be a number")
-                    .map(|n| n as u8)
-            })
-            .collect::<Result<Vec<u8>, &str>>()
-            .map_err(|e| e.to_string())
-    }
-    
-    /// Calculate the uncompressed size from header data
-    fn get_uncompressed_size(header: &[u8]) -> usize {
-        // Check if we have a valid header
-        if header.len() < 2 {
-            eprintln!("Warning: Header too short to extract size");
-            return 0;
-        }
-        
-        // Print header bytes in hex for debugging
-        let header_hex: String = header.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
-        eprintln!("Header bytes: {}", header_hex);
-        
-        // Special case for 205 (0xCD) which is MessagePack uint16
-        if header[0] == 205 {
-            // For uint16, we expect format [205, high_byte, low_byte, ...]
-            if header.len() >= 3 {
-                let size = ((header[1] as usize) << 8) | (header[2] as usize);
-                eprintln!("Detected MessagePack uint16 size marker: {}", size);
-                return size;
-            }
-        }
-        
-        // Special case for 206 (0xCE) which is MessagePack uint32
-        if header[0] == 206 {
-            // For uint32, we expect format [206, b3, b2, b1, b0, ...]
-            if header.len() >= 5 {
-                let size = ((header[1] as usize) << 24) | ((header[2] as usize) << 16) |
-                           ((header[3] as usize) << 8) | (header[4] as usize);
-                eprintln!("Detected MessagePack uint32 size marker: {}", size);
-                return size;
-            }
-        }
-        
-        // Special case for header with 2-byte prefix
-        if header.len() >= 4 && header[0] == 204 && header[1] == 12 {
-            if header[2] == 229 && header[3] == 205 {
-                // This pattern was observed in the default input
-                eprintln!("Detected special header pattern with 229,205 sequence");
-                return 3941; // Value derived from analysis of original content
-            }
-        }
-        
-        // The header format depends on the first byte
-        // For type 204 (0xCC), the size is usually in the following bytes
-        if header[0] == 204 { // 0xCC
-            // Try to extract the size based on the header length
-            match header.len() {
-                2 => {
-                    let size = header[1] as usize;
-                    eprintln!("Detected single-byte size: {}", size);
-                    return size;
-                },
-                3 => {
-                    let size = ((header[1] as usize) << 8) | (header[2] as usize);
-                    eprintln!("Detected two-byte size: {}", size);
-                    return size;
-                },
-                4 => {
-                    let size = ((header[1] as usize) << 16) | ((header[2] as usize) << 8) | (header[3] as usize);
-                    eprintln!("Detected three-byte size: {}", size);
-                    return size;
-                },
-                5 => {
-                    let size = ((header[1] as usize) << 24) | ((header[2] as usize) << 16) |
-                               ((header[3] as usize) << 8) | (header[4] as usize);
-                    eprintln!("Detected four-byte size: {}", size);
-                    return size;
-                },
-                _ => {
-                    // Try to determine the size based on the subsequent bytes
-                    if header.len() > 2 {
-                        // Look for a MessagePack size marker
-                        match header[1] {
-                            // For various MessagePack markers
-                            205 => { // uint16
-                                if header.len() >= 4 {
-                                    let size = ((header[2] as usize) << 8) | (header[3] as usize);
-                                    eprintln!("Detected MessagePack uint16: {}", size);
-                                    return size;
-                                }
-                            },
-                            206 => { // uint32
-                                if header.len() >= 6 {
-                                    let size = ((header[2] as usize) << 24) | ((header[3] as usize) << 16) |
-                                              ((header[4] as usize) << 8) | (header[5] as usize);
-                                    eprintln!("Detected MessagePack uint32: {}", size);
-                                    return size;
-                                }
-                            },
-                            _ => {
-                                // If second byte doesn't appear to be a size marker,
-                                // try interpreting as little-endian uint16/uint32
-                                if header.len() >= 3 {
-                                    let le_size = (header[1] as usize) | ((header[2] as usize) << 8);
-                                    eprintln!("Trying little-endian uint16: {}", le_size);
-                                    if le_size > 0 && le_size < 100000 {
-                                        return le_size;
-                                    }
-                                }
-                                
-                                if header.len() >= 5 {
-                                    let le_size = (header[1] as usize) | ((header[2] as usize) << 8) |
-                                                 ((header[3] as usize) << 16) | ((header[4] as usize) << 24);
-                                    eprintln!("Trying little-endian uint32: {}", le_size);
-                                    if le_size > 0 && le_size < 1000000 {
-                                        return le_size;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    
-                    // If we can't determine the format, estimate based on compressed size
-                    let compressed_len = header.len(); // This isn't accurate but just a fallback
-                    let estimated_size = compressed_len * 4; // Assume 4:1 compression ratio as fallback
-                    eprintln!("Warning: Unrecognized header format, estimating size: {}", estimated_size);
-                    return estimated_size;
-                }
-            }
-        } else if header[0] == 205 { // MessagePack uint16
-            if header.len() >= 3 {
-                let size = ((header[1] as usize) << 8) | (header[2] as usize);
-                eprintln!("Detected direct MessagePack uint16: {}", size);
-                return size;
-            }
-        } else if header[0] == 206 { // MessagePack uint32
-            if header.len() >= 5 {
-                let size = ((header[1] as usize) << 24) | ((header[2] as usize) << 16) |
-                          ((header[3] as usize) << 8) | (header[4] as usize);
-                eprintln!("Detected direct MessagePack uint32: {}", size);
-                return size;
-            }
-        } else {
-            eprintln!("Warning: Unexpected header type: {}", header[0]);
-        }
-        
-        // Fallback when header type detection fails
-        let compressed_len = header.len();
-        let estimated_size = compressed_len * 4; // Assume 4:1 compression ratio
-        eprintln!("Using fallback size estimation: {}", estimated_size);
-        return estimated_size;
-    }
-    
-    /// Reserialize the MessagePackExt back to MessagePack format
-    fn reserialize_to_msgpack(ext: &MessagePackExt) -> Result<Vec<u8>, String> {
-        let mut output = Vec::new();
-        
-        // Create a MessagePack extension object for the first part
-        let ext_value = Value::Ext(ext.ext_type, ext.header_data.clone());
-        
-        // Create the second part with the buffer data
-        let buffer_value = Value::Binary(ext.data.clone());
-        
-        // Create the final array
-        let final_array = Value::Array(vec![ext_value, buffer_value]);
-        
-        // Serialize to MessagePack
-        write_value(&mut output, &final_array)
-            .map_err(|e| format!("Failed to serialize to MessagePack: {}", e))?;
-        
-        Ok(output)
-    }
-    
-    /// Attempt to decompress data using different strategies
-    fn decompress_data(data: &[u8], uncompressed_size: usize) -> Option<(Vec<u8>, usize)> {
-        // Check for empty data
-        if data.is_empty() {
-            eprintln!("Error: Empty compressed data");
-            return None;
-        }
-        
-        eprintln!("Trying to decompress {} bytes of data, expected size: {}", data.len(), uncompressed_size);
-        
-        // Calculate a reasonable maximum size for decompression
-        // LZ4 data is typically smaller than original, so a factor of 10 should be safe
-        let max_size = if uncompressed_size > 0 {
-            uncompressed_size * 10
-        } else {
-            data.len() * 10
-        };
-        
-        // Try each decompression strategy sequentially
-        
-        // 1. Standard LZ4 decompression with estimated size
-        match decompress(data, Some(max_size as i32)) {
-            Ok(decompressed) => {
-                eprintln!("Decompression succeeded with max_size: {}, got {} bytes", max_size, decompressed.len());
-                return Some((decompressed, 1));
-            },
-            Err(e) => eprintln!("Decompression attempt 1 failed: {}", e),
-        }
-        
-        // 2. Try without size hint
-        match decompress(data, None) {
-            Ok(decompressed) => {
-                eprintln!("Decompression succeeded without size hint, got {} bytes", decompressed.len());
-                return Some((decompressed, 2));
-            },
-            Err(e) => eprintln!("Decompression attempt 2 failed: {}", e),
-        }
-        
-        // 3. Try with offset 1 (in case there's a header byte we should skip)
-        if data.len() > 1 {
-            match decompress(&data[1..], None) {
-                Ok(decompressed) => {
-                    eprintln!("Decompression succeeded with offset 1, got {} bytes", decompressed.len());
-                    return Some((decompressed, 3));
-                },
-                Err(e) => eprintln!("Decompression attempt 3 failed: {}", e),
-            }
-        }
-        
-        // 4. Try with offset 2 (in case there's a 2-byte header)
-        if data.len() > 2 {
-            match decompress(&data[2..], None) {
-                Ok(decompressed) => {
-                    eprintln!("Decompression succeeded with offset 2, got {} bytes", decompressed.len());
-                    return Some((decompressed, 4));
-                },
-                Err(e) => eprintln!("Decompression attempt 4 failed: {}", e),
-            }
-        }
-        
-        // 5. Try with offset 4 (in case there's a 4-byte header)
-        if data.len() > 4 {
-            match decompress(&data[4..], None) {
-                Ok(decompressed) => {
-                    eprintln!("Decompression succeeded with offset 4, got {} bytes", decompressed.len());
-                    return Some((decompressed, 5));
-                },
-                Err(e) => eprintln!("Decompression attempt 5 failed: {}", e),
-            }
-        }
-        
-        // 6. Try looking for the LZ4 magic number
-        for i in 0..std::cmp::min(data.len(), 20) {
-            if i + 4 <= data.len() && data[i] == 0x04 && data[i+1] == 0x22 && data[i+2] == 0x4D && data[i+3] == 0x18 {
-                eprintln!("Found potential LZ4 magic number at offset {}", i);
-                match decompress(&data[i..], None) {
-                    Ok(decompressed) => {
-                        eprintln!("Decompression succeeded with magic number at offset {}, got {} bytes", i, decompressed.len());
-                        return Some((decompressed, 6));
-                    },
-                    Err(e) => eprintln!("Decompression attempt with magic number failed: {}", e),
-                }
-                break;
-            }
-        }
-        
-        // 7. Brute force approach - try every offset up to a reasonable limit
-        for i in 0..std::cmp::min(data.len(), 20) {
-            if i > 4 && data.len() > i { // Already tried offsets 0-4
-                match decompress(&data[i..], None) {
-                    Ok(decompressed) => {
-                        eprintln!("Decompression succeeded with brute force offset {}, got {} bytes", i, decompressed.len());
-                        return Some((decompressed, 7));
-                    },
-                    Err(_) => {} // Don't print error for every offset
-                }
-            }
-        }
-        
-        None
-    }
-    
-    /// Process the decompressed data and convert to a more readable format
-    fn process_decompressed_data(decompressed: &[u8], attempt_num: usize) -> Result<JsonValue, String> {
-        eprintln!("Decompression attempt {} succeeded, got {} bytes", attempt_num, decompressed.len());
-        Self::debug_dump("First bytes of decompressed data", decompressed, 32);
-        
-        // Return error for empty data
-        if decompressed.is_empty() {
-            return Err("Empty data after decompression".to_string());
-        }
-        
-        // Try to parse as MessagePack with error recovery
-        let mut cursor = Cursor::new(decompressed);
-        match read_value(&mut cursor) {
-            Ok(value) => {
-                eprintln!("Successfully parsed MessagePack data");
-                
-                // Convert to JSON
-                let json_value = Self::convert_value_to_json(&value);
-                
-                // If the value is an array with at least 5 elements, try to extract common fields
-                if let JsonValue::Array(items) = &json_value {
-                    if items.len() >= 5 {
-                        let mut result = serde_json::Map::new();
-                        
-                        // Try to extract common fields if they match the expected types
-                        if let Some(JsonValue::String(type_val)) = items.get(0) {
-                            result.insert("type".to_string(), json!(type_val));
-                        }
-                        if let Some(JsonValue::String(title)) = items.get(1) {
-                            result.insert("title".to_string(), json!(title));
-                        }
-                        if let Some(JsonValue::Number(status)) = items.get(2) {
-                            result.insert("status".to_string(), json!(status));
-                        }
-                        if let Some(JsonValue::String(detail)) = items.get(3) {
-                            result.insert("detail".to_string(), json!(detail));
-                        }
-                        if let Some(JsonValue::String(instance)) = items.get(4) {
-                            result.insert("instance".to_string(), json!(instance));
-                        }
-                        
-                        // If we found any of the expected fields, return the structured object
-                        if !result.is_empty() {
-                            return Ok(JsonValue::Object(result));
-                        }
-                    }
-                }
-                
-                // Otherwise return the raw converted value
-                Ok(json_value)
-            },
-            Err(e) => {
-                eprintln!("Failed to parse decompressed data as MessagePack: {}", e);
-                
-                // Try partial parsing - read as many values as possible
-                Self::debug_print("Attempting partial parsing of MessagePack data");
-                let partial_values = Self::parse_partial_messagepack(decompressed);
-                if !partial_values.is_empty() {
-                    eprintln!("Successfully parsed {} partial MessagePack values", partial_values.len());
-                    return Ok(json!(partial_values));
-                }
-                
-                // Try to interpret as UTF-8 string
-                match String::from_utf8(decompressed.to_vec()) {
-                    Ok(s) => {
-                        if s.chars().any(|c| !c.is_control()) {
-                            eprintln!("Interpreted as UTF-8 string");
-                            
-                            // Try to parse as JSON if it looks like JSON
-                            if s.trim().starts_with('{') || s.trim().starts_with('[') {
-                                match serde_json::from_str::<JsonValue>(&s) {
-                                    Ok(parsed_json) => {
-                                        eprintln!("Successfully parsed as JSON");
-                                        return Ok(parsed_json);
-                                    },
-                                    Err(json_err) => {
-                                        eprintln!("Failed to parse as JSON: {}", json_err);
-                                    }
-                                }
-                            }
-                            
-                            // Return as raw string
-                            Ok(json!({ "raw_string": s }))
-                        } else {
-                            // Return data summary if no readable string
-                            Ok(Self::summarize_binary_data(decompressed))
-                        }
-                    },
-                    Err(_) => {
-                        // Return binary data summary
-                        eprintln!("Not valid UTF-8, returning binary data summary");
-                        Ok(Self::summarize_binary_data(decompressed))
-                    }
-                }
-            }
-        }
-    }
-    
-    /// Try to parse as many MessagePack values as possible from a byte stream
-    fn parse_partial_messagepack(data: &[u8]) -> Vec<JsonValue> {
-        let mut result = Vec::new();
-        let mut offset = 0;
-        
-        while offset < data.len() {
-            // Try to read a single value
-            let mut cursor = Cursor::new(&data[offset..]);
-            match read_value(&mut cursor) {
-                Ok(value) => {
-                    let consumed = cursor.position() as usize;
-                    if consumed == 0 {
-                        // No progress made, move to next byte
-                        offset += 1;
-                    } else {
-                        // Successfully read a value
-                        result.push(Self::convert_value_to_json(&value));
-                        offset += consumed;
-                    }
-                },
-                Err(_) => {
-                    // Failed to read value, skip this byte
-                    offset += 1;
-                }
-            }
-            
-            // Limit the number of values we extract to avoid excessive processing
-            if result.len() >= 100 {
-                break;
-            }
-        }
-        
-        result
-    }
-    
-    /// Create a summary of binary data
-    fn summarize_binary_data(data: &[u8]) -> JsonValue {
-        // Calculate some basic statistics
-        let total_bytes = data.len();
-        let zero_bytes = data.iter().filter(|&&b| b == 0).count();
-        let text_bytes = data.iter().filter(|&&b| (b >= 32 && b <= 126) || b == 9 || b == 10 || b == 13).count();
-        let control_bytes = data.iter().filter(|&&b| b < 32 && b != 9 && b != 10 && b != 13).count();
-        let high_bytes = data.iter().filter(|&&b| b > 127).count();
-        
-        // Get histogram of byte values for analysis
-        let mut byte_histogram = [0u32; 256];
-        for &b in data {
-            byte_histogram[b as usize] += 1;
-        }
-        
-        // Find most common bytes
-        let mut common_bytes = Vec::new();
-        for (byte, &count) in byte_histogram.iter().enumerate() {
-            if count > 0 {
-                common_bytes.push((byte, count));
-            }
-        }
-        common_bytes.sort_by(|a, b| b.1.cmp(&a.1));
-        
-        // Take top 10 most common bytes
-        let top_bytes: Vec<_> = common_bytes.iter().take(10).map(|&(b, c)| {
-            json!({
-                "byte": b,
-                "hex": format!("0x{:02x}", b),
-                "ascii": if b >= 32 && b <= 126 { 
-                    // Convert to u32 first, then to char
-                    let ch = char::from_u32(b as u32).unwrap_or('?');
-                    format!("{}", ch)
-                } else { 
-                    "N/A".to_string() 
-                },
-                "count": c,
-                "percentage": format!("{:.2}%", (c as f64 / total_bytes as f64) * 100.0)
-            })
-        }).collect();
-        
-        // Create a summary of the data
-        json!({
-            "summary": "Binary data",
-            "total_bytes": total_bytes,
-            "zero_bytes": zero_bytes,
-            "zero_percentage": format!("{:.2}%", (zero_bytes as f64 / total_bytes as f64) * 100.0),
-            "ascii_text_bytes": text_bytes,
-            "text_percentage": format!("{:.2}%", (text_bytes as f64 / total_bytes as f64) * 100.0),
-            "control_bytes": control_bytes,
-            "high_bytes": high_bytes,
-            "most_common_bytes": top_bytes,
-            "first_32_bytes": data.iter().take(32).map(|&b| format!("{:02x}", b)).collect::<Vec<_>>().join(" "),
-            "appears_to_be": if text_bytes > total_bytes * 3 / 4 {
-                "Mostly ASCII text"
-            } else if zero_bytes > total_bytes / 3 {
-                "Contains many zero bytes (possibly padded data or null-terminated strings)"
-            } else if high_bytes > total_bytes / 2 {
-                "Contains many high bytes (possibly compressed or encrypted data)"
-            } else {
-                "General binary data"
-            }
-        })
-    }
-    
-    /// Convert a MessagePack value to a JSON value
-    fn convert_value_to_json(value: &Value) -> JsonValue {
-        match value {
-            Value::Nil => JsonValue::Null,
-            Value::Boolean(b) => json!(*b),
-            Value::Integer(i) => json!(i.as_i64()),
-            Value::F32(f) => json!(*f),
-            Value::F64(f) => json!(*f),
-            Value::String(s) => {
-                if let Some(text) = s.as_str() {
-                    json!(text)
-                } else {
-                    json!(null)
-                }
-            },
-            Value::Binary(b) => json!(b.iter().map(|&byte| byte).collect::<Vec<u8>>()),
-            Value::Array(a) => {
-                json!(a.iter().map(Self::convert_value_to_json).collect::<Vec<_>>())
-            },
-            Value::Map(m) => {
-                let mut obj = serde_json::Map::new();
-                for (k, v) in m {
-                    if let Value::String(key_str) = k {
-                        if let Some(key) = key_str.as_str() {
-                            obj.insert(key.to_string(), Self::convert_value_to_json(v));
-                        }
-                    }
-                }
-                JsonValue::Object(obj)
-            },
-            Value::Ext(typ, data) => {
-                json!({
-                    "ext_type": typ,
-                    "ext_data": data.iter().map(|&byte| byte).collect::<Vec<u8>>()
-                })
-            }
-        }
-    }
-    
-    /// Analyze input data to determine its format
-    fn analyze_input_format(input_json: &str) -> Result<String, String> {
-        // Try to parse as JSON first
-        if let Ok(json_value) = serde_json::from_str::<JsonValue>(input_json) {
-            // Check if it's our expected format (array with objects)
-            if let Some(array) = json_value.as_array() {
-                if !array.is_empty() {
-                    eprintln!("Input appears to be in JSON format with {} elements", array.len());
-                    
-                    // Check if it follows our LZ4BlockArray format
-                    let has_type = array.iter().any(|item| item.get("type").is_some());
-                    let has_buffer = array.iter().any(|item| item.get("buffer").is_some());
-                    
-                    if has_type && has_buffer {
-                        return Ok("lz4_block_array".to_string());
-                    } else {
-                        return Ok("json_array".to_string());
-                    }
-                }
-            } else if json_value.is_object() {
-                eprintln!("Input appears to be a JSON object");
-                return Ok("json_object".to_string());
-            }
-        }
-        
-        // Check if it might be binary data encoded as text
-        let hex_chars = input_json.chars().filter(|c| c.is_ascii_hexdigit()).count();
-        if hex_chars > (input_json.len() as f64 * 0.8) as usize {
-            eprintln!("Input appears to be hexadecimal data");
-            return Ok("hex_data".to_string());
-        }
-        
-        // Check if it looks like raw MessagePack data
-        let input_bytes = input_json.as_bytes();
-        if input_bytes.len() > 4 {
-            if (input_bytes[0] == 0xc0 || input_bytes[0] == 0xc1 || 
-                input_bytes[0] == 0xc2 || input_bytes[0] == 0xc3) ||
-               (input_bytes[0] == 0x90 || input_bytes[0] == 0x91 || input_bytes[0] == 0x92) ||
-               (input_bytes[0] == 0x80 || input_bytes[0] == 0x81 || input_bytes[0] == 0x82) {
-                eprintln!("Input appears to be raw MessagePack data");
-                return Ok("messagepack".to_string());
-            }
-        }
-        
-        // Default to our standard format
-        eprintln!("Input format not clearly identifiable, processing as standard LZ4BlockArray");
-        Ok("lz4_block_array".to_string())
-    }
-    
-    /// Parse and process input in a format-aware manner
-    fn process_input(input_json: &str) -> Result<Vec<MessagePackExt>, String> {
-        // First analyze the format
-        let format = Self::analyze_input_format(input_json)?;
-        
-        match format.as_str() {
-            "lz4_block_array" => {
-                // Use our standard parser
-                Self::parse_input(input_json)
-            },
-            "json_array" | "json_object" => {
-                // For regular JSON, we'll need to convert it to our format first
-                eprintln!("Converting JSON data to LZ4BlockArray format...");
-                let json_value: JsonValue = serde_json::from_str(input_json)
-                    .map_err(|e| format!("Failed to parse JSON: {}", e))?;
-                
-                // Serialize the JSON to MessagePack
-                let mut msgpack_data = Vec::new();
-                write_value(&mut msgpack_data, &Self::convert_json_to_msgpack(&json_value)?)
-                    .map_err(|e| format!("Failed to serialize to MessagePack: {}", e))?;
-                
-                // Create a MessagePackExt for our data
-                let ext = MessagePackExt {
-                    ext_type: 98, // LZ4BlockArray type
-                    header_data: vec![204, msgpack_data.len() as u8], // Simple header
-                    data: msgpack_data,
-                };
-                
-                Ok(vec![ext])
-            },
-            "hex_data" => {
-                // Try to parse hex data
-                eprintln!("Attempting to parse hexadecimal data...");
-                let mut hex_data = Vec::new();
-                
-                // Strip non-hex characters
-                let cleaned_input: String = input_json.chars()
-                    .filter(|c| c.is_ascii_hexdigit())
-                    .collect();
-                
-                // Convert hex to bytes
-                for i in (0..cleaned_input.len()).step_by(2) {
-                    if i + 1 < cleaned_input.len() {
-                        let byte_str = &cleaned_input[i..i+2];
-                        if let Ok(byte) = u8::from_str_radix(byte_str, 16) {
-                            hex_data.push(byte);
-                        }
-                    }
-                }
-                
-                if hex_data.is_empty() {
-                    return Err("Failed to parse hex data".to_string());
-                }
-                
-                // Create a MessagePackExt for our data
-                let ext = MessagePackExt {
-                    ext_type: 98, // LZ4BlockArray type
-                    header_data: vec![204, hex_data.len() as u8], // Simple header
-                    data: hex_data,
-                };
-                
-                Ok(vec![ext])
-            },
-            _ => {
-                // Default to our standard parser but with a warning
-                eprintln!("Warning: Unrecognized format, attempting standard parsing...");
-                Self::parse_input(input_json)
-            }
-        }
-    }
-    
-    // Helper function to convert JSON to MessagePack value
-    fn convert_json_to_msgpack(json: &JsonValue) -> Result<Value, String> {
-        match json {
-            JsonValue::Null => Ok(Value::Nil),
-            JsonValue::Bool(b) => Ok(Value::Boolean(*b)),
-            JsonValue::Number(n) => {
-                if n.is_i64() {
-                    Ok(Value::Integer(n.as_i64().unwrap().into()))
-                } else if n.is_u64() {
-                    Ok(Value::Integer(n.as_u64().unwrap().into()))
-                } else if n.is_f64() {
-                    Ok(Value::F64(n.as_f64().unwrap()))
-                } else {
-                    Err("Unsupported number type".to_string())
-                }
-            },
-            JsonValue::String(s) => Ok(Value::String(s.clone().into())),
-            JsonValue::Array(a) => {
-                let mut values = Vec::new();
-                for item in a {
-                    values.push(Self::convert_json_to_msgpack(item)?);
-                }
-                Ok(Value::Array(values))
-            },
-            JsonValue::Object(o) => {
-                let mut items = Vec::new();
-                for (k, v) in o {
-                    items.push((
-                        Value::String(k.clone().into()),
-                        Self::convert_json_to_msgpack(v)?
-                    ));
-                }
-                Ok(Value::Map(items))
-            }
-        }
-    }
-    
-    /// Helper method to print debug information
-    fn debug_print(message: &str) {
-        if std::env::var("LZ4_MESSAGEPACK_DEBUG").is_ok() {
-            eprintln!("DEBUG: {}", message);
-        }
-    }
-    
-    /// Helper method to dump binary data in debug mode
-    fn debug_dump(prefix: &str, data: &[u8], max_bytes: usize) {
-        if std::env::var("LZ4_MESSAGEPACK_DEBUG").is_ok() {
-            let bytes_to_show = std::cmp::min(data.len(), max_bytes);
-            let hex_dump: String = data[..bytes_to_show]
-                .iter()
-                .map(|b| format!("{:02x}", b))
-                .collect::<Vec<_>>()
-                .join(" ");
-            
-            eprintln!("DEBUG: {} (first {} of {} bytes): {}", 
-                prefix, bytes_to_show, data.len(), hex_dump);
-        }
-    }
-    
-    /// Process an input file or string and output the result
-    pub fn process(input_source: Option<&str>, output_format: OutputFormat) -> Result<String, String> {
-        // Read input JSON
-        let input_json = Self::read_input(input_source)?;
-        
-        // Print first few bytes for debugging
-        Self::debug_dump("Input data", input_json.as_bytes(), 32);
-        
-        // Parse the input into blocks with format awareness
-        let blocks = Self::process_input(&input_json)?;
-        
-        if std::env::var("LZ4_MESSAGEPACK_DEBUG").is_ok() {
-            eprintln!("Found {} LZ4 blocks to process", blocks.len());
-        } else {
-            eprintln!("Found {} LZ4 blocks to process", blocks.len());
-        }
-        
-        // Process each block
-        let mut results = Vec::new();
-        
-        for (i, ext) in blocks.iter().enumerate() {
-            if std::env::var("LZ4_MESSAGEPACK_DEBUG").is_ok() {
-                eprintln!("Processing block {} of {}", i+1, blocks.len());
-                eprintln!("Ext type: {}", ext.ext_type);
-                eprintln!("Header data length: {}", ext.header_data.len());
-                eprintln!("Compressed data length: {}", ext.data.len());
-                Self::debug_dump("Header data", &ext.header_data, ext.header_data.len());
-                Self::debug_dump("Compressed data", &ext.data, 32);
-            } else {
-                eprintln!("Processing block {} of {}", i+1, blocks.len());
-                eprintln!("Ext type: {}", ext.ext_type);
-                eprintln!("Header data length: {}", ext.header_data.len());
-                eprintln!("Compressed data length: {}", ext.data.len());
-            }
-            
-            // Process based on the extension type
-            if ext.ext_type == 98 { // LZ4BlockArray
-                // Get expected uncompressed size
-                let uncompressed_size = Self::get_uncompressed_size(&ext.header_data);
-                eprintln!("Expected uncompressed size from header: {}", uncompressed_size);
-                
-                // Reserialize to MessagePack
-                let msgpack_output = Self::reserialize_to_msgpack(ext)?;
-                eprintln!("MessagePack output length: {} bytes", msgpack_output.len());
-                
-                // Try to decompress
-                let human_readable = match Self::decompress_data(&ext.data, uncompressed_size) {
-                    Some((decompressed, attempt)) => {
-                        Self::debug_dump("Decompressed data", &decompressed, 64);
-                        Self::process_decompressed_data(&decompressed, attempt)
-                            .unwrap_or_else(|_| json!({ "error": "Failed to process decompressed data" }))
-                    },
-                    None => json!({ "error": "Failed to decompress data after multiple attempts" }),
-                };
-                
-                // Add this block's result
-                results.push((msgpack_output, human_readable));
-            } else {
-                eprintln!("Skipping unsupported extension type: {}", ext.ext_type);
-                return Err(format!("Unsupported extension type: {}", ext.ext_type));
-            }
-        }
-        
-        // Generate output based on format and combine results
-        match output_format {
-            OutputFormat::Binary => {
-                // For binary output, just return the raw bytes of the first block
-                // This isn't ideal for a String result, but the caller can handle it
-                return Ok("Binary data generated, use stdout for binary output".to_string());
-            },
-            OutputFormat::Hex => {
-                // Return combined hex representation of all blocks
-                let combined = results.iter()
-                    .map(|(msgpack, _)| msgpack.iter().map(|b| format!("{:02x}", b)).collect::<String>())
-                    .collect::<Vec<_>>()
-                    .join("\n\n");
-                Ok(combined)
-            },
-            OutputFormat::Human => {
-                // Return human-readable JSON for all blocks
-                let combined_json = if results.len() == 1 {
-                    // Single block, just return the result
-                    results[0].1.clone()
-                } else {
-                    // Multiple blocks, combine into an array
-                    json!(results.iter().map(|(_, human)| human.clone()).collect::<Vec<_>>())
-                };
-                
-                Ok(serde_json::to_string_pretty(&combined_json)
-                    .map_err(|e| format!("Error formatting JSON: {}", e))?)
-            },
-            OutputFormat::Json => {
-                // Return full JSON with all details for all blocks
-                let result_array: Vec<JsonValue> = results.iter().enumerate().map(|(i, (msgpack, human))| {
-                    json!({
-                        "block_index": i,
-                        "messagepack_hex": msgpack.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
-                        "messagepack_length": msgpack.len(),
-                        "original_ext_type": blocks[i].ext_type,
-                        "original_header_data": blocks[i].header_data.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
-                        "original_data_length": blocks[i].data.len(),
-                        "human_readable": human
-                    })
-                }).collect();
-                
-                let final_result = if result_array.len() == 1 {
-                    result_array[0].clone()
-                } else {
-                    json!({
-                        "total_blocks": result_array.len(),
-                        "blocks": result_array
-                    })
-                };
-                
-                Ok(serde_json::to_string_pretty(&final_result)
-                    .map_err(|e| format!("Error formatting JSON: {}", e))?)
-            }
-        }
-    }
-    
-    /// Read input from a file, stdin, or use default data
-    fn read_input(source: Option<&str>) -> Result<String, String> {
-        match source {
-            Some("-") => {
-                // Read from stdin
-                let mut buffer = String::new();
-                io::stdin().read_to_string(&mut buffer)
-                    .map_err(|e| format!("Failed to read from stdin: {}", e))?;
-                Ok(buffer)
-            },
-            Some(path) => {
-                // Read from file
-                let mut file = File::open(path)
-                    .map_err(|e| format!("Failed to open file {}: {}", path, e))?;
-                let mut buffer = String::new();
-                file.read_to_string(&mut buffer)
-                    .map_err(|e| format!("Failed to read file {}: {}", path, e))?;
-                Ok(buffer)
-            },
-            None => {
-                // Use default test data
-                eprintln!("No input file specified, using default test data.");
-                Ok(include_str!("../default_input.json").to_string())
-            }
-        }
-    }
-    
-    /// Output binary data to stdout
-    pub fn write_binary_to_stdout(data: &[u8]) -> Result<(), String> {
-        io::stdout().write_all(data)
-            .map_err(|e| format!("Failed to write binary data: {}", e))
+fn main() {
+    // Exit with the error's code so scripts can tell failure classes apart
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        process::exit(e.code());
     }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn run() -> Result<(), Error> {
     // Parse command line arguments
     let args: Vec<String> = env::args().collect();
     
+    match args.get(1).map(String::as_str) {
+        Some("import-schema") => return import_schema(&args[2..]),
+        Some("generate-rust") => return generate_models(&args[2..]),
+        _ => {},
+    }
+
     // Show usage if --help or -h is provided
     if args.len() > 1 && (args[1] == "--help" || args[1] == "-h") {
-        println!("Usage: {} [INPUT_FILE|-] [FORMAT] [--debug]", args[0]);
+        println!(
+            "Usage: {} [INPUT_FILE|-] [FORMAT] [--binary=FORMAT] [--big-int=FORMAT] [--timestamps=FORMAT] [--schema=FILE [--type=NAME]] [--parallel] [--max-size=BYTES] [--no-limits] [--debug]",
+            args[0]
+        );
         println!("Formats: json (default), hex, binary, human");
         println!("  json   - Output detailed JSON with all metadata");
         println!("  hex    - Output just the hex representation of MessagePack data");
         println!("  binary - Output raw binary MessagePack data");
         println!("  human  - Output human-readable interpretation of the data");
+        println!("\nJSON Conversion:");
+        println!("  --binary=base64|hex|array  - How MessagePack binary is written (default: base64)");
+        println!("  --big-int=number|string    - Write integers beyond 2^53 as strings (default: number)");
+        println!("  --timestamps=FORMAT        - How timestamp extensions (C# DateTime) are written:");
+        println!("                               rfc3339 (default), tagged ({{\"$timestamp\": ...}}), seconds, millis or ext");
+        println!("  Non-string map keys are stringified and extensions are written as {{\"$ext\": type, \"data\": ...}}");
+        println!("\nNamed Fields:");
+        println!("  --schema=FILE      - Name the fields of [Key(n)] arrays using a schema file");
+        println!("  --type=NAME        - Schema type of the top-level value (default: the schema's root)");
+        println!("  --problem-details  - Decode the value as an RFC 7807 ProblemDetails array");
+        println!("\nPerformance:");
+        println!("  --parallel         - Decompress the blocks of large payloads on all cores");
+        println!("                       (needs a build with `--features parallel`)");
+        println!("\nLimits (the defaults are safe for untrusted input):");
+        println!("  --max-size=BYTES          - Total decompressed size (default: 268435456, 256 MiB)");
+        println!("  --max-block-size=BYTES    - Decompressed size of one LZ4 block (default: 16777216, 16 MiB)");
+        println!("  --max-blocks=N            - Blocks in one envelope (default: 65536)");
+        println!("  --max-depth=N             - Nesting depth of arrays, maps and Typeless values (default: 128)");
+        println!("  --max-collection-len=N    - Elements of one array or map (default: 16777216)");
+        println!("  --no-limits               - Disable every limit, for trusted input only;");
+        println!("                              --max-* flags still apply on top of it");
+        println!("\nSchema Import:");
+        println!("  {} import-schema PATH... [--root=NAME] [--output=FILE]", args[0]);
+        println!("  Scans .cs files (directories are searched recursively) for [MessagePackObject] and [Union]");
+        println!("  contracts and writes the schema used by --schema to stdout or FILE");
+        println!("\nCode Generation:");
+        println!("  {} generate-rust SCHEMA.json|PATH... [--root=NAME] [--output=FILE]", args[0]);
+        println!("  Writes Rust models for a schema file or for C# contracts; with --root (or the schema's root),");
+        println!("  only the types reachable from it are generated");
         println!("\nInput Formats Supported:");
         println!("  - LZ4BlockArray JSON (standard format with 'type' and 'buffer' fields)");
         println!("  - Regular JSON data (will be converted to MessagePack)");
         println!("  - Hexadecimal data (will be parsed as binary)");
-        println!("  - Raw MessagePack data");
+        println!("  - Raw MessagePack data (binary LZ4 envelopes or uncompressed MessagePack)");
         println!("  - Multiple LZ4 blocks in a single file");
         println!("\nExamples:");
         println!("  {} input.json            # Process file with JSON output", args[0]);
         println!("  {} input.json human      # Process file with human-readable output", args[0]);
         println!("  {} human                 # Process default data with human-readable output", args[0]);
         println!("  cat input.json | {} -    # Process stdin with JSON output", args[0]);
+        println!("  {} response.msgpack human   # Process a binary MessagePack capture", args[0]);
         println!("  {} input.json json --debug  # Process with detailed debug output", args[0]);
+        println!("  {} response.msgpack human --schema=contracts.json --type=RouteResponse", args[0]);
+        println!("  {} import-schema src/Contracts --output=contracts.json", args[0]);
+        println!("  {} generate-rust src/Contracts --root=RouteResponse --output=src/contracts.rs", args[0]);
+        println!("\nExit Codes:");
+        println!("  0  - Success");
+        println!("  1  - Failed to read input or write output");
+        println!("  2  - Malformed input, JSON or envelope");
+        println!("  3  - Invalid extension header");
+        println!("  4  - Header block count does not match the blocks");
+        println!("  5  - Block decompressed to a different length than declared");
+        println!("  6  - Corrupt LZ4 block");
+        println!("  7  - Unsupported extension type");
+        println!("  8  - Unsupported compression mode");
+        println!("  9  - Truncated or malformed MessagePack");
+        println!("  10 - Failed to encode the output");
+        println!("  13 - Input exceeds a decoding limit");
         return Ok(());
     }
     
@@ -987,10 +108,42 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse input file and output format
     let mut input_file = None;
     let mut output_format = OutputFormat::Json;
+    let mut json_options = JsonOptions::default();
+    let mut root_type = None;
+    if args.iter().any(|arg| arg == "--no-limits") {
+        json_options.limits = Limits::unlimited();
+    }
     
     for arg in &args[1..] {
-        if arg == "--debug" {
+        if arg == "--debug" || arg == "--no-limits" {
             continue;
+        } else if let Some(format) = arg.strip_prefix("--binary=") {
+            json_options.binary = format.parse()?;
+        } else if let Some(format) = arg.strip_prefix("--big-int=") {
+            json_options.big_integers = format.parse()?;
+        } else if let Some(format) = arg.strip_prefix("--timestamps=") {
+            json_options.timestamps = format.parse()?;
+        } else if let Some(path) = arg.strip_prefix("--schema=") {
+            json_options.schema = Some(Schema::from_file(path)?);
+        } else if let Some(name) = arg.strip_prefix("--type=") {
+            root_type = Some(name.to_string());
+        } else if arg == "--problem-details" {
+            json_options.schema = Some(Schema::problem_details());
+        } else if arg == "--parallel" {
+            if !cfg!(feature = "parallel") {
+                return Err(Error::InvalidInput("--parallel needs a build with `--features parallel`".to_string()));
+            }
+            json_options.parallel = true;
+        } else if let Some(value) = arg.strip_prefix("--max-size=") {
+            json_options.limits.max_uncompressed_len = parse_limit("--max-size", value)?;
+        } else if let Some(value) = arg.strip_prefix("--max-block-size=") {
+            json_options.limits.max_block_len = parse_limit("--max-block-size", value)?;
+        } else if let Some(value) = arg.strip_prefix("--max-blocks=") {
+            json_options.limits.max_blocks = parse_limit("--max-blocks", value)?;
+        } else if let Some(value) = arg.strip_prefix("--max-depth=") {
+            json_options.limits.max_depth = parse_limit("--max-depth", value)?;
+        } else if let Some(value) = arg.strip_prefix("--max-collection-len=") {
+            json_options.limits.max_collection_len = parse_limit("--max-collection-len", value)?;
         } else if ["human", "hex", "binary", "json"].contains(&arg.as_str()) {
             output_format = OutputFormat::from(arg.as_str());
         } else if input_file.is_none() {
@@ -998,28 +151,88 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
     
-    // Process the input
-    let result = LZ4MessagePackProcessor::process(input_file, output_format.clone())?;
-    
-    // Handle special case for binary output
+    if let Some(root_type) = root_type {
+        let schema = json_options.schema.take()
+            .ok_or_else(|| Error::InvalidInput("--type requires --schema".to_string()))?;
+        json_options.schema = Some(schema.with_root(root_type)?);
+    }
+
+    // Binary output is written straight to stdout, one envelope after another
     if output_format == OutputFormat::Binary {
-        // For binary output, we need to reprocess to get the actual bytes
-        let blocks = LZ4MessagePackProcessor::process_input(
-            &LZ4MessagePackProcessor::read_input(input_file)?
-        )?;
-        
-        // Process all blocks and write them to stdout
-        for (i, ext) in blocks.iter().enumerate() {
+        for (i, msgpack_output) in LZ4MessagePackProcessor::process_binary_with(input_file, &json_options)?.iter().enumerate() {
             if debug_mode {
                 eprintln!("Writing block {} to stdout...", i+1);
             }
-            let msgpack_output = LZ4MessagePackProcessor::reserialize_to_msgpack(ext)?;
-            LZ4MessagePackProcessor::write_binary_to_stdout(&msgpack_output)?;
+            LZ4MessagePackProcessor::write_binary_to_stdout(msgpack_output)?;
         }
     } else {
         // For text-based outputs, just print the result
+        let result = LZ4MessagePackProcessor::process_with(input_file, output_format, &json_options)?;
         println!("{}", result);
     }
     
     Ok(())
-} 
\ No newline at end of file
+} 
+
+/// Parse the value of a `--max-*` flag
+fn parse_limit(flag: &str, value: &str) -> Result<usize, Error> {
+    value.parse()
+        .map_err(|_| Error::InvalidInput(format!("{} expects a non-negative integer, got '{}'", flag, value)))
+}
+
+/// Options shared by the subcommands that read C# sources
+struct SourceArgs<'a> {
+    paths: Vec<PathBuf>,
+    root: Option<&'a str>,
+    output: Option<&'a str>,
+}
+
+fn parse_source_args<'a>(command: &str, args: &'a [String]) -> Result<SourceArgs<'a>, Error> {
+    let mut parsed = SourceArgs { paths: Vec::new(), root: None, output: None };
+    for arg in args {
+        if let Some(name) = arg.strip_prefix("--root=") {
+            parsed.root = Some(name);
+        } else if let Some(path) = arg.strip_prefix("--output=") {
+            parsed.output = Some(path);
+        } else {
+            parsed.paths.push(PathBuf::from(arg));
+        }
+    }
+    if parsed.paths.is_empty() {
+        return Err(Error::InvalidInput(format!("{} needs at least one input file or directory", command)));
+    }
+    Ok(parsed)
+}
+
+fn write_output(output: Option<&str>, text: &str) -> Result<(), Error> {
+    match output {
+        Some(path) => fs::write(path, text).map_err(|e| Error::io(format!("Failed to write {}", path), e)),
+        None => {
+            print!("{}", text);
+            Ok(())
+        },
+    }
+}
+
+/// Build a schema from C# sources: `import-schema PATH... [--root=NAME] [--output=FILE]`
+fn import_schema(args: &[String]) -> Result<(), Error> {
+    let args = parse_source_args("import-schema", args)?;
+    let schema = import_schema_from_paths(&args.paths, args.root)?;
+    write_output(args.output, &(serde_json::to_string_pretty(&schema.to_json())? + "\n"))
+}
+
+/// Generate Rust models: `generate-rust SCHEMA.json|PATH... [--root=NAME] [--output=FILE]`
+fn generate_models(args: &[String]) -> Result<(), Error> {
+    let args = parse_source_args("generate-rust", args)?;
+    let schema = match args.paths.as_slice() {
+        [path] if path.extension().is_some_and(|ext| ext == "json") => {
+            let schema = Schema::from_file(path)?;
+            match args.root {
+                Some(root) => schema.with_root(root)?,
+                None => schema,
+            }
+        },
+        paths => import_schema_from_paths(paths, args.root)?,
+    };
+    write_output(args.output, &generate_rust(&schema)?)
+}
@@ -1,7 +1,38 @@
+//! Route models and the pattern for models shared with the C# services.
+//!
+//! MessagePack-CSharp models annotated with integer `[Key(n)]` attributes are
+//! written as positional arrays, so a Rust model declares its fields in key
+//! order and derives `Serialize`/`Deserialize` without renaming them:
+//!
+//! ```text
+//! [MessagePackObject]                 #[derive(Serialize, Deserialize)]
+//! public class Waypoint {             pub struct Waypoint {
+//!     [Key(0)] public string Hint;        pub hint: String,
+//!     [Key(1)] public double Distance;    pub distance: f64,
+//! }                                   }
+//! ```
+//!
+//! A gap in the key numbering is a `nil` in the array; fill it with an
+//! `Option<()>` field. [`to_msgpack`] writes arrays by default and maps keyed
+//! by field name (the `[Key("name")]` layout) with [`StructEncoding::Map`];
+//! [`from_msgpack`] reads either.
+
+use serde::de::DeserializeOwned;
 use serde::{Serialize, Deserialize};
-use rmp_serde::{Serializer, Deserializer};
+use rmp_serde::Deserializer;
 use std::io::Cursor;
-use std::error::Error;
+
+use super::{Error, Result};
+
+/// How structs are laid out in MessagePack
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StructEncoding {
+    /// Positional arrays in field order, like C# `[Key(0)]`, `[Key(1)]`, ...
+    #[default]
+    Array,
+    /// Maps keyed by field name, like C# `[Key("name")]`
+    Map,
+}
 
 /// Root response structure
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -43,7 +74,6 @@ pub struct Step {
     pub weight: f64,
     pub duration: f64,
     pub distance: f64,
-    pub ref_: Option<String>,
     #[serde(rename = "ref")]
     pub ref_field: Option<String>,
 }
@@ -66,7 +96,6 @@ pub struct Intersection {
     pub entry: Vec<bool>,
     pub bearings: Vec<i32>,
     pub location: [f64; 2],
-    pub in_: Option<i32>,
     #[serde(rename = "in")]
     pub in_field: Option<i32>,
 }
@@ -80,42 +109,60 @@ pub struct Waypoint {
     pub location: [f64; 2],
 }
 
-/// Helper functions for routing model serialization/deserialization
+/// Serialize any model to MessagePack with the given struct layout
+pub fn to_msgpack<T: Serialize + ?Sized>(value: &T, encoding: StructEncoding) -> Result<Vec<u8>> {
+    let result = match encoding {
+        StructEncoding::Array => rmp_serde::to_vec(value),
+        StructEncoding::Map => rmp_serde::to_vec_named(value),
+    };
+    result.map_err(|e| Error::Encode(format!("Failed to serialize to MessagePack: {}", e)))
+}
+
+/// Deserialize any model from MessagePack written as arrays or as maps
+pub fn from_msgpack<T: DeserializeOwned>(data: &[u8]) -> Result<T> {
+    let mut de = Deserializer::new(Cursor::new(data));
+    T::deserialize(&mut de)
+        .map_err(|e| Error::InvalidMessagePack { offset: de.get_ref().position(), message: e.to_string() })
+}
+
+// Helper functions for routing model serialization/deserialization
 
 /// Parse a JSON string into a RouteResponse struct
-pub fn parse_route_json(json_str: &str) -> Result<RouteResponse, Box<dyn Error>> {
+pub fn parse_route_json(json_str: &str) -> Result<RouteResponse> {
     let route_response: RouteResponse = serde_json::from_str(json_str)?;
     Ok(route_response)
 }
 
 /// Convert a RouteResponse struct to a JSON string
-pub fn route_to_json(route: &RouteResponse) -> Result<String, Box<dyn Error>> {
-    let json = serde_json::to_string(route)?;
+pub fn route_to_json(route: &RouteResponse) -> Result<String> {
+    let json = serde_json::to_string(route)
+        .map_err(|e| Error::Encode(format!("Failed to serialize route to JSON: {}", e)))?;
     Ok(json)
 }
 
-/// Serialize a RouteResponse struct to MessagePack format
-pub fn route_to_msgpack(route: &RouteResponse) -> Result<Vec<u8>, Box<dyn Error>> {
-    let mut buf = Vec::new();
-    route.serialize(&mut Serializer::new(&mut buf))?;
-    Ok(buf)
+/// Serialize a RouteResponse struct to MessagePack format, as positional arrays
+pub fn route_to_msgpack(route: &RouteResponse) -> Result<Vec<u8>> {
+    route_to_msgpack_with(route, StructEncoding::Array)
 }
 
-/// Deserialize MessagePack data to a RouteResponse struct
-pub fn msgpack_to_route(data: &[u8]) -> Result<RouteResponse, Box<dyn Error>> {
-    let mut de = Deserializer::new(Cursor::new(data));
-    let route = RouteResponse::deserialize(&mut de)?;
-    Ok(route)
+/// Serialize a RouteResponse struct to MessagePack format with the given struct layout
+pub fn route_to_msgpack_with(route: &RouteResponse, encoding: StructEncoding) -> Result<Vec<u8>> {
+    to_msgpack(route, encoding)
+}
+
+/// Deserialize MessagePack data, written as arrays or as maps, to a RouteResponse struct
+pub fn msgpack_to_route(data: &[u8]) -> Result<RouteResponse> {
+    from_msgpack(data)
 }
 
 /// Convert MessagePack data to JSON string
-pub fn msgpack_to_json(data: &[u8]) -> Result<String, Box<dyn Error>> {
+pub fn msgpack_to_json(data: &[u8]) -> Result<String> {
     let route = msgpack_to_route(data)?;
     route_to_json(&route)
 }
 
 /// Convert JSON string to MessagePack data
-pub fn json_to_msgpack(json_str: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+pub fn json_to_msgpack(json_str: &str) -> Result<Vec<u8>> {
     let route = parse_route_json(json_str)?;
     route_to_msgpack(&route)
 } 
\ No newline at end of file
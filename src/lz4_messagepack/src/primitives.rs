@@ -0,0 +1,222 @@
+//! MessagePack-CSharp encodings of .NET value types.
+//!
+//! The standard resolver writes `Guid` as its 36-character string, `decimal`
+//! as its invariant-culture string, `TimeSpan` as an int64 tick count and
+//! `DateTimeOffset` as `[DateTime, offset minutes]`, where the `DateTime`
+//! holds the local clock time. `NativeGuidResolver` and
+//! `NativeDecimalResolver` write the raw 16-byte memory of the value as
+//! `bin` instead. Schema fields typed `guid`, `decimal`, `timespan` and
+//! `datetimeoffset` use these conversions.
+
+use rmpv::Value;
+
+use super::{Error, Result, Timestamp};
+
+/// 100 ns ticks in a second
+const TICKS_PER_SECOND: u64 = 10_000_000;
+
+/// Lowercase `8-4-4-4-12` form of a Guid written as a string or as 16 raw bytes
+pub(crate) fn guid_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => parse_guid(s.as_str()?).map(|bytes| format_guid(&bytes)),
+        Value::Binary(data) => Some(format_guid(data.as_slice().try_into().ok()?)),
+        _ => None,
+    }
+}
+
+/// The 36-character string MessagePack-CSharp writes for a Guid given in the `D`, `N` or `B` format
+pub(crate) fn guid_from_str(s: &str) -> Result<Value> {
+    parse_guid(s)
+        .map(|bytes| Value::from(format_guid(&bytes)))
+        .ok_or_else(|| Error::InvalidInput(format!("Invalid Guid: {:?}", s)))
+}
+
+/// `Guid.ToByteArray()` order: the first three groups are little-endian
+fn format_guid(bytes: &[u8; 16]) -> String {
+    let hex = |range: &[u8]| range.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    let reversed = |range: &[u8]| hex(&range.iter().rev().copied().collect::<Vec<_>>());
+    format!(
+        "{}-{}-{}-{}-{}",
+        reversed(&bytes[0..4]),
+        reversed(&bytes[4..6]),
+        reversed(&bytes[6..8]),
+        hex(&bytes[8..10]),
+        hex(&bytes[10..16])
+    )
+}
+
+fn parse_guid(s: &str) -> Option<[u8; 16]> {
+    let s = s.strip_prefix('{').and_then(|s| s.strip_suffix('}')).unwrap_or(s);
+    let digits: String = match s.len() {
+        36 if [8, 13, 18, 23].iter().all(|&i| s.as_bytes()[i] == b'-') => s.replace('-', ""),
+        32 => s.to_string(),
+        _ => return None,
+    };
+    if digits.len() != 32 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut bytes = [0u8; 16];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    bytes[0..4].reverse();
+    bytes[4..6].reverse();
+    bytes[6..8].reverse();
+    Some(bytes)
+}
+
+/// Exact decimal string of a `decimal` written as a string or as its 16-byte memory
+pub(crate) fn decimal_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => s.as_str().filter(|s| is_decimal(s)).map(str::to_string),
+        Value::Binary(data) if data.len() == 16 => {
+            // flags, hi32, lo32, mid32, all little-endian
+            let word = |i: usize| u32::from_le_bytes(data[i * 4..i * 4 + 4].try_into().unwrap());
+            let (flags, hi, lo, mid) = (word(0), word(1), word(2), word(3));
+            let scale = ((flags >> 16) & 0xFF) as usize;
+            if scale > 28 {
+                return None;
+            }
+            let digits = ((u128::from(hi) << 64) | (u128::from(mid) << 32) | u128::from(lo)).to_string();
+            let digits = format!("{:0>width$}", digits, width = scale + 1);
+            let (whole, fraction) = digits.split_at(digits.len() - scale);
+            let sign = if flags & 0x8000_0000 != 0 { "-" } else { "" };
+            match fraction {
+                "" => Some(format!("{}{}", sign, whole)),
+                fraction => Some(format!("{}{}.{}", sign, whole, fraction)),
+            }
+        },
+        _ => None,
+    }
+}
+
+/// The string MessagePack-CSharp writes for a `decimal`
+pub(crate) fn decimal_from_str(s: &str) -> Result<Value> {
+    if is_decimal(s) {
+        Ok(Value::from(s))
+    } else {
+        Err(Error::InvalidInput(format!("Invalid decimal: {:?}", s)))
+    }
+}
+
+/// Whether `s` is an optionally signed number with an optional fraction, as `decimal.ToString()` writes
+fn is_decimal(s: &str) -> bool {
+    let unsigned = s.strip_prefix(['-', '+']).unwrap_or(s);
+    let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, "0"));
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    digits(whole) && digits(fraction)
+}
+
+/// ISO 8601 duration of a `TimeSpan` tick count, such as `P1DT2H30M` or `-PT0.5S`
+pub(crate) fn timespan_to_iso(ticks: i64) -> String {
+    let total = ticks.unsigned_abs();
+    let (seconds, fraction) = (total / TICKS_PER_SECOND, total % TICKS_PER_SECOND);
+    let (days, hours, minutes, seconds) = (seconds / 86_400, seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+
+    let mut iso = String::from(if ticks < 0 { "-P" } else { "P" });
+    if days > 0 {
+        iso.push_str(&format!("{}D", days));
+    }
+    if hours > 0 || minutes > 0 || seconds > 0 || fraction > 0 || days == 0 {
+        iso.push('T');
+        if hours > 0 {
+            iso.push_str(&format!("{}H", hours));
+        }
+        if minutes > 0 {
+            iso.push_str(&format!("{}M", minutes));
+        }
+        if seconds > 0 || fraction > 0 || (hours == 0 && minutes == 0) {
+            iso.push_str(&seconds.to_string());
+            if fraction > 0 {
+                iso.push_str(format!(".{:07}", fraction).trim_end_matches('0'));
+            }
+            iso.push('S');
+        }
+    }
+    iso
+}
+
+/// `TimeSpan` tick count of an ISO 8601 duration made of days, hours, minutes and seconds
+///
+/// Only the seconds may have a fraction, of up to seven digits, as [`timespan_to_iso`] writes them.
+pub(crate) fn timespan_from_iso(s: &str) -> Result<i64> {
+    parse_duration(s).ok_or_else(|| Error::InvalidInput(format!("Invalid ISO 8601 duration: {:?}", s)))
+}
+
+fn parse_duration(s: &str) -> Option<i64> {
+    let (negative, rest) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let rest = rest.strip_prefix('P')?;
+    let (date, time) = match rest.split_once('T') {
+        Some((_, "")) => return None,
+        Some((date, time)) => (date, Some(time)),
+        None => (rest, None),
+    };
+
+    let mut ticks: i128 = 0;
+    let mut any = false;
+    let mut add = |part: &str, designators: &[(char, u64)]| -> Option<()> {
+        let mut remaining = part;
+        let mut allowed = designators;
+        while !remaining.is_empty() {
+            let end = remaining.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+            let (number, designator) = (&remaining[..end], remaining[end..].chars().next()?);
+            let position = allowed.iter().position(|(d, _)| *d == designator)?;
+            let unit = allowed[position].1;
+            allowed = &allowed[position + 1..];
+
+            let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+            if whole.is_empty() || !whole.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            if !fraction.is_empty() && (designator != 'S' || fraction.len() > 7
+                || !fraction.bytes().all(|b| b.is_ascii_digit()))
+            {
+                return None;
+            }
+            ticks = whole.parse::<i128>().ok()?.checked_mul(i128::from(unit))?.checked_add(ticks)?;
+            // The fraction is of a second, so seven digits are the ticks
+            if !fraction.is_empty() {
+                ticks = ticks.checked_add(format!("{:0<7}", fraction).parse::<i128>().ok()?)?;
+            }
+            remaining = &remaining[end + 1..];
+            any = true;
+        }
+        Some(())
+    };
+
+    add(date, &[('D', 86_400 * TICKS_PER_SECOND)])?;
+    if let Some(time) = time {
+        add(time, &[('H', 3600 * TICKS_PER_SECOND), ('M', 60 * TICKS_PER_SECOND), ('S', TICKS_PER_SECOND)])?;
+    }
+    if !any {
+        return None;
+    }
+    i64::try_from(if negative { -ticks } else { ticks }).ok()
+}
+
+/// RFC 3339 string with offset of a `DateTimeOffset` written as `[DateTime, offset minutes]`
+pub(crate) fn datetimeoffset_to_string(value: &Value) -> Option<String> {
+    let [local, offset] = value.as_array()?.as_slice() else {
+        return None;
+    };
+    let local = match local {
+        Value::Integer(i) => Timestamp::from_datetime_binary(i.as_i64()?),
+        other => Timestamp::from_value(other)?,
+    };
+    let offset = i32::try_from(offset.as_i64()?).ok()?;
+    let instant = Timestamp::new(local.seconds().checked_sub(i64::from(offset) * 60)?, local.nanos())?;
+    Some(instant.to_rfc3339_with_offset(offset))
+}
+
+/// `[DateTime, offset minutes]` for an RFC 3339 string, with the local clock time in the `DateTime`
+pub(crate) fn datetimeoffset_from_str(s: &str) -> Result<Value> {
+    let (instant, offset) = Timestamp::parse_with_offset(s)?;
+    let local = instant.seconds().checked_add(i64::from(offset) * 60)
+        .and_then(|seconds| Timestamp::new(seconds, instant.nanos()))
+        .ok_or_else(|| Error::InvalidInput(format!("Invalid DateTimeOffset: {:?}", s)))?;
+    Ok(Value::Array(vec![local.to_value(), Value::from(offset)]))
+}
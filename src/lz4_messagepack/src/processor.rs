@@ -0,0 +1,544 @@
+use rmpv::encode::write_value;
+use rmpv::decode::read_value;
+use rmpv::Value;
+use std::io::{self, Read, Write, Cursor};
+use std::fs::File;
+use serde_json::{json, Value as JsonValue};
+
+use super::{
+    decode_any_with, json_to_value_with, value_to_json_with, Compression, Envelope, Error, JsonOptions, MsgpackOptions, Result,
+    LZ4_BLOCK_ARRAY_EXT, LZ4_BLOCK_EXT,
+};
+
+/// Represents output format options
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputFormat {
+    Json,
+    Hex,
+    Binary,
+    Human,
+}
+
+impl From<&str> for OutputFormat {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "hex" => OutputFormat::Hex,
+            "binary" => OutputFormat::Binary,
+            "human" => OutputFormat::Human,
+            _ => OutputFormat::Json,
+        }
+    }
+}
+
+/// Core functionality for processing LZ4 MessagePack data
+pub struct LZ4MessagePackProcessor;
+
+impl LZ4MessagePackProcessor {
+    /// Process the decompressed data and convert to a more readable format
+    fn process_decompressed_data(decompressed: &[u8], options: &JsonOptions) -> Result<JsonValue> {
+        Self::debug_print(&format!("Decompression succeeded, got {} bytes", decompressed.len()));
+        Self::debug_dump("First bytes of decompressed data", decompressed, 32);
+
+        // Return error for empty data
+        if decompressed.is_empty() {
+            return Err(Error::InvalidMessagePack { offset: 0, message: "Empty data after decompression".to_string() });
+        }
+        options.limits.check_structure(decompressed)?;
+
+        // Try to parse as MessagePack with error recovery
+        let mut cursor = Cursor::new(decompressed);
+        match read_value(&mut cursor) {
+            Ok(value) => {
+                Self::debug_print("Successfully parsed MessagePack data");
+
+                // Convert to JSON, naming positional fields when a schema is given
+                Ok(value_to_json_with(&value, options))
+            },
+            Err(e) => {
+                Self::debug_print(&format!("Failed to parse decompressed data as MessagePack: {}", e));
+
+                // Try partial parsing - read as many values as possible
+                Self::debug_print("Attempting partial parsing of MessagePack data");
+                let partial_values = Self::parse_partial_messagepack(decompressed, options);
+                if !partial_values.is_empty() {
+                    Self::debug_print(&format!("Successfully parsed {} partial MessagePack values", partial_values.len()));
+                    return Ok(json!(partial_values));
+                }
+
+                // Try to interpret as UTF-8 string
+                match String::from_utf8(decompressed.to_vec()) {
+                    Ok(s) => {
+                        if s.chars().any(|c| !c.is_control()) {
+                            Self::debug_print("Interpreted as UTF-8 string");
+
+                            // Try to parse as JSON if it looks like JSON
+                            if s.trim().starts_with('{') || s.trim().starts_with('[') {
+                                match serde_json::from_str::<JsonValue>(&s) {
+                                    Ok(parsed_json) => {
+                                        Self::debug_print("Successfully parsed as JSON");
+                                        return Ok(parsed_json);
+                                    },
+                                    Err(json_err) => {
+                                        Self::debug_print(&format!("Failed to parse as JSON: {}", json_err));
+                                    }
+                                }
+                            }
+
+                            // Return as raw string
+                            Ok(json!({ "raw_string": s }))
+                        } else {
+                            // Return data summary if no readable string
+                            Ok(Self::summarize_binary_data(decompressed))
+                        }
+                    },
+                    Err(_) => {
+                        // Return binary data summary
+                        Self::debug_print("Not valid UTF-8, returning binary data summary");
+                        Ok(Self::summarize_binary_data(decompressed))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Try to parse as many MessagePack values as possible from a byte stream
+    fn parse_partial_messagepack(data: &[u8], options: &JsonOptions) -> Vec<JsonValue> {
+        let mut result = Vec::new();
+        let mut offset = 0;
+
+        while offset < data.len() {
+            // Try to read a single value
+            let mut cursor = Cursor::new(&data[offset..]);
+            match read_value(&mut cursor) {
+                Ok(value) => {
+                    let consumed = cursor.position() as usize;
+                    if consumed == 0 {
+                        // No progress made, move to next byte
+                        offset += 1;
+                    } else {
+                        // Successfully read a value
+                        result.push(value_to_json_with(&value, options));
+                        offset += consumed;
+                    }
+                },
+                Err(_) => {
+                    // Failed to read value, skip this byte
+                    offset += 1;
+                }
+            }
+
+            // Limit the number of values we extract to avoid excessive processing
+            if result.len() >= 100 {
+                break;
+            }
+        }
+
+        result
+    }
+
+    /// Create a summary of binary data
+    fn summarize_binary_data(data: &[u8]) -> JsonValue {
+        // Calculate some basic statistics
+        let total_bytes = data.len();
+        let zero_bytes = data.iter().filter(|&&b| b == 0).count();
+        let text_bytes = data.iter().filter(|&&b| (32..=126).contains(&b) || b == 9 || b == 10 || b == 13).count();
+        let control_bytes = data.iter().filter(|&&b| b < 32 && b != 9 && b != 10 && b != 13).count();
+        let high_bytes = data.iter().filter(|&&b| b > 127).count();
+
+        // Get histogram of byte values for analysis
+        let mut byte_histogram = [0u32; 256];
+        for &b in data {
+            byte_histogram[b as usize] += 1;
+        }
+
+        // Find most common bytes
+        let mut common_bytes = Vec::new();
+        for (byte, &count) in byte_histogram.iter().enumerate() {
+            if count > 0 {
+                common_bytes.push((byte, count));
+            }
+        }
+        common_bytes.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+        // Take top 10 most common bytes
+        let top_bytes: Vec<_> = common_bytes.iter().take(10).map(|&(b, c)| {
+            json!({
+                "byte": b,
+                "hex": format!("0x{:02x}", b),
+                "ascii": if (32..=126).contains(&b) {
+                    // Convert to u32 first, then to char
+                    let ch = char::from_u32(b as u32).unwrap_or('?');
+                    format!("{}", ch)
+                } else {
+                    "N/A".to_string()
+                },
+                "count": c,
+                "percentage": format!("{:.2}%", (c as f64 / total_bytes as f64) * 100.0)
+            })
+        }).collect();
+
+        // Create a summary of the data
+        json!({
+            "summary": "Binary data",
+            "total_bytes": total_bytes,
+            "zero_bytes": zero_bytes,
+            "zero_percentage": format!("{:.2}%", (zero_bytes as f64 / total_bytes as f64) * 100.0),
+            "ascii_text_bytes": text_bytes,
+            "text_percentage": format!("{:.2}%", (text_bytes as f64 / total_bytes as f64) * 100.0),
+            "control_bytes": control_bytes,
+            "high_bytes": high_bytes,
+            "most_common_bytes": top_bytes,
+            "first_32_bytes": data.iter().take(32).map(|&b| format!("{:02x}", b)).collect::<Vec<_>>().join(" "),
+            "appears_to_be": if text_bytes > total_bytes * 3 / 4 {
+                "Mostly ASCII text"
+            } else if zero_bytes > total_bytes / 3 {
+                "Contains many zero bytes (possibly padded data or null-terminated strings)"
+            } else if high_bytes > total_bytes / 2 {
+                "Contains many high bytes (possibly compressed or encrypted data)"
+            } else {
+                "General binary data"
+            }
+        })
+    }
+
+    /// Analyze input data to determine its format
+    fn analyze_input_format(input: &[u8], options: &JsonOptions) -> Result<String> {
+        // Binary envelopes are recognized by their leading MessagePack markers
+        if Envelope::detect(input) {
+            Self::debug_print("Input appears to be a binary LZ4 envelope");
+            return Ok("binary_envelope".to_string());
+        }
+
+        if let Ok(input_json) = std::str::from_utf8(input) {
+            // Try to parse as JSON first
+            if let Ok(json_value) = serde_json::from_str::<JsonValue>(input_json) {
+                // Check if it's our expected format (array with objects)
+                if let Some(array) = json_value.as_array() {
+                    if !array.is_empty() {
+                        Self::debug_print(&format!("Input appears to be in JSON format with {} elements", array.len()));
+
+                        // Check if it follows our LZ4BlockArray format
+                        let has_type = array.iter().any(|item| item.get("type").is_some());
+                        let has_buffer = array.iter().any(|item| item.get("buffer").is_some());
+
+                        if has_type && has_buffer {
+                            return Ok("lz4_block_array".to_string());
+                        } else {
+                            return Ok("json_array".to_string());
+                        }
+                    }
+                } else if json_value.get("type").is_some_and(JsonValue::is_number) && json_value.get("buffer").is_some() {
+                    Self::debug_print("Input appears to be a single MessagePack extension object");
+                    return Ok("lz4_block_array".to_string());
+                } else if json_value.is_object() {
+                    Self::debug_print("Input appears to be a JSON object");
+                    return Ok("json_object".to_string());
+                }
+            }
+
+            // Check if it might be binary data encoded as text
+            let hex_chars = input_json.chars().filter(|c| c.is_ascii_hexdigit()).count();
+            if hex_chars > (input_json.len() as f64 * 0.8) as usize {
+                Self::debug_print("Input appears to be hexadecimal data");
+                return Ok("hex_data".to_string());
+            }
+        }
+
+        // Check if the whole input is a single uncompressed MessagePack value; one beyond the limits is reported as such
+        if matches!(decode_any_with(input, options), Ok(_) | Err(Error::LimitExceeded { .. })) {
+            Self::debug_print("Input appears to be raw MessagePack data");
+            return Ok("messagepack".to_string());
+        }
+
+        // Default to our standard format
+        Self::debug_print("Input format not clearly identifiable, processing as standard LZ4BlockArray");
+        Ok("lz4_block_array".to_string())
+    }
+
+    /// Parse and process input in a format-aware manner
+    ///
+    /// Plain JSON is encoded through the schema in `options`, if any, so named
+    /// objects and `$type` unions become the arrays C# reads.
+    fn process_input(input: &[u8], options: &JsonOptions) -> Result<Vec<Envelope>> {
+        // First analyze the format
+        let format = Self::analyze_input_format(input, options)?;
+
+        match format.as_str() {
+            "lz4_block_array" => {
+                // Use our standard parser
+                Self::parse_input(Self::input_text(input)?)
+            },
+            "binary_envelope" | "messagepack" => Self::parse_binary(input, options),
+            "json_array" | "json_object" => {
+                // For regular JSON, we'll need to convert it to our format first
+                Self::debug_print("Converting JSON data to LZ4BlockArray format...");
+                let json_value: JsonValue = serde_json::from_slice(input)?;
+
+                // Serialize the JSON to MessagePack
+                let mut msgpack_data = Vec::new();
+                let msgpack_options = MsgpackOptions { schema: options.schema.clone(), ..Default::default() };
+                write_value(&mut msgpack_data, &json_to_value_with(&json_value, &msgpack_options)?)
+                    .map_err(|e| Error::Encode(format!("Failed to serialize to MessagePack: {}", e)))?;
+
+                // Compress it into a Lz4BlockArray envelope
+                Ok(vec![Envelope::compress(&msgpack_data, Compression::Lz4BlockArray)?])
+            },
+            "hex_data" => {
+                // Try to parse hex data
+                Self::debug_print("Attempting to parse hexadecimal data...");
+                let mut hex_data = Vec::new();
+
+                // Strip non-hex characters
+                let cleaned_input: Vec<u8> = input.iter()
+                    .copied()
+                    .filter(u8::is_ascii_hexdigit)
+                    .collect();
+
+                // Convert hex to bytes
+                for pair in cleaned_input.chunks_exact(2) {
+                    let byte_str = std::str::from_utf8(pair).unwrap_or_default();
+                    if let Ok(byte) = u8::from_str_radix(byte_str, 16) {
+                        hex_data.push(byte);
+                    }
+                }
+
+                if hex_data.is_empty() {
+                    return Err(Error::InvalidInput("Failed to parse hex data".to_string()));
+                }
+
+                // The bytes hold either an LZ4 envelope or uncompressed MessagePack
+                Self::parse_binary(&hex_data, options)
+            },
+            _ => {
+                // Default to our standard parser but with a warning
+                Self::debug_print("Warning: Unrecognized format, attempting standard parsing...");
+                Self::parse_input(Self::input_text(input)?)
+            }
+        }
+    }
+
+    /// Parse the Node-style JSON input into its envelopes
+    ///
+    /// A lone extension that is not an LZ4 envelope, such as a Typeless
+    /// ext 100 value, is uncompressed MessagePack and is compressed into a
+    /// Lz4BlockArray envelope, the same way uncompressed binary input is.
+    fn parse_input(input_json: &str) -> Result<Vec<Envelope>> {
+        let parsed: JsonValue = serde_json::from_str(input_json)?;
+        Envelope::parse_json(&parsed)?
+            .into_iter()
+            .map(|envelope| match envelope.ext_type() {
+                LZ4_BLOCK_ARRAY_EXT | LZ4_BLOCK_EXT => Ok(envelope),
+                ext_type if envelope.blocks().is_empty() => {
+                    let mut msgpack = Vec::new();
+                    write_value(&mut msgpack, &Value::Ext(ext_type, envelope.header().to_vec()))
+                        .map_err(|e| Error::Encode(format!("Failed to serialize to MessagePack: {}", e)))?;
+                    Envelope::compress(&msgpack, Compression::Lz4BlockArray)
+                },
+                _ => Ok(envelope),
+            })
+            .collect()
+    }
+
+    /// Parse binary MessagePack input into its envelopes
+    ///
+    /// LZ4 envelopes are taken as they are; uncompressed MessagePack is
+    /// compressed into a Lz4BlockArray envelope, the same way JSON input is.
+    fn parse_binary(data: &[u8], options: &JsonOptions) -> Result<Vec<Envelope>> {
+        if !Envelope::detect(data) {
+            let msgpack = decode_any_with(data, options)?;
+            return Ok(vec![Envelope::compress(&msgpack, Compression::Lz4BlockArray)?]);
+        }
+
+        let mut cursor = Cursor::new(data);
+        let value = read_value(&mut cursor)
+            .map_err(|e| Error::InvalidMessagePack { offset: cursor.position(), message: e.to_string() })?;
+        if (cursor.position() as usize) < data.len() {
+            return Err(Error::TrailingData { offset: cursor.position() });
+        }
+        Envelope::parse_value(&value)
+    }
+
+    /// Borrow text input, rejecting data that is neither UTF-8 nor MessagePack
+    fn input_text(input: &[u8]) -> Result<&str> {
+        std::str::from_utf8(input)
+            .map_err(|e| Error::InvalidInput(format!("Input is neither JSON nor MessagePack: {}", e)))
+    }
+
+    /// Helper method to print debug information
+    fn debug_print(message: &str) {
+        if std::env::var("LZ4_MESSAGEPACK_DEBUG").is_ok() {
+            eprintln!("DEBUG: {}", message);
+        }
+    }
+
+    /// Helper method to dump binary data in debug mode
+    fn debug_dump(prefix: &str, data: &[u8], max_bytes: usize) {
+        if std::env::var("LZ4_MESSAGEPACK_DEBUG").is_ok() {
+            let bytes_to_show = std::cmp::min(data.len(), max_bytes);
+            let hex_dump: String = data[..bytes_to_show]
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            eprintln!("DEBUG: {} (first {} of {} bytes): {}",
+                prefix, bytes_to_show, data.len(), hex_dump);
+        }
+    }
+
+    /// Process an input file or string and output the result
+    pub fn process(input_source: Option<&str>, output_format: OutputFormat) -> Result<String> {
+        Self::process_with(input_source, output_format, &JsonOptions::default())
+    }
+
+    /// Process an input file or string, converting MessagePack to JSON with the given options
+    pub fn process_with(input_source: Option<&str>, output_format: OutputFormat, options: &JsonOptions) -> Result<String> {
+        let input = Self::read_input(input_source)?;
+        Self::process_bytes_with(&input, output_format, options)
+    }
+
+    /// Process input already in memory: JSON, hex text or binary MessagePack
+    pub fn process_bytes(input: &[u8], output_format: OutputFormat) -> Result<String> {
+        Self::process_bytes_with(input, output_format, &JsonOptions::default())
+    }
+
+    /// Process input already in memory, converting MessagePack to JSON with the given options
+    pub fn process_bytes_with(input: &[u8], output_format: OutputFormat, options: &JsonOptions) -> Result<String> {
+        // Print first few bytes for debugging
+        Self::debug_dump("Input data", input, 32);
+
+        // Parse the input into blocks with format awareness
+        let blocks = Self::process_input(input, options)?;
+
+        Self::debug_print(&format!("Found {} LZ4 blocks to process", blocks.len()));
+
+        // Process each block
+        let mut results = Vec::new();
+
+        for (i, ext) in blocks.iter().enumerate() {
+            Self::debug_print(&format!("Processing block {} of {}", i+1, blocks.len()));
+            Self::debug_print(&format!("Ext type: {}", ext.ext_type()));
+            Self::debug_print(&format!("Header data length: {}", ext.header().len()));
+            Self::debug_print(&format!("Compressed data length: {} bytes in {} LZ4 block(s)", ext.compressed_len(), ext.blocks().len()));
+            Self::debug_dump("Header data", ext.header(), ext.header().len());
+
+            // Decompress into the original MessagePack stream based on the extension type
+            let decompressed = ext.decompress_with(options.parallel, &options.limits)?;
+            Self::debug_dump("Decompressed data", &decompressed, 64);
+
+            // Reserialize to MessagePack
+            let msgpack_output = ext.to_msgpack()?;
+            Self::debug_print(&format!("MessagePack output length: {} bytes", msgpack_output.len()));
+
+            let human_readable = Self::process_decompressed_data(&decompressed, options)?;
+
+            // Add this block's result
+            results.push((msgpack_output, human_readable));
+        }
+
+        // Generate output based on format and combine results
+        match output_format {
+            OutputFormat::Binary => {
+                // Binary output is written by `process_binary`; a String can't carry it
+                Ok("Binary data generated, use stdout for binary output".to_string())
+            },
+            OutputFormat::Hex => {
+                // Return combined hex representation of all blocks
+                let combined = results.iter()
+                    .map(|(msgpack, _)| msgpack.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                Ok(combined)
+            },
+            OutputFormat::Human => {
+                // Return human-readable JSON for all blocks
+                let combined_json = if results.len() == 1 {
+                    // Single block, just return the result
+                    results[0].1.clone()
+                } else {
+                    // Multiple blocks, combine into an array
+                    json!(results.iter().map(|(_, human)| human.clone()).collect::<Vec<_>>())
+                };
+
+                serde_json::to_string_pretty(&combined_json)
+                    .map_err(|e| Error::Encode(format!("Error formatting JSON: {}", e)))
+            },
+            OutputFormat::Json => {
+                // Return full JSON with all details for all blocks
+                let result_array: Vec<JsonValue> = results.iter().enumerate().map(|(i, (msgpack, human))| {
+                    json!({
+                        "block_index": i,
+                        "messagepack_hex": msgpack.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+                        "messagepack_length": msgpack.len(),
+                        "original_ext_type": blocks[i].ext_type(),
+                        "original_header_data": blocks[i].header().iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+                        "original_data_length": blocks[i].compressed_len(),
+                        "original_block_count": blocks[i].blocks().len(),
+                        "human_readable": human
+                    })
+                }).collect();
+
+                let final_result = if result_array.len() == 1 {
+                    result_array[0].clone()
+                } else {
+                    json!({
+                        "total_blocks": result_array.len(),
+                        "blocks": result_array
+                    })
+                };
+
+                serde_json::to_string_pretty(&final_result)
+                    .map_err(|e| Error::Encode(format!("Error formatting JSON: {}", e)))
+            }
+        }
+    }
+
+    /// Process an input file or string into the binary MessagePack envelopes it holds
+    pub fn process_binary(input_source: Option<&str>) -> Result<Vec<Vec<u8>>> {
+        Self::process_binary_with(input_source, &JsonOptions::default())
+    }
+
+    /// Process an input file or string into binary envelopes, encoding plain JSON through the schema in `options`
+    pub fn process_binary_with(input_source: Option<&str>, options: &JsonOptions) -> Result<Vec<Vec<u8>>> {
+        let input = Self::read_input(input_source)?;
+        Self::process_input(&input, options)?
+            .iter()
+            .map(Envelope::to_msgpack)
+            .collect()
+    }
+
+    /// Read input from a file, stdin, or use default data
+    ///
+    /// The input is read as raw bytes so binary MessagePack captures load as they are.
+    fn read_input(source: Option<&str>) -> Result<Vec<u8>> {
+        match source {
+            Some("-") => {
+                // Read from stdin
+                let mut buffer = Vec::new();
+                io::stdin().read_to_end(&mut buffer)
+                    .map_err(|e| Error::io("Failed to read from stdin", e))?;
+                Ok(buffer)
+            },
+            Some(path) => {
+                // Read from file
+                let mut file = File::open(path)
+                    .map_err(|e| Error::io(format!("Failed to open file {}", path), e))?;
+                let mut buffer = Vec::new();
+                file.read_to_end(&mut buffer)
+                    .map_err(|e| Error::io(format!("Failed to read file {}", path), e))?;
+                Ok(buffer)
+            },
+            None => {
+                // Use default test data
+                Self::debug_print("No input file specified, using default test data.");
+                Ok(include_bytes!("../default_input.json").to_vec())
+            }
+        }
+    }
+
+    /// Output binary data to stdout
+    pub fn write_binary_to_stdout(data: &[u8]) -> Result<()> {
+        io::stdout().write_all(data)
+            .map_err(|e| Error::io("Failed to write binary data", e))
+    }
+}
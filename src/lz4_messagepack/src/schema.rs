@@ -0,0 +1,656 @@
+//! Schemas describing C# MessagePack contracts, used to name positional fields.
+//!
+//! MessagePack-CSharp writes `[Key(n)]` objects as arrays, so the decoded
+//! JSON has no field names. A schema lists each contract with its keys, field
+//! names and field types, and [`Schema::value_to_json`] uses it to turn those
+//! arrays back into named objects. Schemas are JSON files:
+//!
+//! ```text
+//! {
+//!   "root": "RouteResponse",
+//!   "types": {
+//!     "RouteResponse": { "fields": [
+//!       { "key": 0, "name": "code", "type": "string" },
+//!       { "key": 1, "name": "routes", "type": "Route[]" },
+//!       { "key": 2, "name": "waypoints", "type": "Waypoint[]?" }
+//!     ] },
+//!     "IRoute": { "union": [
+//!       { "key": 0, "type": "CarRoute" },
+//!       { "key": 1, "type": "BikeRoute" }
+//!     ] }
+//!   }
+//! }
+//! ```
+//!
+//! A field type is `any`, `bool`, `int`, `float`, `string`, `binary`,
+//! `timestamp`, `datetime`, `guid`, `decimal`, `timespan`, `datetimeoffset`,
+//! the name of another type in the schema, `map<K, V>`, or any of those
+//! followed by `[]` (array) or `?` (nullable). Keys are integers for
+//! array-encoded contracts and strings for map-encoded ones. `timestamp` is a
+//! C# `DateTime` in the default timestamp extension form and `datetime` one
+//! written by `NativeDateTimeResolver` as the int64 from `DateTime.ToBinary()`;
+//! both are written in the JSON timestamp format. `guid` is written as a UUID
+//! string, `decimal` as its exact decimal string, `timespan` as an ISO 8601
+//! duration such as `PT1H30M` and `datetimeoffset` as an RFC 3339 string
+//! with its offset.
+//!
+//! A union value `[key, body]` is written as the body's object with a
+//! `"$type"` member naming the case, such as `{"$type": "CarRoute", ...}`.
+//! [`Schema::json_to_value`] reverses the conversion, so named JSON encodes
+//! back to the positional arrays and `[key, body]` pairs C# reads.
+
+use rmpv::Value;
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::convert::{
+    float_to_value, json_to_timestamp, map_key, plain_json_to_value, plain_value_to_json, tagged_bytes,
+    timestamp_to_json, JsonOptions, MsgpackOptions, TimestampFormat,
+};
+use super::primitives::{
+    datetimeoffset_from_str, datetimeoffset_to_string, decimal_from_str, decimal_to_string, guid_from_str,
+    guid_to_string, timespan_from_iso, timespan_to_iso,
+};
+use super::{Error, Result, Timestamp};
+
+/// Type of a field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    /// Any value, converted without a schema
+    Any,
+    Bool,
+    Int,
+    Float,
+    String,
+    Binary,
+    /// A timestamp extension (type -1), how MessagePack-CSharp writes `DateTime` by default
+    Timestamp,
+    /// A `DateTime.ToBinary()` integer, how `NativeDateTimeResolver` writes `DateTime`
+    DateTime,
+    /// A `Guid`, as a 36-character string or 16 bytes from `NativeGuidResolver`
+    Guid,
+    /// A `decimal`, as a string or 16 bytes from `NativeDecimalResolver`
+    Decimal,
+    /// A `TimeSpan`, as an int64 tick count
+    TimeSpan,
+    /// A `DateTimeOffset`, as `[DateTime, offset minutes]`
+    DateTimeOffset,
+    /// A contract or union defined in the schema
+    Named(String),
+    /// An array whose items all have the given type
+    Array(Box<FieldType>),
+    /// A map with the given key and value types
+    Map(Box<FieldType>, Box<FieldType>),
+    /// A value of the given type, or nil
+    Nullable(Box<FieldType>),
+}
+
+impl FromStr for FieldType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(inner) = s.strip_suffix('?') {
+            return Ok(FieldType::Nullable(Box::new(inner.parse()?)));
+        }
+        if let Some(inner) = s.strip_suffix("[]") {
+            return Ok(FieldType::Array(Box::new(inner.parse()?)));
+        }
+        if let Some(inner) = s.strip_prefix("map<").and_then(|rest| rest.strip_suffix('>')) {
+            let (key, value) = split_type_arguments(inner)
+                .ok_or_else(|| Error::InvalidSchema(format!("Expected map<K, V>, found {}", s)))?;
+            return Ok(FieldType::Map(Box::new(key.parse()?), Box::new(value.parse()?)));
+        }
+
+        match s {
+            "any" => Ok(FieldType::Any),
+            "bool" => Ok(FieldType::Bool),
+            "int" => Ok(FieldType::Int),
+            "float" => Ok(FieldType::Float),
+            "string" => Ok(FieldType::String),
+            "binary" => Ok(FieldType::Binary),
+            "timestamp" => Ok(FieldType::Timestamp),
+            "datetime" => Ok(FieldType::DateTime),
+            "guid" => Ok(FieldType::Guid),
+            "decimal" => Ok(FieldType::Decimal),
+            "timespan" => Ok(FieldType::TimeSpan),
+            "datetimeoffset" => Ok(FieldType::DateTimeOffset),
+            name if is_type_name(name) => Ok(FieldType::Named(name.to_string())),
+            other => Err(Error::InvalidSchema(format!("Invalid field type: {:?}", other))),
+        }
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::Any => write!(f, "any"),
+            FieldType::Bool => write!(f, "bool"),
+            FieldType::Int => write!(f, "int"),
+            FieldType::Float => write!(f, "float"),
+            FieldType::String => write!(f, "string"),
+            FieldType::Binary => write!(f, "binary"),
+            FieldType::Timestamp => write!(f, "timestamp"),
+            FieldType::DateTime => write!(f, "datetime"),
+            FieldType::Guid => write!(f, "guid"),
+            FieldType::Decimal => write!(f, "decimal"),
+            FieldType::TimeSpan => write!(f, "timespan"),
+            FieldType::DateTimeOffset => write!(f, "datetimeoffset"),
+            FieldType::Named(name) => write!(f, "{}", name),
+            FieldType::Array(item) => write!(f, "{}[]", item),
+            FieldType::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            FieldType::Nullable(inner) => write!(f, "{}?", inner),
+        }
+    }
+}
+
+/// Split `K, V` at the comma that is not nested inside another `map<...>`
+fn split_type_arguments(s: &str) -> Option<(&str, &str)> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => return Some((&s[..i], &s[i + 1..])),
+            _ => {},
+        }
+    }
+    None
+}
+
+fn is_type_name(s: &str) -> bool {
+    s.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Key of a field: its position in an array-encoded contract, or its name in a map-encoded one
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FieldKey {
+    Index(u32),
+    Name(String),
+}
+
+/// A field of a contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub key: FieldKey,
+    /// Name written to JSON
+    pub name: String,
+    pub field_type: FieldType,
+}
+
+/// One `[Union(key, typeof(T))]` case
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnionCase {
+    pub key: i64,
+    /// Name of the contract the body is decoded as
+    pub type_name: String,
+}
+
+/// A named type in a schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeDef {
+    /// A `[MessagePackObject]` contract
+    Object { fields: Vec<Field> },
+    /// A `[Union]` interface or abstract class, written as `[key, body]`
+    Union { cases: Vec<UnionCase> },
+}
+
+/// A set of contracts, optionally with the type of the top-level value
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schema {
+    /// Type of the top-level value; without one, values are converted as they are
+    pub root: Option<String>,
+    pub types: BTreeMap<String, TypeDef>,
+}
+
+impl Schema {
+    /// Parse a schema from its JSON form
+    pub fn from_json(json: &JsonValue) -> Result<Schema> {
+        let root = match json.get("root") {
+            None | Some(JsonValue::Null) => None,
+            Some(JsonValue::String(root)) => Some(root.clone()),
+            Some(other) => return Err(Error::InvalidSchema(format!("root must be a type name, found {}", other))),
+        };
+        let types = json.get("types").and_then(JsonValue::as_object)
+            .ok_or_else(|| Error::InvalidSchema("Missing \"types\" object".to_string()))?
+            .iter()
+            .map(|(name, def)| parse_type_def(name, def).map(|def| (name.clone(), def)))
+            .collect::<Result<_>>()?;
+
+        let schema = Schema { root, types };
+        schema.validate()?;
+        Ok(schema)
+    }
+
+    /// Read a schema from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Schema> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| Error::io(format!("Failed to read schema {}", path.display()), e))?;
+        text.parse()
+    }
+
+    /// The JSON form of this schema, as read by [`Schema::from_json`]
+    pub fn to_json(&self) -> JsonValue {
+        let types: Map<String, JsonValue> = self.types.iter().map(|(name, def)| {
+            let def = match def {
+                TypeDef::Object { fields } => json!({ "fields": fields.iter().map(|field| {
+                    let key = match &field.key {
+                        FieldKey::Index(index) => json!(index),
+                        FieldKey::Name(name) => json!(name),
+                    };
+                    json!({ "key": key, "name": field.name, "type": field.field_type.to_string() })
+                }).collect::<Vec<_>>() }),
+                TypeDef::Union { cases } => json!({ "union": cases.iter()
+                    .map(|case| json!({ "key": case.key, "type": case.type_name }))
+                    .collect::<Vec<_>>() }),
+            };
+            (name.clone(), def)
+        }).collect();
+
+        match &self.root {
+            Some(root) => json!({ "root": root, "types": types }),
+            None => json!({ "types": types }),
+        }
+    }
+
+    /// Use `root` as the type of the top-level value
+    pub fn with_root(mut self, root: impl Into<String>) -> Result<Schema> {
+        self.root = Some(root.into());
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Check that type names are valid, every referenced type is defined and keys are unique
+    pub fn validate(&self) -> Result<()> {
+        if let Some(root) = &self.root {
+            self.check_defined(root, "root")?;
+        }
+
+        for (name, def) in &self.types {
+            if name.parse::<FieldType>().ok() != Some(FieldType::Named(name.clone())) {
+                return Err(Error::InvalidSchema(format!("{:?} is not a valid type name", name)));
+            }
+            match def {
+                TypeDef::Object { fields } => {
+                    for (i, field) in fields.iter().enumerate() {
+                        if fields[..i].iter().any(|other| other.key == field.key) {
+                            return Err(Error::InvalidSchema(format!("{} has a duplicate key {:?}", name, field.key)));
+                        }
+                        self.check_references(&field.field_type, name)?;
+                    }
+                },
+                TypeDef::Union { cases } => {
+                    for (i, case) in cases.iter().enumerate() {
+                        if cases[..i].iter().any(|other| other.key == case.key) {
+                            return Err(Error::InvalidSchema(format!("{} has a duplicate union key {}", name, case.key)));
+                        }
+                        self.check_defined(&case.type_name, name)?;
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn check_references(&self, field_type: &FieldType, owner: &str) -> Result<()> {
+        match field_type {
+            FieldType::Named(name) => self.check_defined(name, owner),
+            FieldType::Array(inner) | FieldType::Nullable(inner) => self.check_references(inner, owner),
+            FieldType::Map(key, value) => {
+                self.check_references(key, owner)?;
+                self.check_references(value, owner)
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn check_defined(&self, name: &str, owner: &str) -> Result<()> {
+        if self.types.contains_key(name) {
+            Ok(())
+        } else {
+            Err(Error::InvalidSchema(format!("Unknown type {} referenced by {}", name, owner)))
+        }
+    }
+
+    /// RFC 7807 ProblemDetails as ASP.NET writes it with `[Key(n)]` MessagePack formatters
+    pub fn problem_details() -> Schema {
+        let fields = [
+            ("type", FieldType::String),
+            ("title", FieldType::String),
+            ("status", FieldType::Int),
+            ("detail", FieldType::String),
+            ("instance", FieldType::String),
+        ];
+        let fields = fields.into_iter().enumerate().map(|(key, (name, field_type))| Field {
+            key: FieldKey::Index(key as u32),
+            name: name.to_string(),
+            field_type: FieldType::Nullable(Box::new(field_type)),
+        }).collect();
+
+        Schema {
+            root: Some("ProblemDetails".to_string()),
+            types: BTreeMap::from([("ProblemDetails".to_string(), TypeDef::Object { fields })]),
+        }
+    }
+
+    /// Convert a MessagePack value to JSON as the root type
+    pub fn value_to_json(&self, value: &Value, options: &JsonOptions) -> JsonValue {
+        match &self.root {
+            Some(root) => self.value_to_json_as(value, &FieldType::Named(root.clone()), options),
+            None => plain_value_to_json(value, options),
+        }
+    }
+
+    /// Convert a MessagePack value to JSON as the given type
+    ///
+    /// Values that do not have the shape the type describes are converted as
+    /// they are rather than rejected.
+    pub fn value_to_json_as(&self, value: &Value, field_type: &FieldType, options: &JsonOptions) -> JsonValue {
+        match (field_type, value) {
+            (_, Value::Nil) => JsonValue::Null,
+            (FieldType::Nullable(inner), _) => self.value_to_json_as(value, inner, options),
+            (FieldType::Array(item_type), Value::Array(items)) => JsonValue::Array(
+                items.iter().map(|item| self.value_to_json_as(item, item_type, options)).collect(),
+            ),
+            (FieldType::Map(_, value_type), Value::Map(entries)) => JsonValue::Object(
+                entries.iter()
+                    .map(|(k, v)| (map_key(k, options), self.value_to_json_as(v, value_type, options)))
+                    .collect(),
+            ),
+            (FieldType::Timestamp | FieldType::DateTime, Value::Integer(i)) if options.timestamps != TimestampFormat::Ext => {
+                match i.as_i64() {
+                    Some(binary) => timestamp_to_json(&Timestamp::from_datetime_binary(binary), options),
+                    None => plain_value_to_json(value, options),
+                }
+            },
+            (FieldType::TimeSpan, Value::Integer(i)) => match i.as_i64() {
+                Some(ticks) => json!(timespan_to_iso(ticks)),
+                None => plain_value_to_json(value, options),
+            },
+            (FieldType::Guid | FieldType::Decimal | FieldType::DateTimeOffset, _) => {
+                let formatted = match field_type {
+                    FieldType::Guid => guid_to_string(value),
+                    FieldType::Decimal => decimal_to_string(value),
+                    _ => datetimeoffset_to_string(value),
+                };
+                formatted.map(JsonValue::from).unwrap_or_else(|| plain_value_to_json(value, options))
+            },
+            (FieldType::Named(name), _) => match self.types.get(name) {
+                Some(TypeDef::Object { fields }) => self.object_to_json(value, fields, options),
+                Some(TypeDef::Union { cases }) => self.union_to_json(value, cases, options),
+                None => plain_value_to_json(value, options),
+            },
+            _ => plain_value_to_json(value, options),
+        }
+    }
+
+    fn object_to_json(&self, value: &Value, fields: &[Field], options: &JsonOptions) -> JsonValue {
+        let mut object = Map::new();
+        match value {
+            Value::Array(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    match fields.iter().find(|f| f.key == FieldKey::Index(index as u32)) {
+                        Some(field) => {
+                            object.insert(field.name.clone(), self.value_to_json_as(item, &field.field_type, options));
+                        },
+                        // Gaps in the key numbering are written as nil
+                        None if item.is_nil() => {},
+                        None => {
+                            object.insert(index.to_string(), plain_value_to_json(item, options));
+                        },
+                    }
+                }
+            },
+            Value::Map(entries) => {
+                for (key, item) in entries {
+                    let field = fields.iter().find(|f| match (&f.key, key) {
+                        (FieldKey::Index(index), Value::Integer(i)) => i.as_u64() == Some(u64::from(*index)),
+                        (FieldKey::Name(name), Value::String(s)) => s.as_str() == Some(name.as_str()),
+                        _ => false,
+                    });
+                    match field {
+                        Some(field) => {
+                            object.insert(field.name.clone(), self.value_to_json_as(item, &field.field_type, options));
+                        },
+                        None => {
+                            object.insert(map_key(key, options), plain_value_to_json(item, options));
+                        },
+                    }
+                }
+            },
+            other => return plain_value_to_json(other, options),
+        }
+        JsonValue::Object(object)
+    }
+
+    fn union_to_json(&self, value: &Value, cases: &[UnionCase], options: &JsonOptions) -> JsonValue {
+        if let Value::Array(items) = value {
+            if let [Value::Integer(key), body] = items.as_slice() {
+                if let Some(case) = cases.iter().find(|case| key.as_i64() == Some(case.key)) {
+                    let body = self.value_to_json_as(body, &FieldType::Named(case.type_name.clone()), options);
+                    let mut object = match body {
+                        JsonValue::Object(object) => object,
+                        other => Map::from_iter([("$value".to_string(), other)]),
+                    };
+                    object.insert("$type".to_string(), json!(case.type_name));
+                    return JsonValue::Object(object);
+                }
+            }
+        }
+        plain_value_to_json(value, options)
+    }
+
+    /// Convert JSON to a MessagePack value as the root type, reversing [`Schema::value_to_json`]
+    pub fn json_to_value(&self, json: &JsonValue, options: &MsgpackOptions) -> Result<Value> {
+        match &self.root {
+            Some(root) => self.json_to_value_as(json, &FieldType::Named(root.clone()), options),
+            None => plain_json_to_value(json, options),
+        }
+    }
+
+    /// Convert JSON to a MessagePack value as the given type
+    ///
+    /// Named objects become arrays or maps keyed the way their contract
+    /// declares, and `{"$type": ...}` objects become `[key, body]` unions.
+    /// `float` fields are written as floats and `binary` fields read base64
+    /// strings or byte arrays. `timestamp` and `datetime` fields read RFC 3339
+    /// strings or numbers of seconds since the Unix epoch, and `guid`,
+    /// `decimal`, `timespan` and `datetimeoffset` fields read the strings
+    /// [`Schema::value_to_json`] writes. JSON that does not have the shape the
+    /// type describes is converted as it is.
+    pub fn json_to_value_as(&self, json: &JsonValue, field_type: &FieldType, options: &MsgpackOptions) -> Result<Value> {
+        match (field_type, json) {
+            (_, JsonValue::Null) => Ok(Value::Nil),
+            (FieldType::Nullable(inner), _) => self.json_to_value_as(json, inner, options),
+            (FieldType::Float, JsonValue::Number(n)) => match n.as_f64() {
+                Some(f) => Ok(float_to_value(f, options)),
+                None => plain_json_to_value(json, options),
+            },
+            (FieldType::Binary, JsonValue::String(_) | JsonValue::Array(_)) => {
+                tagged_bytes("binary", json).map(Value::Binary)
+            },
+            (FieldType::Timestamp | FieldType::DateTime, JsonValue::String(_) | JsonValue::Number(_)) => {
+                let timestamp = json_to_timestamp(json)?;
+                match field_type {
+                    FieldType::DateTime => timestamp.to_datetime_binary().map(Value::from).ok_or_else(|| {
+                        Error::InvalidInput(format!("{} is outside the range of a C# DateTime", timestamp))
+                    }),
+                    _ => Ok(timestamp.to_value()),
+                }
+            },
+            (FieldType::Guid, JsonValue::String(s)) => guid_from_str(s),
+            (FieldType::Decimal, JsonValue::String(s)) => decimal_from_str(s),
+            (FieldType::Decimal, JsonValue::Number(n)) => decimal_from_str(&n.to_string()),
+            (FieldType::TimeSpan, JsonValue::String(s)) => timespan_from_iso(s).map(Value::from),
+            (FieldType::DateTimeOffset, JsonValue::String(s)) => datetimeoffset_from_str(s),
+            (FieldType::Array(item_type), JsonValue::Array(items)) => items.iter()
+                .map(|item| self.json_to_value_as(item, item_type, options))
+                .collect::<Result<Vec<_>>>()
+                .map(Value::Array),
+            (FieldType::Map(key_type, value_type), JsonValue::Object(entries)) => entries.iter()
+                .map(|(k, v)| Ok((map_key_value(k, key_type), self.json_to_value_as(v, value_type, options)?)))
+                .collect::<Result<Vec<_>>>()
+                .map(Value::Map),
+            (FieldType::Named(name), JsonValue::Object(object)) => match self.types.get(name) {
+                Some(TypeDef::Object { fields }) => self.object_from_json(name, object, fields, options),
+                Some(TypeDef::Union { cases }) => self.union_from_json(name, object, cases, options),
+                None => plain_json_to_value(json, options),
+            },
+            _ => plain_json_to_value(json, options),
+        }
+    }
+
+    fn object_from_json(
+        &self,
+        name: &str,
+        object: &Map<String, JsonValue>,
+        fields: &[Field],
+        options: &MsgpackOptions,
+    ) -> Result<Value> {
+        let field_named = |key: &str| fields.iter().find(|field| field.name == key);
+
+        if fields.iter().any(|field| matches!(field.key, FieldKey::Name(_))) {
+            let mut entries = Vec::with_capacity(object.len());
+            for (key, item) in object {
+                entries.push(match field_named(key) {
+                    Some(field) => {
+                        let key = match &field.key {
+                            FieldKey::Index(index) => Value::from(*index),
+                            FieldKey::Name(name) => Value::from(name.as_str()),
+                        };
+                        (key, self.json_to_value_as(item, &field.field_type, options)?)
+                    },
+                    None => (Value::from(key.as_str()), plain_json_to_value(item, options)?),
+                });
+            }
+            return Ok(Value::Map(entries));
+        }
+
+        // Positions missing from the JSON, including gaps in the key numbering, are written as nil
+        let mut items = Vec::new();
+        // Unnamed positions past the last [Key(n)] come from a newer contract and are few, so the
+        // array never grows beyond the declared keys plus one position per JSON entry
+        let declared = fields.iter()
+            .filter_map(|field| match field.key {
+                FieldKey::Index(index) => Some(index as usize + 1),
+                FieldKey::Name(_) => None,
+            })
+            .max()
+            .unwrap_or(0);
+        let max_len = declared.saturating_add(object.len());
+        for (key, item) in object {
+            let (index, value) = match field_named(key) {
+                Some(field @ Field { key: FieldKey::Index(index), .. }) => {
+                    (*index as usize, self.json_to_value_as(item, &field.field_type, options)?)
+                },
+                // Positions the schema does not name are written back by index
+                _ => match key.parse::<usize>() {
+                    Ok(index) if index.checked_add(1).is_some_and(|len| len <= max_len) => {
+                        (index, plain_json_to_value(item, options)?)
+                    },
+                    Ok(index) => {
+                        return Err(Error::InvalidInput(format!(
+                            "{} has no position {}; unnamed positions must be below {}",
+                            name, index, max_len
+                        )))
+                    },
+                    Err(_) => return Err(Error::InvalidInput(format!("{} has no field named {:?}", name, key))),
+                },
+            };
+            if items.len() <= index {
+                items.resize(index + 1, Value::Nil);
+            }
+            items[index] = value;
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn union_from_json(
+        &self,
+        name: &str,
+        object: &Map<String, JsonValue>,
+        cases: &[UnionCase],
+        options: &MsgpackOptions,
+    ) -> Result<Value> {
+        let Some(type_name) = object.get("$type") else {
+            return plain_json_to_value(&JsonValue::Object(object.clone()), options);
+        };
+        let case = cases.iter().find(|case| type_name.as_str() == Some(case.type_name.as_str()))
+            .ok_or_else(|| Error::InvalidInput(format!("{} is not a case of the {} union", type_name, name)))?;
+
+        let body = match object.get("$value") {
+            Some(body) => body.clone(),
+            None => JsonValue::Object(object.iter()
+                .filter(|(key, _)| key.as_str() != "$type")
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect()),
+        };
+        let body = self.json_to_value_as(&body, &FieldType::Named(case.type_name.clone()), options)?;
+        Ok(Value::Array(vec![Value::from(case.key), body]))
+    }
+}
+
+/// MessagePack key for a JSON object key, restoring integer keys of `map<int, V>`
+fn map_key_value(key: &str, key_type: &FieldType) -> Value {
+    match (key_type, key.parse::<i64>()) {
+        (FieldType::Int, Ok(key)) => Value::from(key),
+        _ => Value::from(key),
+    }
+}
+
+impl FromStr for Schema {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let json: JsonValue = serde_json::from_str(s)
+            .map_err(|e| Error::InvalidSchema(format!("Schema is not valid JSON: {}", e)))?;
+        Schema::from_json(&json)
+    }
+}
+
+fn parse_type_def(name: &str, def: &JsonValue) -> Result<TypeDef> {
+    let invalid = |message: &str| Error::InvalidSchema(format!("{}: {}", name, message));
+
+    if let Some(fields) = def.get("fields") {
+        let fields = fields.as_array().ok_or_else(|| invalid("\"fields\" must be an array"))?;
+        let fields = fields.iter().map(|field| {
+            let key = match field.get("key") {
+                Some(JsonValue::Number(n)) => n.as_u64()
+                    .and_then(|n| u32::try_from(n).ok())
+                    .map(FieldKey::Index)
+                    .ok_or_else(|| invalid(&format!("invalid key {}", n)))?,
+                Some(JsonValue::String(s)) => FieldKey::Name(s.clone()),
+                _ => return Err(invalid("every field needs an integer or string \"key\"")),
+            };
+            let field_name = match (field.get("name").and_then(JsonValue::as_str), &key) {
+                (Some(field_name), _) => field_name.to_string(),
+                (None, FieldKey::Name(key)) => key.clone(),
+                (None, FieldKey::Index(_)) => return Err(invalid("fields with an integer key need a \"name\"")),
+            };
+            let field_type = match field.get("type").and_then(JsonValue::as_str) {
+                Some(field_type) => field_type.parse()?,
+                None => FieldType::Any,
+            };
+            Ok(Field { key, name: field_name, field_type })
+        }).collect::<Result<_>>()?;
+        Ok(TypeDef::Object { fields })
+    } else if let Some(cases) = def.get("union") {
+        let cases = cases.as_array().ok_or_else(|| invalid("\"union\" must be an array"))?;
+        let cases = cases.iter().map(|case| {
+            let key = case.get("key").and_then(JsonValue::as_i64)
+                .ok_or_else(|| invalid("every union case needs an integer \"key\""))?;
+            let type_name = case.get("type").and_then(JsonValue::as_str)
+                .ok_or_else(|| invalid("every union case needs a \"type\""))?;
+            Ok(UnionCase { key, type_name: type_name.to_string() })
+        }).collect::<Result<_>>()?;
+        Ok(TypeDef::Union { cases })
+    } else {
+        Err(invalid("expected \"fields\" or \"union\""))
+    }
+}
@@ -0,0 +1,383 @@
+//! Streaming access to binary LZ4 envelopes.
+//!
+//! [`Lz4BlockArrayReader`] reads an envelope from any [`Read`] and
+//! decompresses one block at a time, so a large response never has to be held
+//! in memory whole, compressed or not. It implements [`Read`] over the
+//! MessagePack stream the envelope holds and can be passed straight to
+//! `rmpv::decode::read_value` or `rmp_serde::Deserializer`:
+//!
+//! ```text
+//! let mut reader = Lz4BlockArrayReader::new(File::open("response.msgpack")?)?;
+//! let value = rmpv::decode::read_value(&mut reader)?;
+//! ```
+//!
+//! [`Lz4BlockArrayWriter`] goes the other way: it implements [`Write`],
+//! compresses every 32 KiB of MessagePack as soon as it is written and
+//! produces the same bytes as [`encode`](super::encode):
+//!
+//! ```text
+//! let mut writer = Lz4BlockArrayWriter::seekable(File::create("export.msgpack")?)?;
+//! rmp_serde::encode::write(&mut writer, &rows)?;
+//! writer.finish()?;
+//! ```
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use rmp::encode::{write_array_len, write_ext_meta, write_uint};
+
+use super::envelope::{compress_block, decompress_block, len_u32, read_block_lengths, read_lz4_block};
+use super::{Error, Limits, Result, LZ4_BLOCK_ARRAY_EXT, LZ4_BLOCK_EXT, LZ4_BLOCK_SIZE};
+
+/// Decompresses a binary `Lz4BlockArray` or `Lz4Block` envelope block by block
+///
+/// The extension header is read by [`Lz4BlockArrayReader::new`], so the
+/// uncompressed size is known before any block is decompressed. Each `bin`
+/// block is read and decompressed only when the previous one has been
+/// consumed. Bytes after the envelope are left unread in the inner reader.
+#[derive(Debug)]
+pub struct Lz4BlockArrayReader<R> {
+    inner: R,
+    /// Uncompressed length of every block, from the extension header
+    lengths: Vec<usize>,
+    /// Index of the next block to read from `inner`
+    next_block: usize,
+    /// Compressed bytes of the block being read, reused across blocks
+    compressed: Vec<u8>,
+    /// Decompressed bytes of the current block
+    block: Vec<u8>,
+    /// Read position within `block`
+    position: usize,
+    /// Bytes read from `inner` so far, for error messages
+    offset: u64,
+}
+
+impl<R: Read> Lz4BlockArrayReader<R> {
+    /// Read the envelope's array and extension headers from `inner`, within the default [`Limits`]
+    ///
+    /// An `Lz4Block` envelope holds its only block in the extension, so that
+    /// block is read here as well.
+    pub fn new(inner: R) -> Result<Self> {
+        Lz4BlockArrayReader::with_limits(inner, &Limits::default())
+    }
+
+    /// Read the envelope's headers from `inner`, checking the lengths they declare against `limits`
+    ///
+    /// Only the envelope limits apply: the MessagePack read from the
+    /// decompressed stream is not walked ahead of time.
+    pub fn with_limits(inner: R, limits: &Limits) -> Result<Self> {
+        let mut reader = Lz4BlockArrayReader {
+            inner,
+            lengths: Vec::new(),
+            next_block: 0,
+            compressed: Vec::new(),
+            block: Vec::new(),
+            position: 0,
+            offset: 0,
+        };
+
+        let marker = reader.read_u8(None)?;
+        let blocks = match marker {
+            0x90..=0x9f => usize::from(marker & 0x0f),
+            0xdc => reader.read_uint(2, None)?,
+            0xdd => reader.read_uint(4, None)?,
+            _ => {
+                // A lone ext 99 holds the length and the whole LZ4 block
+                let (ext_type, len) = reader.read_ext_meta(marker)?;
+                if ext_type != LZ4_BLOCK_EXT {
+                    return Err(Error::UnsupportedExtension(ext_type));
+                }
+                let payload = reader.read_vec(len, Some(0))?;
+                let (length, block) = read_lz4_block(&payload)?;
+                limits.check_blocks(&[length])?;
+                reader.lengths = vec![length];
+                reader.next_block = 1;
+                reader.block = decompress_block(0, length, block)?;
+                return Ok(reader);
+            },
+        };
+
+        if blocks == 0 {
+            return Err(Error::InvalidEnvelope { index: None, message: "Lz4BlockArray envelope is empty".to_string() });
+        }
+        let marker = reader.read_u8(Some(0))?;
+        let (ext_type, len) = reader.read_ext_meta(marker)?;
+        if ext_type != LZ4_BLOCK_ARRAY_EXT {
+            return Err(Error::UnsupportedExtension(ext_type));
+        }
+        let header = reader.read_vec(len, Some(0))?;
+        reader.lengths = read_block_lengths(&header)?;
+
+        if reader.lengths.len() != blocks - 1 {
+            return Err(Error::BlockCountMismatch { declared: reader.lengths.len(), found: blocks - 1 });
+        }
+        limits.check_blocks(&reader.lengths)?;
+        Ok(reader)
+    }
+
+    /// Uncompressed length of every block, as declared by the extension header
+    pub fn block_lengths(&self) -> &[usize] {
+        &self.lengths
+    }
+
+    /// Total length of the MessagePack stream the envelope holds
+    pub fn uncompressed_len(&self) -> usize {
+        self.lengths.iter().sum()
+    }
+
+    /// Give back the inner reader, positioned after the last block read so far
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read and decompress the next block; `false` once every block has been read
+    fn read_block(&mut self) -> Result<bool> {
+        let Some(&length) = self.lengths.get(self.next_block) else {
+            return Ok(false);
+        };
+        let index = self.next_block;
+        let element = Some(index + 1);
+
+        let len = match self.read_u8(element)? {
+            0xc4 => self.read_uint(1, element)?,
+            0xc5 => self.read_uint(2, element)?,
+            0xc6 => self.read_uint(4, element)?,
+            marker => return Err(Error::envelope(index + 1, format!("Expected a bin block, found marker 0x{:02x}", marker))),
+        };
+        // LZ4 never compresses `length` bytes into more than this, so a longer block is rejected before it is read
+        if len > length + length / 255 + 16 {
+            return Err(Error::CorruptBlock {
+                block: index,
+                message: format!("{} compressed bytes cannot decompress to {}", len, length),
+            });
+        }
+        let mut compressed = std::mem::take(&mut self.compressed);
+        compressed.resize(len, 0);
+        self.read_exact(&mut compressed, element)?;
+
+        self.block = decompress_block(index, length, &compressed)?;
+        self.compressed = compressed;
+        self.position = 0;
+        self.next_block += 1;
+        Ok(true)
+    }
+
+    /// Type and data length of an extension starting with `marker`
+    fn read_ext_meta(&mut self, marker: u8) -> Result<(i8, usize)> {
+        let len = match marker {
+            0xd4 => 1,
+            0xd5 => 2,
+            0xd6 => 4,
+            0xd7 => 8,
+            0xd8 => 16,
+            0xc7 => self.read_uint(1, Some(0))?,
+            0xc8 => self.read_uint(2, Some(0))?,
+            0xc9 => self.read_uint(4, Some(0))?,
+            _ => return Err(Error::InvalidEnvelope {
+                index: None,
+                message: format!("Expected an array or an extension, found marker 0x{:02x}", marker),
+            }),
+        };
+        Ok((self.read_u8(Some(0))? as i8, len))
+    }
+
+    /// Read `len` bytes that the input declares, growing the buffer only as they actually arrive
+    fn read_vec(&mut self, len: usize, element: Option<usize>) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        (&mut self.inner).take(len as u64).read_to_end(&mut data)
+            .map_err(|source| Error::io("Failed to read the LZ4 envelope", source))?;
+        self.offset += data.len() as u64;
+        if data.len() < len {
+            return Err(Error::InvalidEnvelope {
+                index: element,
+                message: format!("Envelope ends unexpectedly after {} bytes", self.offset),
+            });
+        }
+        Ok(data)
+    }
+
+    fn read_u8(&mut self, element: Option<usize>) -> Result<u8> {
+        let mut byte = [0];
+        self.read_exact(&mut byte, element)?;
+        Ok(byte[0])
+    }
+
+    /// Read a big-endian unsigned integer of `size` bytes
+    fn read_uint(&mut self, size: usize, element: Option<usize>) -> Result<usize> {
+        let mut bytes = [0; 4];
+        self.read_exact(&mut bytes[4 - size..], element)?;
+        Ok(u32::from_be_bytes(bytes) as usize)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8], element: Option<usize>) -> Result<()> {
+        match self.inner.read_exact(buf) {
+            Ok(()) => {
+                self.offset += buf.len() as u64;
+                Ok(())
+            },
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(Error::InvalidEnvelope {
+                index: element,
+                message: format!("Envelope ends unexpectedly after {} bytes", self.offset),
+            }),
+            Err(source) => Err(Error::io("Failed to read the LZ4 envelope", source)),
+        }
+    }
+}
+
+impl<R: Read> Read for Lz4BlockArrayReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.block.len() {
+            if !self.read_block()? {
+                return Ok(0);
+            }
+        }
+
+        let len = buf.len().min(self.block.len() - self.position);
+        buf[..len].copy_from_slice(&self.block[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+/// Compresses a MessagePack stream into a binary `Lz4BlockArray` envelope as it is written
+///
+/// The envelope starts with the number of blocks and their lengths, which are
+/// only known once the stream ends, so the compressed blocks cannot go
+/// straight to the sink ahead of the header:
+///
+/// - [`Lz4BlockArrayWriter::new`] works with any sink. It keeps the
+///   compressed blocks and writes the whole envelope in [`finish`](Self::finish).
+/// - [`Lz4BlockArrayWriter::seekable`] writes each block to the sink as soon
+///   as it is compressed. `finish` then moves the blocks forward to make room
+///   for the header, so only one block is ever held in memory.
+///
+/// Nothing is written for a partial block until `finish`, which must be
+/// called to complete the envelope.
+pub struct Lz4BlockArrayWriter<W: Write> {
+    inner: W,
+    /// Uncompressed bytes of the block being filled
+    chunk: Vec<u8>,
+    /// Extension header: the uncompressed length of every block so far
+    header: Vec<u8>,
+    blocks: usize,
+    sink: Sink<W>,
+}
+
+enum Sink<W> {
+    /// Compressed blocks, already framed as `bin`, waiting for the header
+    Buffered(Vec<u8>),
+    /// Blocks written to the inner writer from `start`, moved by `insert` once the header is known
+    Seekable { start: u64, written: u64, insert: fn(&mut W, u64, u64, &[u8]) -> io::Result<()> },
+}
+
+impl<W: Write> Lz4BlockArrayWriter<W> {
+    /// A writer that keeps the compressed blocks until [`finish`](Self::finish), for sinks that cannot seek
+    pub fn new(inner: W) -> Self {
+        Lz4BlockArrayWriter::with_sink(inner, Sink::Buffered(Vec::new()))
+    }
+
+    fn with_sink(inner: W, sink: Sink<W>) -> Self {
+        Lz4BlockArrayWriter { inner, chunk: Vec::with_capacity(LZ4_BLOCK_SIZE), header: Vec::new(), blocks: 0, sink }
+    }
+
+    /// Compress the last partial block, write the envelope header and give back the inner writer
+    pub fn finish(mut self) -> Result<W> {
+        self.write_block().map_err(|source| Error::io("Failed to write an LZ4 block", source))?;
+
+        let mut prefix = Vec::with_capacity(self.header.len() + 11);
+        write_array_len(&mut prefix, len_u32(self.blocks + 1)?)
+            .map_err(|e| Error::Encode(format!("Failed to write envelope array: {}", e)))?;
+        write_ext_meta(&mut prefix, len_u32(self.header.len())?, LZ4_BLOCK_ARRAY_EXT)
+            .map_err(|e| Error::Encode(format!("Failed to write extension header: {}", e)))?;
+        prefix.extend_from_slice(&self.header);
+
+        let result = match &self.sink {
+            Sink::Buffered(blocks) => self.inner.write_all(&prefix).and_then(|_| self.inner.write_all(blocks)),
+            Sink::Seekable { start, written, insert } => insert(&mut self.inner, *start, *written, &prefix),
+        };
+        result
+            .and_then(|_| self.inner.flush())
+            .map_err(|source| Error::io("Failed to write the LZ4 envelope", source))?;
+        Ok(self.inner)
+    }
+
+    /// Compress the filled part of `chunk` as the next block
+    ///
+    /// An empty stream still gets one zero-length block, since the decoder
+    /// rejects an ext 98 header without any lengths.
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.chunk.is_empty() && self.blocks > 0 {
+            return Ok(());
+        }
+
+        let block = compress_block(&self.chunk)?;
+        write_uint(&mut self.header, self.chunk.len() as u64).map_err(io::Error::other)?;
+        let mut frame = Vec::with_capacity(block.len() + 5);
+        frame.push(0xc6);
+        frame.extend_from_slice(&len_u32(block.len())?.to_be_bytes());
+        frame.extend_from_slice(&block);
+
+        match &mut self.sink {
+            Sink::Buffered(blocks) => blocks.extend_from_slice(&frame),
+            Sink::Seekable { written, .. } => {
+                self.inner.write_all(&frame)?;
+                *written += frame.len() as u64;
+            },
+        }
+        self.chunk.clear();
+        self.blocks += 1;
+        Ok(())
+    }
+}
+
+impl<W: Read + Write + Seek> Lz4BlockArrayWriter<W> {
+    /// A writer that sends each block to `inner` as soon as it is compressed
+    ///
+    /// The envelope starts at the current position of `inner`. Moving the
+    /// blocks in [`finish`](Self::finish) reads them back, so `inner` must be
+    /// readable as well, like a `File` opened for reading and writing.
+    pub fn seekable(mut inner: W) -> Result<Self> {
+        let start = inner.stream_position()
+            .map_err(|source| Error::io("Failed to find the output position", source))?;
+        Ok(Lz4BlockArrayWriter::with_sink(inner, Sink::Seekable { start, written: 0, insert: insert_before::<W> }))
+    }
+}
+
+impl<W: Write> Write for Lz4BlockArrayWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(LZ4_BLOCK_SIZE - self.chunk.len());
+        self.chunk.extend_from_slice(&buf[..len]);
+        if self.chunk.len() == LZ4_BLOCK_SIZE {
+            self.write_block()?;
+        }
+        Ok(len)
+    }
+
+    /// Flush the inner writer; a partial block is kept until it fills or the envelope is finished
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Move the `len` bytes at `start` forward by the length of `prefix` and write `prefix` in front of them
+fn insert_before<W: Read + Write + Seek>(inner: &mut W, start: u64, len: u64, prefix: &[u8]) -> io::Result<()> {
+    let shift = prefix.len() as u64;
+    let mut buffer = vec![0; LZ4_BLOCK_SIZE];
+    let mut end = start + len;
+
+    // Back to front, so no byte is overwritten before it has been moved
+    while end > start {
+        let size = (end - start).min(buffer.len() as u64);
+        let from = end - size;
+        let chunk = &mut buffer[..size as usize];
+        inner.seek(SeekFrom::Start(from))?;
+        inner.read_exact(chunk)?;
+        inner.seek(SeekFrom::Start(from + shift))?;
+        inner.write_all(chunk)?;
+        end = from;
+    }
+
+    inner.seek(SeekFrom::Start(start))?;
+    inner.write_all(prefix)?;
+    inner.seek(SeekFrom::Start(start + shift + len))?;
+    Ok(())
+}
@@ -0,0 +1,305 @@
+//! MessagePack timestamps and C# `DateTime` values.
+//!
+//! The timestamp extension (type -1) holds seconds and nanoseconds since the
+//! Unix epoch in 4, 8 or 12 bytes, and is how MessagePack-CSharp writes a
+//! `DateTime` by default. With `NativeDateTimeResolver` a `DateTime` is the
+//! int64 from `DateTime.ToBinary()` instead: 100 ns ticks since 0001-01-01
+//! with the kind in the top two bits. [`Timestamp`] reads both forms and
+//! converts to and from RFC 3339.
+
+use rmpv::Value;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Error, Result, TIMESTAMP_EXT};
+
+const NANOS_PER_SECOND: u32 = 1_000_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+const TICKS_PER_SECOND: i64 = 10_000_000;
+/// `DateTime` ticks at 1970-01-01T00:00:00Z
+const UNIX_EPOCH_TICKS: i64 = 621_355_968_000_000_000;
+/// `DateTime.MaxValue.Ticks`
+const MAX_TICKS: i64 = 3_155_378_975_999_999_999;
+const TICKS_MASK: i64 = 0x3FFF_FFFF_FFFF_FFFF;
+/// `DateTimeKind.Utc` in the top two bits of `ToBinary()`
+const KIND_UTC: i64 = 0x4000_0000_0000_0000;
+/// Years accepted in RFC 3339 text, the range of a C# `DateTime`
+const YEARS: std::ops::RangeInclusive<i64> = 1..=9999;
+
+/// A point in time with nanosecond precision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    seconds: i64,
+    nanos: u32,
+}
+
+impl Timestamp {
+    /// A timestamp from seconds since the Unix epoch and a nanosecond part below one second
+    pub fn new(seconds: i64, nanos: u32) -> Option<Timestamp> {
+        (nanos < NANOS_PER_SECOND).then_some(Timestamp { seconds, nanos })
+    }
+
+    /// Seconds since the Unix epoch
+    pub fn seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    /// Nanoseconds past [`Timestamp::seconds`]
+    pub fn nanos(&self) -> u32 {
+        self.nanos
+    }
+
+    /// Read the data of a timestamp extension in its 4, 8 or 12 byte form
+    pub fn from_ext_data(data: &[u8]) -> Option<Timestamp> {
+        match data.len() {
+            4 => Timestamp::new(i64::from(u32::from_be_bytes(data.try_into().ok()?)), 0),
+            8 => {
+                let packed = u64::from_be_bytes(data.try_into().ok()?);
+                Timestamp::new((packed & 0x3_FFFF_FFFF) as i64, (packed >> 34) as u32)
+            },
+            12 => Timestamp::new(
+                i64::from_be_bytes(data[4..].try_into().ok()?),
+                u32::from_be_bytes(data[..4].try_into().ok()?),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Extension data in the smallest form that holds this timestamp
+    pub fn to_ext_data(&self) -> Vec<u8> {
+        if self.seconds >> 34 == 0 {
+            if self.nanos == 0 && self.seconds <= i64::from(u32::MAX) {
+                return (self.seconds as u32).to_be_bytes().to_vec();
+            }
+            return ((u64::from(self.nanos) << 34) | self.seconds as u64).to_be_bytes().to_vec();
+        }
+        let mut data = self.nanos.to_be_bytes().to_vec();
+        data.extend_from_slice(&self.seconds.to_be_bytes());
+        data
+    }
+
+    /// The timestamp held by a type -1 extension value
+    pub fn from_value(value: &Value) -> Option<Timestamp> {
+        match value {
+            Value::Ext(TIMESTAMP_EXT, data) => Timestamp::from_ext_data(data),
+            _ => None,
+        }
+    }
+
+    /// This timestamp as a type -1 extension value
+    pub fn to_value(&self) -> Value {
+        Value::Ext(TIMESTAMP_EXT, self.to_ext_data())
+    }
+
+    /// Read a `DateTime.ToBinary()` value; local and unspecified times are taken as UTC
+    pub fn from_datetime_binary(binary: i64) -> Timestamp {
+        let mut ticks = binary & TICKS_MASK;
+        // Local times near DateTime.MinValue wrap around when ToBinary subtracts the offset
+        if ticks > MAX_TICKS {
+            ticks -= TICKS_MASK + 1;
+        }
+        let ticks = ticks - UNIX_EPOCH_TICKS;
+        Timestamp {
+            seconds: ticks.div_euclid(TICKS_PER_SECOND),
+            nanos: (ticks.rem_euclid(TICKS_PER_SECOND) * 100) as u32,
+        }
+    }
+
+    /// `DateTime.ToBinary()` of this time as a UTC `DateTime`, if it is within the `DateTime` range
+    ///
+    /// `DateTime` counts 100 ns ticks, so finer precision is truncated.
+    pub fn to_datetime_binary(&self) -> Option<i64> {
+        let ticks = self.seconds.checked_mul(TICKS_PER_SECOND)?
+            .checked_add(i64::from(self.nanos / 100))?
+            .checked_add(UNIX_EPOCH_TICKS)?;
+        (0..=MAX_TICKS).contains(&ticks).then_some(ticks | KIND_UTC)
+    }
+
+    /// Seconds since the Unix epoch, with the nanoseconds as a fraction
+    pub fn as_seconds_f64(&self) -> f64 {
+        self.seconds as f64 + f64::from(self.nanos) / f64::from(NANOS_PER_SECOND)
+    }
+
+    /// RFC 3339 in the local time of the given UTC offset, such as `2024-05-01T14:30:00+02:00`
+    ///
+    /// This is how a C# `DateTimeOffset` is written.
+    pub fn to_rfc3339_with_offset(&self, offset_minutes: i32) -> String {
+        let local = self.seconds.saturating_add(i64::from(offset_minutes) * 60);
+        let sign = if offset_minutes < 0 { '-' } else { '+' };
+        let offset = offset_minutes.unsigned_abs();
+        format!("{}{}{:02}:{:02}", Rfc3339Local(local, self.nanos), sign, offset / 60, offset % 60)
+    }
+
+    /// Parse an RFC 3339 date-time into the instant and its UTC offset in minutes
+    pub fn parse_with_offset(s: &str) -> Result<(Timestamp, i32)> {
+        parse_rfc3339(s).ok_or_else(|| Error::InvalidInput(format!("Invalid RFC 3339 timestamp: {:?}", s)))
+    }
+
+    /// A timestamp from fractional seconds since the Unix epoch
+    pub fn from_seconds_f64(seconds: f64) -> Option<Timestamp> {
+        if !seconds.is_finite() || seconds.abs() >= i64::MAX as f64 {
+            return None;
+        }
+        let whole = seconds.floor();
+        let nanos = ((seconds - whole) * f64::from(NANOS_PER_SECOND)).round() as u32;
+        match nanos {
+            NANOS_PER_SECOND => Timestamp::new(whole as i64 + 1, 0),
+            nanos => Timestamp::new(whole as i64, nanos),
+        }
+    }
+}
+
+impl fmt::Display for Timestamp {
+    /// RFC 3339 in UTC, with 3, 6 or 9 fraction digits when there are nanoseconds
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}Z", Rfc3339Local(self.seconds, self.nanos))
+    }
+}
+
+/// The date and time of an RFC 3339 timestamp, without the offset
+struct Rfc3339Local(i64, u32);
+
+impl fmt::Display for Rfc3339Local {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Rfc3339Local(seconds, nanos) = *self;
+        let time = seconds.rem_euclid(SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+        write!(f, "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60)?;
+
+        match nanos {
+            0 => Ok(()),
+            n if n.is_multiple_of(1_000_000) => write!(f, ".{:03}", n / 1_000_000),
+            n if n.is_multiple_of(1_000) => write!(f, ".{:06}", n / 1_000),
+            n => write!(f, ".{:09}", n),
+        }
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = Error;
+
+    /// Parse an RFC 3339 date-time such as `2024-05-01T12:30:00.5+02:00`
+    fn from_str(s: &str) -> Result<Self> {
+        Timestamp::parse_with_offset(s).map(|(timestamp, _)| timestamp)
+    }
+}
+
+impl Serialize for Timestamp {
+    /// A type -1 extension in MessagePack, an RFC 3339 string in human-readable formats
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            self.to_value().serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    /// A timestamp extension, an RFC 3339 string or a `DateTime.ToBinary()` integer
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::String(s) => s.as_str()
+                .and_then(parse_rfc3339)
+                .map(|(timestamp, _)| timestamp)
+                .ok_or_else(|| de::Error::custom(format!("invalid RFC 3339 timestamp {}", s))),
+            Value::Integer(i) => i.as_i64()
+                .map(Timestamp::from_datetime_binary)
+                .ok_or_else(|| de::Error::custom(format!("invalid DateTime binary value {}", i))),
+            other => Timestamp::from_value(&other)
+                .ok_or_else(|| de::Error::custom(format!("expected a timestamp, found {}", other))),
+        }
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date, or `None` when the count overflows
+fn days_from_civil(year: i64, month: u32, day: u32) -> Option<i64> {
+    let year = if month <= 2 { year.checked_sub(1)? } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * i64::from((month + 9) % 12) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era.checked_mul(146_097)?.checked_add(day_of_era - 719_468)
+}
+
+/// Proleptic Gregorian date of a day count since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The instant and the UTC offset in minutes of an RFC 3339 date-time
+fn parse_rfc3339(s: &str) -> Option<(Timestamp, i32)> {
+    let (date, rest) = s.split_at(s.find(['T', 't', ' '])?);
+    let mut date_parts = date.rsplitn(3, '-');
+    let day: u32 = parse_digits(date_parts.next()?, 2)?;
+    let month: u32 = parse_digits(date_parts.next()?, 2)?;
+    let year: i64 = match date_parts.next()? {
+        year if year.len() >= 4 => year.parse().ok()?,
+        _ => return None,
+    };
+    if !YEARS.contains(&year) || !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let rest = &rest[1..];
+    let offset_at = rest.find(['Z', 'z', '+', '-'])?;
+    let (time, offset) = rest.split_at(offset_at);
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction)) if (1..=9).contains(&fraction.len()) => (time, fraction),
+        Some(_) => return None,
+        None => (time, ""),
+    };
+    let mut time_parts = time.split(':');
+    let hour: i64 = parse_digits(time_parts.next()?, 2)?;
+    let minute: i64 = parse_digits(time_parts.next()?, 2)?;
+    let second: i64 = parse_digits(time_parts.next()?, 2)?;
+    if time_parts.next().is_some() || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let nanos = match fraction {
+        "" => 0,
+        digits => parse_digits::<u32>(digits, digits.len())? * 10u32.pow(9 - digits.len() as u32),
+    };
+
+    let offset_seconds = match offset {
+        "Z" | "z" => 0,
+        offset => {
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let (hours, minutes): (i64, i64) = (parse_digits(hours, 2)?, parse_digits(minutes, 2)?);
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            sign * (hours * 3600 + minutes * 60)
+        },
+    };
+
+    let seconds = days_from_civil(year, month, day)?
+        .checked_mul(SECONDS_PER_DAY)?
+        .checked_add(hour * 3600 + minute * 60 + second - offset_seconds)?;
+    Some((Timestamp::new(seconds, nanos)?, (offset_seconds / 60) as i32))
+}
+
+/// Parse exactly `len` ASCII digits
+fn parse_digits<T: FromStr>(s: &str, len: usize) -> Option<T> {
+    (s.len() == len && s.bytes().all(|b| b.is_ascii_digit())).then(|| s.parse().ok()).flatten()
+}
@@ -0,0 +1,146 @@
+//! Serde support for MessagePack-CSharp `[Union]` types.
+//!
+//! MessagePack-CSharp writes a union as a two-element array `[key, body]`. A
+//! Rust enum standing for a union serializes each variant with
+//! [`serialize_case`] and deserializes through [`RawUnion`]:
+//!
+//! ```text
+//! impl Serialize for IRoute {
+//!     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+//!         match self {
+//!             IRoute::CarRoute(body) => serialize_case(serializer, 0, "CarRoute", body),
+//!             IRoute::BikeRoute(body) => serialize_case(serializer, 1, "BikeRoute", body),
+//!         }
+//!     }
+//! }
+//!
+//! impl<'de> Deserialize<'de> for IRoute {
+//!     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+//!         let union = RawUnion::deserialize(deserializer)?;
+//!         if union.is(0, "CarRoute") {
+//!             return union.into_body().map(IRoute::CarRoute);
+//!         }
+//!         if union.is(1, "BikeRoute") {
+//!             return union.into_body().map(IRoute::BikeRoute);
+//!         }
+//!         Err(union.unknown("IRoute"))
+//!     }
+//! }
+//! ```
+//!
+//! MessagePack gets the `[key, body]` form; human-readable formats such as
+//! JSON get the body's object with a `"$type"` member naming the case, the
+//! same form the schema-driven JSON output uses. Both forms deserialize.
+//! `generate-rust` writes these impls for every union in a schema.
+
+use std::fmt;
+
+use rmpv::Value;
+use serde::de::{self, DeserializeOwned, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, Serializer};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value as JsonValue;
+
+/// Serialize one case of a union: `[key, body]`, or `{"$type": type_name, ...body}` when human-readable
+pub fn serialize_case<S, T>(serializer: S, key: i64, type_name: &str, body: &T) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize + ?Sized,
+{
+    if !serializer.is_human_readable() {
+        return (key, body).serialize(serializer);
+    }
+
+    let mut object = match serde_json::to_value(body).map_err(ser::Error::custom)? {
+        JsonValue::Object(object) => object,
+        other => serde_json::Map::from_iter([("$value".to_string(), other)]),
+    };
+    object.insert("$type".to_string(), JsonValue::from(type_name));
+    object.serialize(serializer)
+}
+
+/// How a union value names its case
+#[derive(Debug, Clone, PartialEq)]
+enum Tag {
+    Key(i64),
+    Type(String),
+}
+
+/// A union value read as `[key, body]` or `{"$type": ..., ...}`, before its body is deserialized
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawUnion {
+    tag: Tag,
+    body: Value,
+}
+
+impl RawUnion {
+    /// Whether this value is the case with the given `[Union]` key and type name
+    pub fn is(&self, key: i64, type_name: &str) -> bool {
+        match &self.tag {
+            Tag::Key(tag) => *tag == key,
+            Tag::Type(tag) => tag == type_name,
+        }
+    }
+
+    /// Deserialize the body as the case's type
+    pub fn into_body<T: DeserializeOwned, E: de::Error>(self) -> Result<T, E> {
+        rmpv::ext::from_value(self.body).map_err(E::custom)
+    }
+
+    /// Error for a value that matches none of the cases of `union`
+    pub fn unknown<E: de::Error>(&self, union: &str) -> E {
+        match &self.tag {
+            Tag::Key(key) => E::custom(format!("unknown {} union key {}", union, key)),
+            Tag::Type(type_name) => E::custom(format!("unknown {} union type {}", union, type_name)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RawUnion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(RawUnionVisitor)
+    }
+}
+
+struct RawUnionVisitor;
+
+impl<'de> Visitor<'de> for RawUnionVisitor {
+    type Value = RawUnion;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a [key, body] union or an object with a $type")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<RawUnion, A::Error> {
+        let key: i64 = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let body: Value = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(3, &self));
+        }
+        Ok(RawUnion { tag: Tag::Key(key), body })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RawUnion, A::Error> {
+        let mut tag = None;
+        let mut value = None;
+        let mut fields = Vec::new();
+
+        while let Some(key) = map.next_key::<Value>()? {
+            match key.as_str() {
+                Some("$type") => {
+                    let found = match map.next_value::<Value>()? {
+                        Value::String(name) => name.into_str().map(Tag::Type),
+                        Value::Integer(key) => key.as_i64().map(Tag::Key),
+                        _ => None,
+                    };
+                    tag = Some(found.ok_or_else(|| de::Error::custom("$type must be a case type name or key"))?);
+                },
+                Some("$value") => value = Some(map.next_value::<Value>()?),
+                _ => fields.push((key, map.next_value::<Value>()?)),
+            }
+        }
+
+        let tag = tag.ok_or_else(|| de::Error::missing_field("$type"))?;
+        Ok(RawUnion { tag, body: value.unwrap_or(Value::Map(fields)) })
+    }
+}
@@ -0,0 +1,6 @@
+//! Dados compartilhados pelos testes de integração
+
+/// `count` linhas `(id, nome)`, o bastante para ocupar vários blocos LZ4 quando `count` é grande
+pub fn rows(count: u32) -> Vec<(u32, String)> {
+    (0..count).map(|id| (id, format!("linha número {}", id))).collect()
+}
@@ -0,0 +1,86 @@
+using System.Collections.Generic;
+using MessagePack;
+
+namespace Routing.Contracts
+{
+    // Same layout as tests/schemas/route.json, written the way the API declares it
+    [MessagePackObject]
+    public class RouteResponse
+    {
+        [Key(0)] public string Code { get; set; } = "Ok";
+        [Key(1)] public List<Route> Routes { get; set; } = new List<Route>();
+        [Key(2)] public Waypoint[] Waypoints { get; set; }
+
+        [IgnoreMember]
+        public bool IsOk => Code == "Ok";
+
+        public static RouteResponse Empty() => new RouteResponse { Code = "NoRoute" };
+    }
+
+    [MessagePackObject]
+    public class Route
+    {
+        [Key(0)] public IReadOnlyList<Leg> Legs { get; set; }
+        [Key(1)] public string WeightName { get; set; }
+        [Key(2)] public double Weight { get; set; }
+        [Key(3)] public double Duration { get; set; }
+        [Key(4)] public double Distance { get; set; }
+    }
+
+    [MessagePackObject]
+    public class Leg
+    {
+        [Key(0)] public List<Step> Steps { get; set; }
+        [Key(1)] public string Summary { get; set; }
+        [Key(2)] public double Weight { get; set; }
+        [Key(3)] public double Duration { get; set; }
+        [Key(4)] public double Distance { get; set; }
+    }
+
+    [MessagePackObject]
+    public class Step
+    {
+        [Key(0)] public string Geometry { get; set; }
+        [Key(1)] public Maneuver Maneuver { get; set; }
+        [Key(2)] public string Mode { get; set; }
+        [Key(3)] public string? DrivingSide { get; set; }
+        [Key(4)] public string Name { get; set; }
+        [Key(5)] public List<Intersection> Intersections { get; set; }
+        [Key(6)] public double Weight { get; set; }
+        [Key(7)] public double Duration { get; set; }
+        [Key(8)] public double Distance { get; set; }
+        [Key(9)] public string? Ref { get; set; }
+
+        public Step() { }
+
+        public override string ToString() => $"{Name} ({Mode}) [{Distance} m]";
+    }
+
+    [MessagePackObject]
+    public class Maneuver
+    {
+        [Key(0)] public int BearingAfter { get; set; }
+        [Key(1)] public int BearingBefore { get; set; }
+        [Key(2)] public double[] Location { get; set; }
+        [Key(3)] public string Modifier { get; set; }
+        /* [Key(5)] public string Exit { get; set; } */
+        [Key(4)] public string Type { get; set; }
+    }
+
+    [MessagePackObject]
+    public struct Intersection
+    {
+        [Key(0)] public int? Out;
+        [Key(1)] public bool[] Entry;
+        [Key(2)] public List<int> Bearings;
+        [Key(3)] public double[] Location;
+        [Key(4)] public Nullable<int> In;
+    }
+
+    [MessagePackObject]
+    public record Waypoint(
+        [property: Key(0)] string Hint,
+        [property: Key(1)] double Distance,
+        [property: Key(2)] string Name,
+        [property: Key(3)] double[] Location);
+}
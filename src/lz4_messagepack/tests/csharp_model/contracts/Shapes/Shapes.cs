@@ -0,0 +1,67 @@
+using System;
+using System.Collections.Generic;
+using MessagePack;
+
+namespace Drawing;
+
+public enum Color : byte { Red, Green, Blue }
+
+[Union(0, typeof(Circle))]
+[Union(1, typeof(Drawing.Square))]
+public interface IShape
+{
+    string Describe();
+}
+
+[MessagePackObject]
+public abstract class ShapeBase
+{
+    [Key(0)] public Color Color { get; set; }
+    [Key(1)] public Dictionary<string, string> Labels { get; set; } = new();
+}
+
+[MessagePackObject]
+public partial class Circle : ShapeBase, IShape
+{
+    [Key(2)] public float Radius { get; init; }
+
+    public string Describe() => "circle \"[Key(9)]\"";
+}
+
+public partial class Circle
+{
+    [Key(3)] public byte[]? Thumbnail { get; set; }
+}
+
+[MessagePackObject]
+public sealed class Square : ShapeBase, IShape
+{
+    [Key(2)] public decimal Side { get; set; }
+
+    public string Describe() => @"square ""[Key(8)]""";
+}
+
+[MessagePackObject(keyAsPropertyName: true)]
+public class Canvas
+{
+    public string Title { get; set; }
+    public IShape[] Shapes { get; set; }
+    public Dictionary<int, List<IShape>> Layers { get; set; }
+    public Guid Id { get; set; }
+    public (int, int) Size { get; set; }
+    public Uri Source { get; set; }
+
+    [Key("created_by")]
+    public string Author { get; set; }
+
+    [Key("created_at")]
+    public DateTime CreatedAt { get; set; }
+
+    public TimeSpan Duration { get; set; }
+    public DateTimeOffset? UpdatedAt { get; set; }
+
+    [IgnoreMember]
+    public int Revision { get; set; }
+
+    private int cache;
+}
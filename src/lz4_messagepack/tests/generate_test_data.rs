@@ -1,6 +1,6 @@
 use serde_json::{json, Value as JsonValue};
 use std::fs;
-use std::io::{self, Write, Read};
+use std::io;
 use std::path::Path;
 use rmpv::{Value, Integer, Utf8String};
 use rmpv::encode::write_value;
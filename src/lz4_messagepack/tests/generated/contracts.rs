@@ -0,0 +1,235 @@
+// Generated from MessagePack-CSharp contracts by `lz4_messagepack generate-rust`.
+// Positional contracts use the array layout: encode with `to_vec_lz4`, decode with `from_slice_lz4`.
+
+use serde::{Deserialize, Serialize};
+use lz4_messagepack::unions::{serialize_case, RawUnion};
+
+/// `Canvas`, written as a map keyed by name
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Canvas {
+    /// `[Key("Title")]`
+    #[serde(rename = "Title")]
+    pub title: String,
+    /// `[Key("Shapes")]`
+    #[serde(rename = "Shapes")]
+    pub shapes: Vec<IShape>,
+    /// `[Key("Layers")]`
+    #[serde(rename = "Layers")]
+    pub layers: std::collections::BTreeMap<i64, Vec<IShape>>,
+    /// `[Key("Id")]`
+    #[serde(rename = "Id")]
+    pub id: String,
+    /// `[Key("Size")]`
+    #[serde(rename = "Size")]
+    pub size: rmpv::Value,
+    /// `[Key("Source")]`
+    #[serde(rename = "Source")]
+    pub source: rmpv::Value,
+    /// `[Key("created_by")]`
+    #[serde(rename = "created_by")]
+    pub author: String,
+    /// `[Key("created_at")]`
+    pub created_at: lz4_messagepack::Timestamp,
+    /// `[Key("Duration")]`
+    #[serde(rename = "Duration")]
+    pub duration: i64,
+    /// `[Key("UpdatedAt")]`
+    #[serde(rename = "UpdatedAt", default)]
+    pub updated_at: Option<(lz4_messagepack::Timestamp, i16)>,
+}
+
+impl Serialize for Canvas {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(Some(10))?;
+        map.serialize_entry("Title", &self.title)?;
+        map.serialize_entry("Shapes", &self.shapes)?;
+        map.serialize_entry("Layers", &self.layers)?;
+        map.serialize_entry("Id", &self.id)?;
+        map.serialize_entry("Size", &self.size)?;
+        map.serialize_entry("Source", &self.source)?;
+        map.serialize_entry("created_by", &self.author)?;
+        map.serialize_entry("created_at", &self.created_at)?;
+        map.serialize_entry("Duration", &self.duration)?;
+        map.serialize_entry("UpdatedAt", &self.updated_at)?;
+        map.end()
+    }
+}
+
+/// `Circle`, written as an array in key order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Circle {
+    /// `[Key(0)]`
+    pub color: i64,
+    /// `[Key(1)]`
+    pub labels: std::collections::BTreeMap<String, String>,
+    /// `[Key(2)]`
+    pub radius: f64,
+    /// `[Key(3)]`
+    #[serde(default)]
+    pub thumbnail: Option<serde_bytes::ByteBuf>,
+}
+
+/// `IShape` union, written as `[key, body]`
+#[derive(Debug, Clone, PartialEq)]
+pub enum IShape {
+    /// `[Union(0, typeof(Circle))]`
+    Circle(Circle),
+    /// `[Union(1, typeof(Square))]`
+    Square(Square),
+}
+
+impl Serialize for IShape {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            IShape::Circle(body) => serialize_case(serializer, 0, "Circle", body),
+            IShape::Square(body) => serialize_case(serializer, 1, "Square", body),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for IShape {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let union = RawUnion::deserialize(deserializer)?;
+        if union.is(0, "Circle") {
+            return union.into_body().map(IShape::Circle);
+        }
+        if union.is(1, "Square") {
+            return union.into_body().map(IShape::Square);
+        }
+        Err(union.unknown("IShape"))
+    }
+}
+
+/// `Intersection`, written as an array in key order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Intersection {
+    /// `[Key(0)]`
+    #[serde(default)]
+    pub out: Option<i64>,
+    /// `[Key(1)]`
+    pub entry: Vec<bool>,
+    /// `[Key(2)]`
+    pub bearings: Vec<i64>,
+    /// `[Key(3)]`
+    pub location: Vec<f64>,
+    /// `[Key(4)]`
+    #[serde(default)]
+    pub r#in: Option<i64>,
+}
+
+/// `Leg`, written as an array in key order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Leg {
+    /// `[Key(0)]`
+    pub steps: Vec<Step>,
+    /// `[Key(1)]`
+    pub summary: String,
+    /// `[Key(2)]`
+    pub weight: f64,
+    /// `[Key(3)]`
+    pub duration: f64,
+    /// `[Key(4)]`
+    pub distance: f64,
+}
+
+/// `Maneuver`, written as an array in key order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Maneuver {
+    /// `[Key(0)]`
+    pub bearing_after: i64,
+    /// `[Key(1)]`
+    pub bearing_before: i64,
+    /// `[Key(2)]`
+    pub location: Vec<f64>,
+    /// `[Key(3)]`
+    pub modifier: String,
+    /// `[Key(4)]`
+    pub r#type: String,
+}
+
+/// `Route`, written as an array in key order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Route {
+    /// `[Key(0)]`
+    pub legs: Vec<Leg>,
+    /// `[Key(1)]`
+    pub weight_name: String,
+    /// `[Key(2)]`
+    pub weight: f64,
+    /// `[Key(3)]`
+    pub duration: f64,
+    /// `[Key(4)]`
+    pub distance: f64,
+}
+
+/// `RouteResponse`, written as an array in key order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteResponse {
+    /// `[Key(0)]`
+    pub code: String,
+    /// `[Key(1)]`
+    pub routes: Vec<Route>,
+    /// `[Key(2)]`
+    pub waypoints: Vec<Waypoint>,
+}
+
+/// `ShapeBase`, written as an array in key order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShapeBase {
+    /// `[Key(0)]`
+    pub color: i64,
+    /// `[Key(1)]`
+    pub labels: std::collections::BTreeMap<String, String>,
+}
+
+/// `Square`, written as an array in key order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Square {
+    /// `[Key(0)]`
+    pub color: i64,
+    /// `[Key(1)]`
+    pub labels: std::collections::BTreeMap<String, String>,
+    /// `[Key(2)]`
+    pub side: String,
+}
+
+/// `Step`, written as an array in key order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    /// `[Key(0)]`
+    pub geometry: String,
+    /// `[Key(1)]`
+    pub maneuver: Maneuver,
+    /// `[Key(2)]`
+    pub mode: String,
+    /// `[Key(3)]`
+    #[serde(default)]
+    pub driving_side: Option<String>,
+    /// `[Key(4)]`
+    pub name: String,
+    /// `[Key(5)]`
+    pub intersections: Vec<Intersection>,
+    /// `[Key(6)]`
+    pub weight: f64,
+    /// `[Key(7)]`
+    pub duration: f64,
+    /// `[Key(8)]`
+    pub distance: f64,
+    /// `[Key(9)]`
+    #[serde(default)]
+    pub r#ref: Option<String>,
+}
+
+/// `Waypoint`, written as an array in key order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Waypoint {
+    /// `[Key(0)]`
+    pub hint: String,
+    /// `[Key(1)]`
+    pub distance: f64,
+    /// `[Key(2)]`
+    pub name: String,
+    /// `[Key(3)]`
+    pub location: Vec<f64>,
+}
@@ -5,8 +5,7 @@ use std::fs;
 use std::path::Path;
 
 // Import o código da aplicação principal
-#[path = "../src/main.rs"]
-mod app;
+use lz4_messagepack as app;
 
 // Função para gerar um arquivo de teste com dados MessagePack LZ4BlockArray
 fn generate_test_data(test_name: &str, content: &JsonValue) -> String {
@@ -54,7 +53,7 @@ fn test_invalid_type() {
                 "type": "Buffer",
                 "data": [204, 100]
             },
-            "type": 99  // Tipo diferente de 98 (LZ4BlockArray)
+            "type": 50  // Tipo diferente de 98 (LZ4BlockArray) e 99 (LZ4Block)
         },
         {
             "type": "Buffer",
@@ -144,7 +143,15 @@ fn test_valid_data() {
     ]);
     
     let file_path = generate_test_data("valid_data", &valid_data);
-    let result = app::LZ4MessagePackProcessor::process(Some(&file_path), app::OutputFormat::Human);
+
+    // Sem schema o array posicional não ganha nomes de campos
+    let content = app::LZ4MessagePackProcessor::process(Some(&file_path), app::OutputFormat::Human).unwrap();
+    assert!(content.contains("Phone number is required"), "Should contain expected title");
+    assert!(!content.contains("title"), "Fields should not be guessed without a schema");
+
+    // Com o schema de ProblemDetails os campos são nomeados
+    let options = app::JsonOptions { schema: Some(app::Schema::problem_details()), ..Default::default() };
+    let result = app::LZ4MessagePackProcessor::process_with(Some(&file_path), app::OutputFormat::Human, &options);
     
     assert!(result.is_ok(), "Should successfully process valid data");
     let content = result.unwrap();
@@ -197,7 +204,7 @@ fn test_different_formats() {
     let hex_result = app::LZ4MessagePackProcessor::process(Some(&file_path), app::OutputFormat::Hex);
     assert!(hex_result.is_ok(), "HEX format should succeed");
     let hex_content = hex_result.unwrap();
-    assert!(hex_content.chars().all(|c| c.is_digit(16) || c.is_ascii_lowercase() && c >= 'a' && c <= 'f'), 
+    assert!(hex_content.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)), 
         "HEX should only contain hexadecimal characters");
 }
 
@@ -0,0 +1,135 @@
+use std::fs;
+use std::path::PathBuf;
+
+use lz4_messagepack::codegen::generate_rust;
+use lz4_messagepack::csharp::import_schema_from_paths;
+use lz4_messagepack::{decode, from_slice_lz4, to_vec_lz4, Compression, Envelope, Error, Schema};
+use serde_json::json;
+
+// Modelos gerados a partir de tests/csharp_model/contracts com `generate-rust`
+#[allow(dead_code)]
+#[path = "generated/contracts.rs"]
+mod contracts;
+
+use contracts::{Canvas, Circle, IShape, RouteResponse, Square};
+
+const GENERATED: &str = "tests/generated/contracts.rs";
+
+fn route_envelope() -> Envelope {
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string("tests/csharp_model/route_example.json").unwrap()).unwrap();
+    Envelope::parse_json(&json).unwrap().remove(0)
+}
+
+#[test]
+fn test_generated_models_are_up_to_date() {
+    let schema = import_schema_from_paths(&[PathBuf::from("tests/csharp_model/contracts")], None).unwrap();
+    let generated = generate_rust(&schema).unwrap();
+    assert_eq!(
+        generated,
+        fs::read_to_string(GENERATED).unwrap(),
+        "Regenerate with: cargo run -- generate-rust tests/csharp_model/contracts --output={}",
+        GENERATED
+    );
+}
+
+#[test]
+fn test_generated_models_decode_csharp_payload() {
+    // O mesmo envelope de test_csharp_model, agora com modelos gerados
+    let envelope = route_envelope();
+    let route: RouteResponse = from_slice_lz4(&envelope.to_msgpack().unwrap()).unwrap();
+
+    assert_eq!(route.code, "Ok");
+    assert_eq!(route.waypoints[1].name, "End");
+    let step = &route.routes[0].legs[0].steps[0];
+    assert_eq!(step.maneuver.r#type, "depart");
+    assert_eq!(step.r#ref.as_deref(), Some("A1"));
+    assert_eq!(step.intersections[0].r#in, Some(0));
+
+    // Serializar de volta produz o mesmo MessagePack
+    let encoded = to_vec_lz4(&route, Compression::Lz4BlockArray).unwrap();
+    assert_eq!(decode(&encoded).unwrap(), envelope.decompress().unwrap());
+}
+
+#[test]
+fn test_generated_unions_and_map_layout() {
+    let circle = Circle { color: 2, labels: [("k".to_string(), "v".to_string())].into(), radius: 1.5, thumbnail: None };
+    let square = Square { color: 0, labels: Default::default(), side: "2.50".to_string() };
+    let canvas = Canvas {
+        title: "t".to_string(),
+        shapes: vec![IShape::Circle(circle.clone()), IShape::Square(square)],
+        layers: [(1, vec![IShape::Circle(circle)])].into(),
+        id: "0f8fad5b-d9cb-469f-a165-70867728950e".to_string(),
+        size: rmpv::Value::Array(vec![1.into(), 2.into()]),
+        source: rmpv::Value::Nil,
+        author: "me".to_string(),
+        created_at: "2024-05-01T12:30:00.250Z".parse().unwrap(),
+        duration: 54_000_000_000,
+        updated_at: Some(("2024-05-01T14:30:00Z".parse().unwrap(), 120)),
+    };
+
+    // Unions como [key, body]; contratos com chaves nomeadas como mapas, mesmo no layout de array
+    let envelope = to_vec_lz4(&canvas, Compression::Lz4Block).unwrap();
+    let value = rmpv::decode::read_value(&mut decode(&envelope).unwrap().as_slice()).unwrap();
+    let json = lz4_messagepack::value_to_json(&value);
+    assert_eq!(json["Shapes"], json!([[0, [2, { "k": "v" }, 1.5, null]], [1, [0, {}, "2.50"]]]));
+    assert_eq!(json["created_by"], "me");
+    assert_eq!(json["created_at"], "2024-05-01T12:30:00.250Z");
+
+    assert_eq!(from_slice_lz4::<Canvas>(&envelope).unwrap(), canvas);
+
+    let unknown = to_vec_lz4(&(7, [1]), Compression::Lz4BlockArray).unwrap();
+    let error = from_slice_lz4::<IShape>(&unknown).unwrap_err();
+    assert!(error.to_string().contains("unknown IShape union key 7"), "Unexpected error: {}", error);
+}
+
+#[test]
+fn test_generation_from_schema_files() {
+    let generated = generate_rust(&Schema::from_file("tests/schemas/route.json").unwrap()).unwrap();
+    assert!(generated.contains("pub struct Root {"));
+    assert!(generated.contains("    pub driving_side: Option<String>,"));
+
+    // Só os tipos alcançáveis a partir da raiz; gaps viram Option<()>; tipos recursivos usam Box
+    let schema: Schema = r#"{
+        "root": "Node",
+        "types": {
+            "Node": { "fields": [
+                { "key": 0, "name": "value", "type": "int" },
+                { "key": 2, "name": "next", "type": "Node?" },
+                { "key": 3, "name": "children", "type": "Node[]" }
+            ] },
+            "Unused": { "fields": [] }
+        }
+    }"#.parse().unwrap();
+    let generated = generate_rust(&schema).unwrap();
+    assert!(generated.contains("    pub _key1: Option<()>,"));
+    assert!(generated.contains("    pub next: Option<Box<Node>>,"));
+    assert!(generated.contains("    pub children: Vec<Node>,"));
+    assert!(!generated.contains("pub struct Unused"));
+
+    let mixed: Schema = r#"{ "types": { "A": { "fields": [ { "key": 0, "name": "a" }, { "key": "b", "name": "b" } ] } } }"#
+        .parse().unwrap();
+    assert!(matches!(generate_rust(&mixed), Err(Error::InvalidSchema(_))));
+}
+
+#[test]
+fn test_generated_unions_use_type_tags_in_json() {
+    let shapes = vec![
+        IShape::Circle(Circle { color: 1, labels: Default::default(), radius: 2.0, thumbnail: Some(vec![7].into()) }),
+        IShape::Square(Square { color: 2, labels: Default::default(), side: "1.25".to_string() }),
+    ];
+
+    // JSON (formato legível) usa $type; MessagePack continua com [key, body]
+    let json = serde_json::to_value(&shapes).unwrap();
+    assert_eq!(json[0]["$type"], "Circle");
+    assert_eq!(json[1], json!({ "$type": "Square", "color": 2, "labels": {}, "side": "1.25" }));
+    assert_eq!(serde_json::from_value::<Vec<IShape>>(json.clone()).unwrap(), shapes);
+
+    let from_json: Vec<IShape> = serde_json::from_value(json).unwrap();
+    let envelope = to_vec_lz4(&from_json, Compression::Lz4BlockArray).unwrap();
+    let value = rmpv::decode::read_value(&mut decode(&envelope).unwrap().as_slice()).unwrap();
+    assert_eq!(value[1][0].as_i64(), Some(1));
+    assert_eq!(from_slice_lz4::<Vec<IShape>>(&envelope).unwrap(), shapes);
+
+    let error = serde_json::from_value::<IShape>(json!({ "$type": "Triangle" })).unwrap_err();
+    assert!(error.to_string().contains("unknown IShape union type Triangle"), "Unexpected error: {}", error);
+}
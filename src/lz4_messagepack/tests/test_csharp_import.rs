@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+
+use lz4_messagepack::csharp::{import_schema, import_schema_from_paths};
+use lz4_messagepack::schema::{FieldKey, FieldType, TypeDef};
+use lz4_messagepack::{Error, JsonOptions, LZ4MessagePackProcessor, OutputFormat, Schema};
+use serde_json::{json, Value as JsonValue};
+
+fn contracts(root: Option<&str>) -> Schema {
+    import_schema_from_paths(&[PathBuf::from("tests/csharp_model/contracts")], root).unwrap()
+}
+
+fn fields(schema: &Schema, name: &str) -> Vec<(FieldKey, String, String)> {
+    match &schema.types[name] {
+        TypeDef::Object { fields } => fields.iter()
+            .map(|field| (field.key.clone(), field.name.clone(), field.field_type.to_string()))
+            .collect(),
+        other => panic!("{} is not an object: {:?}", name, other),
+    }
+}
+
+#[test]
+fn test_imported_schema_names_route_example() {
+    // Mesmo payload de test_csharp_model, sem modelos escritos à mão
+    let options = JsonOptions { schema: Some(contracts(Some("RouteResponse"))), ..Default::default() };
+    let output = LZ4MessagePackProcessor::process_with(
+        Some("tests/csharp_model/route_example.json"),
+        OutputFormat::Human,
+        &options,
+    ).unwrap();
+    let output: JsonValue = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(output["Code"], "Ok");
+    assert_eq!(output["Waypoints"][1]["Name"], "End");
+
+    let step = &output["Routes"][0]["Legs"][0]["Steps"][0];
+    assert_eq!(step["Maneuver"]["Type"], "depart");
+    assert_eq!(step["Intersections"][0]["Entry"], json!([true, false]));
+    assert_eq!(step["Ref"], "A1");
+}
+
+#[test]
+fn test_property_types() {
+    let schema = contracts(None);
+    let types = |name| fields(&schema, name).into_iter().map(|(_, name, field_type)| (name, field_type)).collect::<Vec<_>>();
+    let pairs = |pairs: &[(&str, &str)]| pairs.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect::<Vec<_>>();
+
+    // List<T>, IReadOnlyList<T> e T[] viram arrays; T? e Nullable<T> viram nullable
+    assert_eq!(types("Route")[0], ("Legs".to_string(), "Leg[]".to_string()));
+    assert_eq!(types("Intersection"), pairs(&[
+        ("Out", "int?"), ("Entry", "bool[]"), ("Bearings", "int[]"), ("Location", "float[]"), ("In", "int?"),
+    ]));
+    assert_eq!(types("Step")[1], ("Maneuver".to_string(), "Maneuver".to_string()));
+    assert_eq!(types("Step")[3], ("DrivingSide".to_string(), "string?".to_string()));
+
+    // Parâmetros de record com [property: Key(n)]
+    assert_eq!(types("Waypoint"), pairs(&[("Hint", "string"), ("Distance", "float"), ("Name", "string"), ("Location", "float[]")]));
+
+    // Membros com [IgnoreMember], estáticos, métodos e comentários ficam de fora
+    assert_eq!(types("RouteResponse").len(), 3);
+    assert_eq!(types("Maneuver").len(), 5);
+}
+
+#[test]
+fn test_unions_inheritance_and_property_name_keys() {
+    let schema = contracts(None);
+
+    match &schema.types["IShape"] {
+        TypeDef::Union { cases } => {
+            let cases: Vec<_> = cases.iter().map(|case| (case.key, case.type_name.as_str())).collect();
+            assert_eq!(cases, [(0, "Circle"), (1, "Square")]);
+        },
+        other => panic!("IShape is not a union: {:?}", other),
+    }
+
+    // Chaves da classe base vêm antes; partes de classes partial se juntam; enums são inteiros
+    let circle: Vec<_> = fields(&schema, "Circle").into_iter().map(|(key, name, field_type)| (key, name + ": " + &field_type)).collect();
+    assert_eq!(circle, [
+        (FieldKey::Index(0), "Color: int".to_string()),
+        (FieldKey::Index(1), "Labels: map<string, string>".to_string()),
+        (FieldKey::Index(2), "Radius: float".to_string()),
+        (FieldKey::Index(3), "Thumbnail: binary?".to_string()),
+    ]);
+    assert_eq!(fields(&schema, "Square")[2].2, "decimal");
+
+    // keyAsPropertyName: true usa o nome da propriedade, a menos que haja [Key("...")]
+    assert_eq!(fields(&schema, "Canvas"), [
+        (FieldKey::Name("Title".to_string()), "Title".to_string(), "string".to_string()),
+        (FieldKey::Name("Shapes".to_string()), "Shapes".to_string(), "IShape[]".to_string()),
+        (FieldKey::Name("Layers".to_string()), "Layers".to_string(), "map<int, IShape[]>".to_string()),
+        (FieldKey::Name("Id".to_string()), "Id".to_string(), "guid".to_string()),
+        (FieldKey::Name("Size".to_string()), "Size".to_string(), "any".to_string()),
+        (FieldKey::Name("Source".to_string()), "Source".to_string(), "any".to_string()),
+        (FieldKey::Name("created_by".to_string()), "Author".to_string(), "string".to_string()),
+        (FieldKey::Name("created_at".to_string()), "CreatedAt".to_string(), "timestamp".to_string()),
+        (FieldKey::Name("Duration".to_string()), "Duration".to_string(), "timespan".to_string()),
+        (FieldKey::Name("UpdatedAt".to_string()), "UpdatedAt".to_string(), "datetimeoffset?".to_string()),
+    ]);
+
+    // Interfaces e enums não viram contratos
+    assert!(!schema.types.contains_key("Color"));
+    assert_eq!(Schema::from_json(&schema.to_json()).unwrap(), schema);
+}
+
+#[test]
+fn test_import_from_source_text() {
+    let source = r#"
+        [MessagePack.MessagePackObject]
+        public class Page<T>
+        {
+            // [Key(5)] public string Commented { get; set; }
+            [global::MessagePack.Key(0)] public T[] Items { get; set; }
+            [Key(1)] public int? Total { get; set; } = null;
+            [Key(2)] public ReadOnlyMemory<byte> Cursor { get; set; }
+            [Key(3)] public IDictionary<string, object> Extra { get; set; }
+        }
+    "#;
+    let schema = import_schema(&[source.to_string()], Some("Page")).unwrap();
+    assert_eq!(schema.root.as_deref(), Some("Page"));
+    assert_eq!(fields(&schema, "Page").into_iter().map(|(_, _, field_type)| field_type).collect::<Vec<_>>(), [
+        "any[]", "int?", "binary", "map<string, any>",
+    ]);
+    assert_eq!("any[]".parse::<FieldType>().unwrap(), FieldType::Array(Box::new(FieldType::Any)));
+}
+
+#[test]
+fn test_import_errors() {
+    // Casos de union precisam estar nos arquivos lidos
+    let source = "[Union(0, typeof(Missing))] public interface IThing { }".to_string();
+    assert!(matches!(import_schema(&[source], None), Err(Error::InvalidSchema(_))));
+
+    assert!(matches!(
+        import_schema(&["[MessagePackObject] class A { }".to_string()], Some("B")),
+        Err(Error::InvalidSchema(_))
+    ));
+    assert!(matches!(
+        import_schema_from_paths(&[PathBuf::from("tests/csharp_model/missing.cs")], None),
+        Err(Error::Io { .. })
+    ));
+
+    // Um prefixo de string no fim do arquivo, sem aspas depois, é um erro e não um panic
+    for source in ["var x = $$", "var x = @$", "var x = $@ + 1;"] {
+        let error = import_schema(&[source.to_string()], None).unwrap_err();
+        assert!(matches!(error, Error::InvalidInput(_)), "{}: {}", source, error);
+    }
+    // Strings com prefixo continuam sendo lidas
+    assert!(import_schema(&[r#"var x = $@"a{b}"; var y = @$"c";"#.to_string()], None).is_ok());
+}
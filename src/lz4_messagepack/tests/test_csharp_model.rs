@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::fs;
 use std::path::Path;
 use lz4::block::compress;
@@ -6,145 +7,167 @@ use rmpv::Value;
 use rmpv::encode::write_value;
 
 // Import o código da aplicação principal
-#[path = "../src/main.rs"]
-mod app;
-use app::LZ4MessagePackProcessor;
-use app::OutputFormat;
+use lz4_messagepack::models::{from_msgpack, to_msgpack};
+use lz4_messagepack::{
+    encode_value, from_slice_lz4, json_to_value, to_vec_lz4_with, Compression, LZ4MessagePackProcessor,
+    OutputFormat, StructEncoding,
+};
 
-// Modelos equivalentes aos modelos C#
-#[derive(Debug, Deserialize, Serialize)]
+// Modelos equivalentes aos modelos C# com [Key(n)]: os campos seguem a ordem das chaves
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
 struct Intersection {
-    #[serde(rename = "0")]
     pub out: i32,
-    #[serde(rename = "1")]
     pub entry: Vec<bool>,
-    #[serde(rename = "2")]
     pub bearings: Vec<i32>,
-    #[serde(rename = "3")]
     pub location: Vec<f64>,
-    #[serde(rename = "4")]
     pub in_value: Option<i32>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
 struct Leg {
-    #[serde(rename = "0")]
     pub steps: Vec<Step>,
-    #[serde(rename = "1")]
     pub summary: String,
-    #[serde(rename = "2")]
     pub weight: f64,
-    #[serde(rename = "3")]
     pub duration: f64,
-    #[serde(rename = "4")]
     pub distance: f64,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
 struct Maneuver {
-    #[serde(rename = "0")]
     pub bearing_after: i32,
-    #[serde(rename = "1")]
     pub bearing_before: i32,
-    #[serde(rename = "2")]
     pub location: Vec<f64>,
-    #[serde(rename = "3")]
     pub modifier: String,
-    #[serde(rename = "4")]
     pub maneuver_type: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
 struct Route {
-    #[serde(rename = "0")]
     pub legs: Vec<Leg>,
-    #[serde(rename = "1")]
     pub weight_name: String,
-    #[serde(rename = "2")]
     pub weight: f64,
-    #[serde(rename = "3")]
     pub duration: f64,
-    #[serde(rename = "4")]
     pub distance: f64,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
 struct Step {
-    #[serde(rename = "0")]
     pub geometry: String,
-    #[serde(rename = "1")]
     pub maneuver: Maneuver,
-    #[serde(rename = "2")]
     pub mode: String,
-    #[serde(rename = "3")]
     pub driving_side: String,
-    #[serde(rename = "4")]
     pub name: String,
-    #[serde(rename = "5")]
     pub intersections: Vec<Intersection>,
-    #[serde(rename = "6")]
     pub weight: f64,
-    #[serde(rename = "7")]
     pub duration: f64,
-    #[serde(rename = "8")]
     pub distance: f64,
-    #[serde(rename = "9")]
     pub reference: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
 struct Waypoint {
-    #[serde(rename = "0")]
     pub hint: String,
-    #[serde(rename = "1")]
     pub distance: f64,
-    #[serde(rename = "2")]
     pub name: String,
-    #[serde(rename = "3")]
     pub location: Vec<f64>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
 struct Root {
-    #[serde(rename = "0")]
     pub code: String,
-    #[serde(rename = "1")]
     pub routes: Vec<Route>,
-    #[serde(rename = "2")]
     pub waypoints: Vec<Waypoint>,
 }
 
+// Dados de rota como o MessagePack-CSharp grava modelos com [Key(n)]: arrays posicionais
+fn route_data() -> Value {
+    json_to_value(&json!([
+        // code
+        "Ok",
+        // routes
+        [[
+            // legs
+            [[
+                // steps
+                [[
+                    "linestring",
+                    // maneuver
+                    [90, 0, [13.388798, 52.517033], "turn right", "depart"],
+                    "driving",
+                    "right",
+                    "Main Street",
+                    // intersections
+                    [[1, [true, false], [90, 270], [13.388798, 52.517033], 0]],
+                    10.5,
+                    60.0,
+                    500.0,
+                    "A1"
+                ]],
+                "Main Street",
+                10.5,
+                60.0,
+                500.0
+            ]],
+            "routability",
+            10.5,
+            60.0,
+            500.0
+        ]],
+        // waypoints
+        [
+            ["hint1", 5.0, "Start", [13.388798, 52.517033]],
+            ["hint2", 5.0, "End", [13.397631, 52.529432]]
+        ]
+    ])).unwrap()
+}
+
+fn assert_route(root: &Root) {
+    // Verificar os dados do modelo
+    assert_eq!(root.code, "Ok");
+    assert_eq!(root.routes.len(), 1);
+    assert_eq!(root.waypoints.len(), 2);
+
+    // Verificar detalhes da rota
+    let route = &root.routes[0];
+    assert_eq!(route.weight_name, "routability");
+    assert_eq!(route.legs.len(), 1);
+
+    // Verificar detalhes da perna (leg)
+    let leg = &route.legs[0];
+    assert_eq!(leg.summary, "Main Street");
+    assert_eq!(leg.steps.len(), 1);
+
+    // Verificar detalhes do step
+    let step = &leg.steps[0];
+    assert_eq!(step.mode, "driving");
+    assert_eq!(step.name, "Main Street");
+    assert_eq!(step.reference.as_deref(), Some("A1"));
+    assert_eq!(step.intersections.len(), 1);
+    assert_eq!(step.intersections[0].in_value, Some(0));
+
+    // Verificar manobra
+    let maneuver = &step.maneuver;
+    assert_eq!(maneuver.bearing_after, 90);
+    assert_eq!(maneuver.maneuver_type, "depart");
+
+    // Verificar waypoints
+    assert_eq!(root.waypoints[0].name, "Start");
+    assert_eq!(root.waypoints[1].name, "End");
+}
+
 // Função auxiliar para comprimir dados e criar o JSON de teste para o modelo C#
 fn create_test_block(value: &Value, ext_type: i8) -> Vec<serde_json::Value> {
     // Serializar o valor para MessagePack
     let mut buffer = Vec::new();
     write_value(&mut buffer, value).unwrap();
-    
+
     // Comprimir os dados com LZ4
     let compressed_data = compress(&buffer, None, false).unwrap_or_default();
-    
-    // Criar o buffer de cabeçalho
+
+    // Criar o buffer de cabeçalho: o tamanho descomprimido como inteiro MessagePack
     let mut header_data = Vec::new();
-    header_data.push(204); // Tipo fixo
-    
-    // Codificar o tamanho descomprimido em big-endian
-    let size = buffer.len();
-    if size <= 0xFF {
-        header_data.push(size as u8);
-    } else if size <= 0xFFFF {
-        header_data.push((size >> 8) as u8);
-        header_data.push(size as u8);
-    } else if size <= 0xFFFFFF {
-        header_data.push((size >> 16) as u8);
-        header_data.push((size >> 8) as u8);
-        header_data.push(size as u8);
-    } else {
-        header_data.push((size >> 24) as u8);
-        header_data.push((size >> 16) as u8);
-        header_data.push((size >> 8) as u8);
-        header_data.push(size as u8);
-    }
-    
+    write_value(&mut header_data, &Value::from(buffer.len())).unwrap();
+
     // Criar o JSON com a estrutura LZ4BlockArray (par de blocos)
     vec![
         serde_json::json!({
@@ -167,313 +190,66 @@ fn generate_test_file(name: &str, blocks: &[serde_json::Value]) -> String {
     if !test_dir.exists() {
         fs::create_dir_all(test_dir).expect("Failed to create test directory");
     }
-    
+
     let file_path = test_dir.join(format!("{}.json", name));
     let json_content = serde_json::to_string_pretty(blocks).expect("Failed to serialize JSON");
-    
+
     fs::write(&file_path, json_content).expect("Failed to write test file");
     file_path.to_string_lossy().to_string()
 }
 
 #[test]
 fn test_csharp_route_model() {
-    // Criar dados de exemplo que correspondem ao modelo de rota C#
-    let route_data = Value::Map(vec![
-        // code
-        (Value::Integer(0.into()), Value::String("Ok".into())),
-        // routes
-        (Value::Integer(1.into()), Value::Array(vec![
-            Value::Map(vec![
-                // legs
-                (Value::Integer(0.into()), Value::Array(vec![
-                    Value::Map(vec![
-                        // steps
-                        (Value::Integer(0.into()), Value::Array(vec![
-                            Value::Map(vec![
-                                // geometry
-                                (Value::Integer(0.into()), Value::String("linestring".into())),
-                                // maneuver
-                                (Value::Integer(1.into()), Value::Map(vec![
-                                    (Value::Integer(0.into()), Value::Integer(90.into())),
-                                    (Value::Integer(1.into()), Value::Integer(0.into())),
-                                    (Value::Integer(2.into()), Value::Array(vec![
-                                        Value::F64(13.388798), Value::F64(52.517033)
-                                    ])),
-                                    (Value::Integer(3.into()), Value::String("turn right".into())),
-                                    (Value::Integer(4.into()), Value::String("depart".into())),
-                                ])),
-                                // mode
-                                (Value::Integer(2.into()), Value::String("driving".into())),
-                                // driving_side
-                                (Value::Integer(3.into()), Value::String("right".into())),
-                                // name
-                                (Value::Integer(4.into()), Value::String("Main Street".into())),
-                                // intersections
-                                (Value::Integer(5.into()), Value::Array(vec![
-                                    Value::Map(vec![
-                                        (Value::Integer(0.into()), Value::Integer(1.into())),
-                                        (Value::Integer(1.into()), Value::Array(vec![
-                                            Value::Boolean(true), Value::Boolean(false)
-                                        ])),
-                                        (Value::Integer(2.into()), Value::Array(vec![
-                                            Value::Integer(90.into()), Value::Integer(270.into())
-                                        ])),
-                                        (Value::Integer(3.into()), Value::Array(vec![
-                                            Value::F64(13.388798), Value::F64(52.517033)
-                                        ])),
-                                        (Value::Integer(4.into()), Value::Integer(0.into())),
-                                    ]),
-                                ])),
-                                // weight
-                                (Value::Integer(6.into()), Value::F64(10.5)),
-                                // duration
-                                (Value::Integer(7.into()), Value::F64(60.0)),
-                                // distance
-                                (Value::Integer(8.into()), Value::F64(500.0)),
-                                // ref
-                                (Value::Integer(9.into()), Value::String("A1".into())),
-                            ])
-                        ])),
-                        // summary
-                        (Value::Integer(1.into()), Value::String("Main Street".into())),
-                        // weight
-                        (Value::Integer(2.into()), Value::F64(10.5)),
-                        // duration
-                        (Value::Integer(3.into()), Value::F64(60.0)),
-                        // distance
-                        (Value::Integer(4.into()), Value::F64(500.0)),
-                    ])
-                ])),
-                // weight_name
-                (Value::Integer(1.into()), Value::String("routability".into())),
-                // weight
-                (Value::Integer(2.into()), Value::F64(10.5)),
-                // duration
-                (Value::Integer(3.into()), Value::F64(60.0)),
-                // distance
-                (Value::Integer(4.into()), Value::F64(500.0)),
-            ])
-        ])),
-        // waypoints
-        (Value::Integer(2.into()), Value::Array(vec![
-            Value::Map(vec![
-                (Value::Integer(0.into()), Value::String("hint1".into())),
-                (Value::Integer(1.into()), Value::F64(5.0)),
-                (Value::Integer(2.into()), Value::String("Start".into())),
-                (Value::Integer(3.into()), Value::Array(vec![
-                    Value::F64(13.388798), Value::F64(52.517033)
-                ])),
-            ]),
-            Value::Map(vec![
-                (Value::Integer(0.into()), Value::String("hint2".into())),
-                (Value::Integer(1.into()), Value::F64(5.0)),
-                (Value::Integer(2.into()), Value::String("End".into())),
-                (Value::Integer(3.into()), Value::Array(vec![
-                    Value::F64(13.397631), Value::F64(52.529432)
-                ])),
-            ]),
-        ])),
-    ]);
-
     // Criar o bloco LZ4 com os dados de rota
-    let blocks = create_test_block(&route_data, 98);
-    
+    let blocks = create_test_block(&route_data(), 98);
+
     // Gerar arquivo de teste
     let file_path = generate_test_file("route_example", &blocks);
-    
+
     // Processar o arquivo com formato JSON
-    let json_result = LZ4MessagePackProcessor::process(Some(&file_path), OutputFormat::Json);
-    assert!(json_result.is_ok(), "Failed to process route example with JSON output");
-    
-    // Inspecionar a estrutura real da saída
-    let json_content = json_result.unwrap();
-    println!("JSON Output: {}", json_content);
-    
-    // Tentar desserializar o JSON para o modelo Root
+    let json_content = LZ4MessagePackProcessor::process(Some(&file_path), OutputFormat::Json)
+        .expect("Failed to process route example with JSON output");
     let route_json: serde_json::Value = serde_json::from_str(&json_content).expect("Failed to parse JSON output");
-    
-    // Imprimir a estrutura para depuração
-    println!("JSON Structure Keys: {:?}", route_json.as_object().map(|o| o.keys().collect::<Vec<_>>()));
-    if let Some(blocks) = route_json.get("blocks") {
-        println!("Blocks structure: {:?}", blocks);
-        if let Some(block_array) = blocks.as_array() {
-            if !block_array.is_empty() {
-                println!("First Block Keys: {:?}", block_array[0].as_object().map(|o| o.keys().collect::<Vec<_>>()));
-                
-                // Se tiver um campo 'human_readable', verificar sua estrutura
-                if let Some(hr) = block_array[0].get("human_readable") {
-                    println!("Human Readable Type: {:?}", hr.is_object());
-                    
-                    // Aqui verificamos a estrutura real para adaptar nossos testes
-                    if hr.is_object() {
-                        let human_readable = hr;
-                        
-                        // Se a estrutura tem os campos indexados (0, 1, 2)
-                        if human_readable.get("0").is_some() {
-                            // Verificar código
-                            assert_eq!(human_readable["0"], "Ok", "Code should be 'Ok'");
-                            
-                            // Verificar rotas
-                            let routes = &human_readable["1"];
-                            assert!(routes.is_array(), "Routes should be an array");
-                            assert_eq!(routes.as_array().unwrap().len(), 1, "Should have 1 route");
-                            
-                            // Verificar waypoints
-                            let waypoints = &human_readable["2"];
-                            assert!(waypoints.is_array(), "Waypoints should be an array");
-                            assert_eq!(waypoints.as_array().unwrap().len(), 2, "Should have 2 waypoints");
-                            
-                            // Teste de desserialização para o modelo Rust
-                            let route_data_str = serde_json::to_string(&human_readable).expect("Failed to convert to string");
-                            let root: Result<Root, _> = serde_json::from_str(&route_data_str);
-                            
-                            assert!(root.is_ok(), "Failed to deserialize to Root model: {:?}", root.err());
-                            let root = root.unwrap();
-                            
-                            // Verificar os dados do modelo
-                            assert_eq!(root.code, "Ok");
-                            assert_eq!(root.routes.len(), 1);
-                            assert_eq!(root.waypoints.len(), 2);
-                            
-                            // Verificar detalhes da rota
-                            let route = &root.routes[0];
-                            assert_eq!(route.weight_name, "routability");
-                            assert_eq!(route.legs.len(), 1);
-                            
-                            // Verificar detalhes da perna (leg)
-                            let leg = &route.legs[0];
-                            assert_eq!(leg.summary, "Main Street");
-                            assert_eq!(leg.steps.len(), 1);
-                            
-                            // Verificar detalhes do step
-                            let step = &leg.steps[0];
-                            assert_eq!(step.mode, "driving");
-                            assert_eq!(step.name, "Main Street");
-                            assert_eq!(step.intersections.len(), 1);
-                            
-                            // Verificar manobra
-                            let maneuver = &step.maneuver;
-                            assert_eq!(maneuver.bearing_after, 90);
-                            assert_eq!(maneuver.maneuver_type, "depart");
-                            
-                            // Verificar waypoints
-                            assert_eq!(root.waypoints[0].name, "Start");
-                            assert_eq!(root.waypoints[1].name, "End");
-                        } else {
-                            // Se a estrutura é diferente, tentar adaptar para a estrutura real
-                            println!("Different structure found in human_readable: {:?}", human_readable);
-                            
-                            // Converter toda a estrutura para string para inspeção
-                            let json_string = serde_json::to_string_pretty(&human_readable)
-                                .expect("Failed to convert human_readable to string");
-                            println!("Human Readable Content: {}", json_string);
-                            
-                            // Testar se podemos desserializar diretamente para nosso modelo Root
-                            let root: Result<Root, _> = serde_json::from_value(human_readable.clone());
-                            if root.is_ok() {
-                                let root = root.unwrap();
-                                println!("Successfully parsed to Root model");
-                                assert!(true, "Parsed successfully with different structure");
-                            } else {
-                                println!("Could not parse to Root: {:?}", root.err());
-                                // Teste alternativo - apenas verificar se tem alguma estrutura válida
-                                assert!(human_readable.is_object(), "Human readable should be some valid structure");
-                            }
-                        }
-                    } else if hr.is_array() {
-                        // Se for um array, tentar desserializar diretamente
-                        println!("Human readable is an array with {} elements", hr.as_array().unwrap().len());
-                        
-                        // Tentativa de desserialização mais flexível
-                        let json_string = serde_json::to_string(&hr).expect("Failed to convert to string");
-                        println!("Human Readable JSON: {}", json_string);
-                        
-                        // Testar se há conteúdo válido sem falhar o teste
-                        assert!(true, "Found array data structure in human_readable");
-                    } else {
-                        println!("Human readable has unexpected type: {:?}", hr);
-                        // Mesmo com tipo inesperado, não falhar o teste neste ponto
-                        assert!(true, "Found data in human_readable field");
-                    }
-                } else {
-                    // Se não tem campo human_readable, verificar a estrutura do bloco diretamente
-                    println!("No human_readable field found, checking block structure directly");
-                    
-                    // Imprimir a primeira estrutura de bloco para inspeção
-                    let block_json = serde_json::to_string_pretty(&block_array[0])
-                        .expect("Failed to convert block to string");
-                    println!("First Block Content: {}", block_json);
-                    
-                    // Verificar se o bloco tem alguma estrutura válida
-                    assert!(block_array[0].is_object(), "Block should be an object with some valid structure");
-                }
-            } else {
-                println!("Block array is empty");
-                assert!(false, "Block array should not be empty");
-            }
-        } else {
-            println!("Blocks is not an array");
-            assert!(blocks.is_array(), "Blocks should be an array");
-        }
-    } else {
-        println!("No 'blocks' field found in JSON");
-        println!("Available fields: {:?}", route_json.as_object().map(|o| o.keys().collect::<Vec<_>>()));
-        
-        // Se a saída tem uma estrutura completamente diferente, tentar adaptar
-        // Verificar se podemos desserializar diretamente para Root
-        let root: Result<Root, _> = serde_json::from_value(route_json.clone());
-        if root.is_ok() {
-            println!("Successfully parsed entire JSON to Root model directly");
-            assert!(true, "Could parse JSON directly to Root model");
-        } else {
-            println!("Error parsing to Root: {:?}", root.err());
-            assert!(false, "JSON structure doesn't match expected format and can't be adapted");
-        }
-    }
-    
-    println!("Successfully validated C# route model!");
+
+    // Um único envelope: o resultado legível fica no nível superior, como array posicional
+    let human_readable = &route_json["human_readable"];
+    assert!(human_readable.is_array(), "Array-keyed model should stay an array: {}", human_readable);
+    assert_eq!(human_readable[0], "Ok");
+    assert_eq!(human_readable[2].as_array().unwrap().len(), 2, "Should have 2 waypoints");
+
+    // O array posicional desserializa direto no modelo Rust
+    let root: Root = serde_json::from_value(human_readable.clone()).expect("Failed to deserialize to Root model");
+    assert_route(&root);
 }
 
 #[test]
 fn test_parse_messagepack_directly() {
-    // Criar dados de exemplo
-    let route_data = Value::Map(vec![
-        (Value::Integer(0.into()), Value::String("Ok".into())),
-        (Value::Integer(1.into()), Value::Array(vec![
-            // Simplified route for brevity
-            Value::Map(vec![
-                (Value::Integer(0.into()), Value::Array(vec![])), // empty legs
-                (Value::Integer(1.into()), Value::String("routability".into())),
-                (Value::Integer(2.into()), Value::F64(10.5)),
-                (Value::Integer(3.into()), Value::F64(60.0)),
-                (Value::Integer(4.into()), Value::F64(500.0)),
-            ])
-        ])),
-        (Value::Integer(2.into()), Value::Array(vec![])), // empty waypoints
-    ]);
-
-    // Serializar para MessagePack
+    // Desserializar o MessagePack direto, sem passar por JSON
     let mut buffer = Vec::new();
-    write_value(&mut buffer, &route_data).unwrap();
-    
-    // Tentar desserializar o MessagePack diretamente
-    let unpacked: Result<rmpv::Value, _> = rmpv::decode::read_value(&mut buffer.as_slice());
-    assert!(unpacked.is_ok(), "Failed to decode MessagePack: {:?}", unpacked.err());
-    
-    // Converter para JSON utilizando serde_json diretamente
-    let unpacked_value = unpacked.unwrap();
-    let root_json_str = serde_json::to_string(&unpacked_value).expect("Failed to convert to JSON string");
-    
-    // Desserializar o JSON para nossa estrutura Root
-    let root: Result<Root, _> = serde_json::from_str(&root_json_str);
-    
-    assert!(root.is_ok(), "Failed to deserialize to Root model: {:?}", root.err());
-    let root = root.unwrap();
-    
-    // Verificar os dados básicos
-    assert_eq!(root.code, "Ok");
-    assert_eq!(root.routes.len(), 1);
-    assert_eq!(root.waypoints.len(), 0);
-    
-    println!("Successfully parsed MessagePack directly!");
-} 
\ No newline at end of file
+    write_value(&mut buffer, &route_data()).unwrap();
+    let root: Root = from_msgpack(&buffer).expect("Failed to deserialize to Root model");
+    assert_route(&root);
+
+    // E a partir do envelope comprimido
+    let root: Root = from_slice_lz4(&encode_value(&route_data()).unwrap()).unwrap();
+    assert_route(&root);
+
+    // Serializar de volta produz os mesmos bytes que o C# grava
+    assert_eq!(to_msgpack(&root, StructEncoding::Array).unwrap(), buffer);
+}
+
+#[test]
+fn test_map_encoding_is_still_available() {
+    let mut buffer = Vec::new();
+    write_value(&mut buffer, &route_data()).unwrap();
+    let root: Root = from_msgpack(&buffer).unwrap();
+
+    // Mapas com o nome dos campos, como [Key("name")] no C#
+    let named = to_msgpack(&root, StructEncoding::Map).unwrap();
+    let value = rmpv::decode::read_value(&mut named.as_slice()).unwrap();
+    assert_eq!(value["code"].as_str(), Some("Ok"));
+    assert_eq!(from_msgpack::<Root>(&named).unwrap(), root);
+
+    let envelope = to_vec_lz4_with(&root, Compression::Lz4BlockArray, StructEncoding::Map).unwrap();
+    assert_eq!(from_slice_lz4::<Root>(&envelope).unwrap(), root);
+}
@@ -0,0 +1,88 @@
+use lz4_messagepack::{
+    decode, decode_into, decode_into_slice, decoded_len, encode_with, to_vec_lz4, Compression, Envelope, Error,
+};
+
+mod common;
+use common::rows;
+
+#[test]
+fn test_decoded_len_comes_from_the_header() {
+    for compression in [Compression::Lz4BlockArray, Compression::Lz4Block] {
+        let envelope = to_vec_lz4(&rows(20_000), compression).unwrap();
+        assert_eq!(decoded_len(&envelope).unwrap(), decode(&envelope).unwrap().len());
+    }
+
+    // O tamanho é lido sem descomprimir: um bloco corrompido não impede a consulta
+    let mut envelope = encode_with(&[0xc0; 100], Compression::Lz4BlockArray).unwrap();
+    let start = envelope.len() - Envelope::parse_msgpack(&envelope).unwrap().blocks()[0].len();
+    envelope[start..start + 4].fill(0xff);
+    assert_eq!(decoded_len(&envelope).unwrap(), 100);
+    assert!(decode(&envelope).is_err());
+}
+
+#[test]
+fn test_decode_into_reuses_the_buffer() {
+    let large = to_vec_lz4(&rows(20_000), Compression::Lz4BlockArray).unwrap();
+    let small = to_vec_lz4(&rows(10), Compression::Lz4Block).unwrap();
+
+    let mut output = Vec::new();
+    decode_into(&large, &mut output).unwrap();
+    assert_eq!(output, decode(&large).unwrap());
+    let capacity = output.capacity();
+    let pointer = output.as_ptr();
+
+    // O conteúdo anterior é substituído e a alocação é mantida
+    decode_into(&small, &mut output).unwrap();
+    assert_eq!(output, decode(&small).unwrap());
+    decode_into(&large, &mut output).unwrap();
+    assert_eq!((output.capacity(), output.as_ptr()), (capacity, pointer));
+
+    // Em caso de erro o buffer fica vazio
+    assert!(matches!(decode_into(&[0xc7, 0x01, 0x05, 0x00], &mut output), Err(Error::UnsupportedExtension(5))));
+    assert!(output.is_empty());
+}
+
+#[test]
+fn test_decode_into_slice() {
+    let envelope = to_vec_lz4(&rows(5_000), Compression::Lz4BlockArray).unwrap();
+    let expected = decode(&envelope).unwrap();
+
+    let mut buffer = vec![0xaa; expected.len() + 4];
+    assert_eq!(decode_into_slice(&envelope, &mut buffer).unwrap(), expected.len());
+    assert_eq!(&buffer[..expected.len()], expected.as_slice());
+    assert_eq!(buffer[expected.len()..], [0xaa; 4]);
+
+    // Buffer pequeno demais: o erro informa o tamanho exato e nada é escrito
+    let mut buffer = vec![0xaa; expected.len() - 1];
+    match decode_into_slice(&envelope, &mut buffer) {
+        Err(error @ Error::OutputTooSmall { .. }) => {
+            assert_eq!(error.code(), 12);
+            assert!(matches!(error, Error::OutputTooSmall { required, available } if required == expected.len() && available == buffer.len()));
+        },
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    assert!(buffer.iter().all(|&b| b == 0xaa));
+}
+
+#[test]
+fn test_borrowed_parsing_errors() {
+    let envelope = encode_with(&[0xc0; 100_000], Compression::Lz4BlockArray).unwrap();
+    let mut buffer = vec![0; 100_000];
+
+    let mut trailing = envelope.clone();
+    trailing.push(0xc0);
+    assert!(matches!(decode_into_slice(&trailing, &mut buffer), Err(Error::TrailingData { .. })));
+    assert!(matches!(decode_into_slice(&envelope[..envelope.len() - 1], &mut buffer), Err(Error::InvalidMessagePack { .. })));
+
+    let mut mismatch = envelope.clone();
+    mismatch[0] -= 1;
+    assert!(matches!(decode_into_slice(&mismatch[..], &mut buffer), Err(Error::TrailingData { .. })));
+
+    // Elementos fora do formato [ext, bin, ...]
+    assert!(matches!(decoded_len(&[0x91, 0xc4, 0x00]), Err(Error::InvalidEnvelope { index: Some(0), .. })));
+    assert!(matches!(decoded_len(&[0x92, 0xd4, 98, 0x01, 0xc0]), Err(Error::InvalidEnvelope { index: Some(1), .. })));
+    assert!(matches!(decoded_len(&[0x92, 0xd4, 98, 0x01, 0xd4, 98, 0x01]), Err(Error::InvalidEnvelope { index: Some(1), .. })));
+    assert!(matches!(decoded_len(&[0x90]), Err(Error::InvalidEnvelope { index: None, .. })));
+    assert!(matches!(decoded_len(&[0x92, 0xd4, 98, 0x02, 0xc4, 0x00]), Ok(2)));
+    assert!(matches!(decoded_len(&[0x93, 0xd4, 98, 0x02, 0xc4, 0x00, 0xc4, 0x00]), Err(Error::BlockCountMismatch { declared: 1, found: 2 })));
+}
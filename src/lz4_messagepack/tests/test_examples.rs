@@ -6,10 +6,7 @@ use rmpv::Value;
 use rmpv::encode::write_value;
 
 // Import o código da aplicação principal
-#[path = "../src/main.rs"]
-mod app;
-use app::LZ4MessagePackProcessor;
-use app::OutputFormat;
+use lz4_messagepack::{LZ4MessagePackProcessor, OutputFormat};
 
 // Função auxiliar para comprimir dados e criar o JSON de teste
 fn create_test_data(value: &Value) -> JsonValue {
@@ -20,26 +17,13 @@ fn create_test_data(value: &Value) -> JsonValue {
     // Comprimir os dados com LZ4
     let compressed_data = compress(&buffer, None, false).unwrap_or_default();
     
-    // Criar o buffer de cabeçalho
+    // Criar o buffer de cabeçalho: o tamanho descomprimido como inteiro MessagePack,
+    // com o marcador uint8 (204) quando cabe em um byte, como o MessagePack-CSharp grava
     let mut header_data = Vec::new();
-    header_data.push(204); // Tipo fixo
-    
-    // Codificar o tamanho descomprimido em big-endian
-    let size = buffer.len();
-    if size <= 0xFF {
-        header_data.push(size as u8);
-    } else if size <= 0xFFFF {
-        header_data.push((size >> 8) as u8);
-        header_data.push(size as u8);
-    } else if size <= 0xFFFFFF {
-        header_data.push((size >> 16) as u8);
-        header_data.push((size >> 8) as u8);
-        header_data.push(size as u8);
+    if buffer.len() <= 0xFF {
+        header_data.extend([204, buffer.len() as u8]);
     } else {
-        header_data.push((size >> 24) as u8);
-        header_data.push((size >> 16) as u8);
-        header_data.push((size >> 8) as u8);
-        header_data.push(size as u8);
+        write_value(&mut header_data, &Value::from(buffer.len())).unwrap();
     }
     
     // Criar o JSON com a estrutura LZ4BlockArray
@@ -158,7 +142,7 @@ fn test_large_numbers() {
     let value = Value::Array(vec![
         Value::Integer(1234567890123456789i64.into()),
         Value::Integer((-987654321098765432i64).into()),
-        Value::F64(3.141592653589793),
+        Value::F64(std::f64::consts::PI),
         Value::F64(-0.000000000000001)
     ]);
     
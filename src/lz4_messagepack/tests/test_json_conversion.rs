@@ -0,0 +1,105 @@
+use lz4_messagepack::{
+    decode_to_json, encode_value, json_to_value_with, value_to_json, value_to_json_with, BigIntFormat,
+    BinaryFormat, JsonOptions, LZ4MessagePackProcessor, MsgpackOptions, OutputFormat,
+};
+use rmpv::Value;
+use rmpv::decode::read_value;
+use serde_json::{json, Value as JsonValue};
+
+fn options(binary: BinaryFormat, big_integers: BigIntFormat) -> JsonOptions {
+    JsonOptions { binary, big_integers, ..Default::default() }
+}
+
+#[test]
+fn test_non_string_keys_are_stringified() {
+    // Chaves inteiras são comuns em modelos do C# com [Key(n)]
+    let value = Value::Map(vec![
+        (Value::from(0), Value::from("zero")),
+        (Value::from(-1), Value::from("negativo")),
+        (Value::Boolean(true), Value::from("verdadeiro")),
+        (Value::Nil, Value::from("nulo")),
+        (Value::from("texto"), Value::from(1)),
+    ]);
+
+    assert_eq!(value_to_json(&value), json!({
+        "0": "zero",
+        "-1": "negativo",
+        "true": "verdadeiro",
+        "null": "nulo",
+        "texto": 1
+    }));
+}
+
+#[test]
+fn test_binary_formats() {
+    let value = Value::Binary(vec![0x00, 0xff, 0x10]);
+
+    assert_eq!(value_to_json(&value), json!("AP8Q"));
+    assert_eq!(value_to_json_with(&value, &options(BinaryFormat::Hex, BigIntFormat::Number)), json!("00ff10"));
+    assert_eq!(value_to_json_with(&value, &options(BinaryFormat::Array, BigIntFormat::Number)), json!([0, 255, 16]));
+}
+
+#[test]
+fn test_ext_is_tagged() {
+    let value = Value::Ext(5, vec![1, 2, 3]);
+
+    assert_eq!(value_to_json(&value), json!({ "$ext": 5, "data": "AQID" }));
+    assert_eq!(
+        value_to_json_with(&value, &options(BinaryFormat::Array, BigIntFormat::Number)),
+        json!({ "$ext": 5, "data": [1, 2, 3] })
+    );
+}
+
+#[test]
+fn test_big_integers_are_preserved() {
+    // u64 acima de i64::MAX não pode virar null
+    let value = Value::Array(vec![Value::from(u64::MAX), Value::from(i64::MIN), Value::from(42)]);
+
+    let json = value_to_json(&value);
+    assert_eq!(json[0].as_u64(), Some(u64::MAX));
+    assert_eq!(json[1].as_i64(), Some(i64::MIN));
+
+    let json = value_to_json_with(&value, &options(BinaryFormat::Base64, BigIntFormat::String));
+    assert_eq!(json, json!([u64::MAX.to_string(), i64::MIN.to_string(), 42]));
+}
+
+#[test]
+fn test_invalid_utf8_strings_are_kept() {
+    let value = read_value(&mut &[0xa3, b'a', 0xff, b'b'][..]).unwrap();
+    assert_eq!(value_to_json(&value), json!("a\u{fffd}b"));
+}
+
+#[test]
+fn test_decode_to_json_and_processor_use_options() {
+    let value = Value::Map(vec![
+        (Value::from(1), Value::Binary(vec![0xca, 0xfe])),
+        (Value::from(2), Value::from(u64::MAX)),
+    ]);
+    let envelope = encode_value(&value).unwrap();
+    let hex_strings = options(BinaryFormat::Hex, BigIntFormat::String);
+
+    let json = decode_to_json(&envelope, &hex_strings).unwrap();
+    assert_eq!(json, json!({ "1": "cafe", "2": u64::MAX.to_string() }));
+
+    let output = LZ4MessagePackProcessor::process_bytes_with(&envelope, OutputFormat::Human, &hex_strings).unwrap();
+    let output: JsonValue = serde_json::from_str(&output).unwrap();
+    assert_eq!(output, json);
+}
+
+#[test]
+fn test_tagged_json_to_messagepack() {
+    // Extensões geradas com base64 ou array voltam para ext com tagged_values
+    let ext = Value::Ext(-3, vec![1, 2, 3]);
+    let msgpack_options = MsgpackOptions { prefer_f32: true, tagged_values: true, ..Default::default() };
+
+    for binary in [BinaryFormat::Base64, BinaryFormat::Array] {
+        let json = value_to_json_with(&ext, &options(binary, BigIntFormat::Number));
+        assert_eq!(json_to_value_with(&json, &msgpack_options).unwrap(), ext);
+    }
+
+    let json = json!({ "bin": { "$bin": "yv4=" }, "float": 0.5 });
+    assert_eq!(json_to_value_with(&json, &msgpack_options).unwrap(), Value::Map(vec![
+        (Value::from("bin"), Value::Binary(vec![0xca, 0xfe])),
+        (Value::from("float"), Value::F32(0.5)),
+    ]));
+}
@@ -0,0 +1,186 @@
+use lz4_messagepack::{
+    decode, decode_any, decode_any_to_json, decode_to_value, encode, encode_value, encode_with, value_to_json,
+    Compression, Envelope, Error, JsonOptions, LZ4MessagePackProcessor, OutputFormat,
+};
+use rmpv::Value;
+use rmpv::encode::write_value;
+use serde_json::Value as JsonValue;
+
+fn sample_value() -> Value {
+    Value::Map(vec![
+        (Value::from("code"), Value::from("Ok")),
+        (Value::from("items"), Value::Array((0..10).map(Value::from).collect())),
+    ])
+}
+
+fn to_msgpack(value: &Value) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    write_value(&mut buffer, value).unwrap();
+    buffer
+}
+
+#[test]
+fn test_encode_decode_round_trip() {
+    // O envelope gerado deve voltar exatamente ao MessagePack original
+    let msgpack = to_msgpack(&sample_value());
+    let envelope = encode(&msgpack).unwrap();
+
+    assert_eq!(decode(&envelope).unwrap(), msgpack);
+    assert_eq!(decode_to_value(&envelope).unwrap(), sample_value());
+    assert_eq!(decode_to_value(&encode_value(&sample_value()).unwrap()).unwrap(), sample_value());
+}
+
+#[test]
+fn test_encode_uses_csharp_layout() {
+    // Mesmo layout do MessagePack-CSharp: [fixext1(98, length), bin32]
+    let msgpack = to_msgpack(&sample_value());
+    let envelope = encode(&msgpack).unwrap();
+
+    assert_eq!(envelope[0], 0x92, "Envelope should be a fixarray of two elements");
+    assert_eq!(&envelope[1..3], &[0xd4, 98], "Header should be a fixext1 of type 98");
+    assert_eq!(envelope[3] as usize, msgpack.len(), "Header should hold the length as a fixint");
+    assert_eq!(envelope[4], 0xc6, "Blocks should be written as bin32");
+}
+
+#[test]
+fn test_large_payload_spans_several_blocks() {
+    // Payloads maiores que 32 KiB são divididos em vários blocos LZ4
+    let value = Value::Array((0..20_000).map(|i| Value::from(format!("item-{}", i))).collect());
+    let msgpack = to_msgpack(&value);
+    let envelope = encode(&msgpack).unwrap();
+
+    let parsed = Envelope::parse_msgpack(&envelope).unwrap();
+    assert!(parsed.blocks().len() > 1, "Expected several blocks");
+    assert_eq!(parsed.block_lengths().unwrap().iter().sum::<usize>(), msgpack.len());
+    assert_eq!(decode(&envelope).unwrap(), msgpack);
+}
+
+#[test]
+fn test_lz4_block_round_trip() {
+    // Lz4Block: ext32 de tipo 99 com o tamanho em int32
+    let msgpack = to_msgpack(&sample_value());
+    let envelope = encode_with(&msgpack, Compression::Lz4Block).unwrap();
+
+    assert_eq!(envelope[0], 0xc9, "Lz4Block should be an ext32");
+    assert_eq!(envelope[5], 99);
+    assert_eq!(envelope[6], 0xd2, "Length should be an int32");
+    assert_eq!(decode(&envelope).unwrap(), msgpack);
+}
+
+#[test]
+fn test_decode_default_input() {
+    // O exemplo padrão em JSON deve decodificar para a mesma resposta em binário
+    let json: JsonValue = serde_json::from_str(include_str!("../default_input.json")).unwrap();
+    let envelopes = Envelope::parse_json(&json).unwrap();
+    assert_eq!(envelopes.len(), 1);
+
+    let binary = envelopes[0].to_msgpack().unwrap();
+    assert_eq!(decode(&binary).unwrap(), envelopes[0].decompress().unwrap());
+    assert_eq!(Envelope::parse_msgpack(&binary).unwrap(), envelopes[0]);
+}
+
+#[test]
+fn test_decode_rejects_invalid_envelopes() {
+    let msgpack = to_msgpack(&sample_value());
+    let mut envelope = encode(&msgpack).unwrap();
+
+    // MessagePack sem envelope
+    let error = decode(&msgpack).unwrap_err();
+    assert!(matches!(error, Error::InvalidEnvelope { index: None, .. }), "Unexpected error: {}", error);
+
+    // Bytes extras após o envelope
+    let length = envelope.len() as u64;
+    envelope.push(0xc0);
+    let error = decode(&envelope).unwrap_err();
+    assert!(matches!(error, Error::TrailingData { offset } if offset == length), "Unexpected error: {}", error);
+}
+
+#[test]
+fn test_errors_carry_offsets_and_codes() {
+    // Cabeçalho truncado: o segundo tamanho começa no byte 1 e não termina
+    let envelope = [0x93, 0xd5, 98, 0x05, 0xcd, 0xc6, 0, 0, 0, 0, 0xc6, 0, 0, 0, 0];
+    let error = decode(&envelope).unwrap_err();
+    assert!(matches!(error, Error::InvalidHeader { offset: 1, .. }), "Unexpected error: {}", error);
+    assert_eq!(error.code(), 3);
+
+    // Bloco corrompido
+    let envelope = [0x92, 0xd4, 98, 0x05, 0xc6, 0, 0, 0, 1, 0xff];
+    let error = decode(&envelope).unwrap_err();
+    assert!(matches!(error, Error::CorruptBlock { block: 0, .. }), "Unexpected error: {}", error);
+    assert_eq!(error.code(), 6);
+
+    // Envelope truncado
+    let envelope = encode(&to_msgpack(&sample_value())).unwrap();
+    let error = decode(&envelope[..envelope.len() - 1]).unwrap_err();
+    assert!(matches!(error, Error::InvalidMessagePack { .. }), "Unexpected error: {}", error);
+    assert_eq!(error.code(), 9);
+
+    assert!(matches!(Compression::try_from(3), Err(Error::UnsupportedCompression(3))));
+}
+
+#[test]
+fn test_detect_binary_envelopes() {
+    let msgpack = to_msgpack(&sample_value());
+
+    assert!(Envelope::detect(&encode(&msgpack).unwrap()));
+    assert!(Envelope::detect(&encode_with(&msgpack, Compression::Lz4Block).unwrap()));
+    assert!(!Envelope::detect(&msgpack));
+    assert!(!Envelope::detect(include_bytes!("../response2")));
+    assert!(!Envelope::detect(&[]));
+}
+
+#[test]
+fn test_decode_any_passes_uncompressed_messagepack_through() {
+    // O MessagePack-CSharp não comprime payloads pequenos
+    let response = include_bytes!("../response2");
+    assert_eq!(decode_any(response).unwrap(), response.to_vec());
+
+    let msgpack = to_msgpack(&sample_value());
+    assert_eq!(decode_any(&encode(&msgpack).unwrap()).unwrap(), msgpack);
+
+    let error = decode_any(&response[..response.len() - 1]).unwrap_err();
+    assert!(matches!(error, Error::InvalidMessagePack { .. }), "Unexpected error: {}", error);
+}
+
+#[test]
+fn test_decode_any_to_json_accepts_every_input_form() {
+    let msgpack = to_msgpack(&sample_value());
+    let expected = value_to_json(&sample_value());
+    let options = JsonOptions::default();
+
+    // Envelope binário, JSON no formato do Node e MessagePack sem compressão
+    let envelope = encode(&msgpack).unwrap();
+    let node_json = Envelope::parse_msgpack(&envelope).unwrap().to_json().to_string();
+    assert_eq!(decode_any_to_json(&envelope, &options).unwrap(), expected);
+    assert_eq!(decode_any_to_json(node_json.as_bytes(), &options).unwrap(), expected);
+    assert_eq!(decode_any_to_json(&msgpack, &options).unwrap(), expected);
+
+    // Vários envelopes no JSON viram um array
+    let both = format!("[{}, {}]", &node_json[1..node_json.len() - 1], &node_json[1..node_json.len() - 1]);
+    assert_eq!(decode_any_to_json(both.as_bytes(), &options).unwrap(), serde_json::json!([expected, expected]));
+
+    let error = decode_any_to_json(b"[1, 2]", &options).unwrap_err();
+    assert!(matches!(error, Error::InvalidEnvelope { .. }), "Unexpected error: {}", error);
+}
+
+#[test]
+fn test_processor_reads_binary_input() {
+    // Envelope binário gravado em arquivo, como capturado da rede
+    let msgpack = to_msgpack(&sample_value());
+    let file_path = "tests/data/binary_envelope.msgpack";
+    std::fs::create_dir_all("tests/data").unwrap();
+    std::fs::write(file_path, encode(&msgpack).unwrap()).unwrap();
+
+    let output = LZ4MessagePackProcessor::process(Some(file_path), OutputFormat::Human).unwrap();
+    let output: JsonValue = serde_json::from_str(&output).unwrap();
+    assert_eq!(output["code"], "Ok");
+    assert_eq!(output["items"][9], 9);
+
+    // MessagePack sem compressão
+    let output = LZ4MessagePackProcessor::process(Some("response2"), OutputFormat::Human).unwrap();
+    let output: JsonValue = serde_json::from_str(&output).unwrap();
+    assert_eq!(output[0], "Ok");
+
+    let output = LZ4MessagePackProcessor::process_bytes(include_bytes!("../response2"), OutputFormat::Human).unwrap();
+    assert!(output.contains("xcvnC"), "Unexpected output: {}", output);
+}
@@ -0,0 +1,187 @@
+use std::io::Cursor;
+
+use lz4_messagepack::{
+    decode, decode_any_to_json, decode_into_with_limits, decode_to_json, decoded_len, encode, to_vec_lz4, Compression,
+    Envelope, Error, JsonOptions, LZ4MessagePackProcessor, Limit, Limits, Lz4BlockArrayReader, OutputFormat,
+};
+use rmpv::encode::write_value;
+use rmpv::Value;
+
+/// Envelope Lz4BlockArray cujo cabeçalho declara os tamanhos indicados, com blocos de poucos bytes
+fn declared_envelope(lengths: &[i32]) -> Vec<u8> {
+    // O cabeçalho usa int32 (0xd2), como o MessagePack-CSharp
+    let header = lengths.iter().flat_map(|length| [&[0xd2][..], &length.to_be_bytes()].concat()).collect();
+    let mut elements = vec![Value::Ext(98, header)];
+    elements.extend(lengths.iter().map(|_| Value::Binary(vec![0x10, 0xc0])));
+    let mut output = Vec::new();
+    write_value(&mut output, &Value::Array(elements)).unwrap();
+    output
+}
+
+/// `depth` arrays de um elemento aninhados em volta de um nil
+fn nested_arrays(depth: usize) -> Vec<u8> {
+    let mut msgpack = vec![0x91; depth];
+    msgpack.push(0xc0);
+    msgpack
+}
+
+/// `depth` extensões Typeless (ext 100) aninhadas, cada uma com o corpo na seguinte, em volta de um nil
+///
+/// Os bytes são gravados de fora para dentro, sem recursão, para não estourar a pilha do próprio teste.
+fn nested_typeless(depth: usize) -> Vec<u8> {
+    // ext 32 (5 bytes), o tipo 100 e o nome "T, a" como fixstr
+    const LEVEL: usize = 5 + 1 + 5;
+    let mut msgpack = Vec::with_capacity(depth * LEVEL + 1);
+    for level in 0..depth {
+        let payload_len = (depth - level) * LEVEL + 1 - 6;
+        msgpack.push(0xc9);
+        msgpack.extend((payload_len as u32).to_be_bytes());
+        msgpack.extend([100, 0xa4, b'T', b',', b' ', b'a']);
+    }
+    msgpack.push(0xc0);
+    msgpack
+}
+
+fn assert_limit(result: Result<impl std::fmt::Debug, Error>, expected: Limit) {
+    match result {
+        Err(Error::LimitExceeded { limit, .. }) if limit == expected => {},
+        other => panic!("Expected the {} limit to be exceeded, got {:?}", expected, other),
+    }
+}
+
+#[test]
+fn test_declared_block_length_is_rejected_before_allocating() {
+    // Poucos bytes declarando 2 GiB: falha de imediato, sem alocar a saída
+    let bomb = declared_envelope(&[i32::MAX]);
+    let error = decode(&bomb).unwrap_err();
+    assert!(
+        matches!(error, Error::LimitExceeded { limit: Limit::BlockLen, actual, max } if actual == i32::MAX as usize && max == 16 * 1024 * 1024),
+        "Unexpected error: {}",
+        error
+    );
+    assert_eq!(error.code(), 13);
+    assert_limit(decoded_len(&bomb), Limit::BlockLen);
+    assert_limit(Envelope::parse_msgpack(&bomb).unwrap().decompress(), Limit::BlockLen);
+    assert_limit(decode_to_json(&bomb, &JsonOptions::default()), Limit::BlockLen);
+    assert_limit(Lz4BlockArrayReader::new(Cursor::new(&bomb)), Limit::BlockLen);
+
+    // O mesmo vale para o tamanho que prefixa o bloco de um Lz4Block (ext 99)
+    let mut payload = vec![0xd2];
+    payload.extend(i32::MAX.to_be_bytes());
+    payload.extend([0x10, 0xc0]);
+    let mut lz4_block = Vec::new();
+    write_value(&mut lz4_block, &Value::Ext(99, payload)).unwrap();
+    assert_limit(decode(&lz4_block), Limit::BlockLen);
+    assert_limit(Lz4BlockArrayReader::new(Cursor::new(&lz4_block)), Limit::BlockLen);
+}
+
+#[test]
+fn test_total_length_and_block_count() {
+    // Cada bloco respeita o limite, mas o total declarado passa de 256 MiB
+    let bomb = declared_envelope(&[16 * 1024 * 1024; 20]);
+    let error = decode(&bomb).unwrap_err();
+    assert!(
+        matches!(error, Error::LimitExceeded { limit: Limit::UncompressedLen, actual, .. } if actual == 320 * 1024 * 1024),
+        "Unexpected error: {}",
+        error
+    );
+    assert_limit(Lz4BlockArrayReader::new(Cursor::new(&bomb)), Limit::UncompressedLen);
+
+    let rows: Vec<(u32, String)> = (0..50_000).map(|id| (id, format!("linha {}", id))).collect();
+    let envelope = to_vec_lz4(&rows, Compression::Lz4BlockArray).unwrap();
+    let blocks = Envelope::parse_msgpack(&envelope).unwrap().blocks().len();
+    let limits = Limits { max_blocks: blocks - 1, ..Limits::default() };
+    let mut output = Vec::new();
+    assert_limit(decode_into_with_limits(&envelope, &mut output, &limits), Limit::BlockCount);
+    assert!(output.is_empty());
+    assert_limit(Lz4BlockArrayReader::with_limits(Cursor::new(&envelope), &limits), Limit::BlockCount);
+
+    // No limite exato a decodificação passa
+    let limits = Limits { max_blocks: blocks, ..Limits::default() };
+    decode_into_with_limits(&envelope, &mut output, &limits).unwrap();
+    assert_eq!(output, decode(&envelope).unwrap());
+}
+
+#[test]
+fn test_nesting_depth() {
+    // 128 níveis, como no serde_json, são aceitos; 129 não
+    let options = JsonOptions::default();
+    assert!(decode_to_json(&encode(&nested_arrays(128)).unwrap(), &options).is_ok());
+    let error = decode_to_json(&encode(&nested_arrays(129)).unwrap(), &options).unwrap_err();
+    assert!(
+        matches!(error, Error::LimitExceeded { limit: Limit::Depth, actual: 129, max: 128 }),
+        "Unexpected error: {}",
+        error
+    );
+
+    // Vale também para MessagePack sem compressão e para mapas
+    assert_limit(decode_any_to_json(&nested_arrays(129), &options), Limit::Depth);
+    let mut maps = [0x81, 0xa1, b'k'].repeat(129);
+    maps.push(0xc0);
+    assert_limit(decode_any_to_json(&maps, &options), Limit::Depth);
+
+    let options = JsonOptions { limits: Limits { max_depth: 3, ..Limits::default() }, ..Default::default() };
+    assert!(decode_any_to_json(&nested_arrays(3), &options).is_ok());
+    assert_limit(decode_any_to_json(&nested_arrays(4), &options), Limit::Depth);
+}
+
+#[test]
+fn test_typeless_nesting_depth() {
+    // Cada ext 100 é convertida lendo o corpo que ela guarda, então conta como um nível
+    let options = JsonOptions::default();
+    let json = decode_any_to_json(&nested_typeless(100), &options).unwrap();
+    assert_eq!(json["$type"], "T, a");
+    assert_limit(decode_any_to_json(&nested_typeless(129), &options), Limit::Depth);
+
+    // Cerca de 1 MB com 100 mil níveis falha com o erro do limite em vez de estourar a pilha
+    let deep = nested_typeless(100_000);
+    assert_limit(decode_any_to_json(&deep, &options), Limit::Depth);
+    assert_limit(decode_to_json(&encode(&deep).unwrap(), &options), Limit::Depth);
+
+    // Também na ext 100 solta da forma JSON do Node
+    let payload = nested_typeless(1_000)[5 + 1..].to_vec();
+    let node_json = serde_json::json!({ "type": 100, "buffer": { "type": "Buffer", "data": payload } });
+    assert_limit(decode_any_to_json(node_json.to_string().as_bytes(), &options), Limit::Depth);
+}
+
+#[test]
+fn test_collection_length() {
+    // array32 e map32 declarando quatro bilhões de elementos em cinco bytes
+    let options = JsonOptions::default();
+    for marker in [0xdd, 0xdf] {
+        let error = decode_any_to_json(&[marker, 0xff, 0xff, 0xff, 0xff], &options).unwrap_err();
+        assert!(
+            matches!(error, Error::LimitExceeded { limit: Limit::CollectionLen, actual: 0xffff_ffff, .. }),
+            "Unexpected error: {}",
+            error
+        );
+    }
+
+    let options = JsonOptions { limits: Limits { max_collection_len: 2, ..Limits::default() }, ..Default::default() };
+    assert!(decode_any_to_json(&[0x92, 0x01, 0x02], &options).is_ok());
+    assert_limit(decode_any_to_json(&[0x93, 0x01, 0x02, 0x03], &options), Limit::CollectionLen);
+    // Elementos dentro de strings e binários não contam
+    assert!(decode_any_to_json(&[0x91, 0xc4, 0x03, 0x93, 0x93, 0x93], &options).is_ok());
+
+    // Dados malformados continuam gerando o erro do parser
+    let error = decode_any_to_json(&[0x92, 0x01], &JsonOptions::default()).unwrap_err();
+    assert!(matches!(error, Error::InvalidMessagePack { .. }), "Unexpected error: {}", error);
+}
+
+#[test]
+fn test_unlimited_and_processor() {
+    let deep = encode(&nested_arrays(200)).unwrap();
+    let unlimited = JsonOptions { limits: Limits::unlimited(), ..Default::default() };
+    assert!(decode_to_json(&deep, &unlimited).is_ok());
+    assert_eq!(
+        Envelope::parse_msgpack(&deep).unwrap().decompress_with_limits(&Limits::unlimited()).unwrap(),
+        nested_arrays(200)
+    );
+
+    // O processador da CLI aplica os limites das opções
+    let error = LZ4MessagePackProcessor::process_bytes(&deep, OutputFormat::Human).unwrap_err();
+    assert_eq!(error.code(), 13);
+    assert_limit(LZ4MessagePackProcessor::process_bytes(&nested_arrays(200), OutputFormat::Human), Limit::Depth);
+    assert!(LZ4MessagePackProcessor::process_bytes_with(&deep, OutputFormat::Human, &unlimited).is_ok());
+    assert!(LZ4MessagePackProcessor::process_bytes_with(&nested_arrays(200), OutputFormat::Human, &unlimited).is_ok());
+}
@@ -6,10 +6,7 @@ use rmpv::Value;
 use rmpv::encode::write_value;
 
 // Import o código da aplicação principal
-#[path = "../src/main.rs"]
-mod app;
-use app::LZ4MessagePackProcessor;
-use app::OutputFormat;
+use lz4_messagepack::{Error, LZ4MessagePackProcessor, OutputFormat};
 
 // Função auxiliar para comprimir dados e criar o JSON de teste
 fn create_test_block(value: &Value, ext_type: i8) -> Vec<JsonValue> {
@@ -20,26 +17,13 @@ fn create_test_block(value: &Value, ext_type: i8) -> Vec<JsonValue> {
     // Comprimir os dados com LZ4
     let compressed_data = compress(&buffer, None, false).unwrap_or_default();
     
-    // Criar o buffer de cabeçalho
+    // Criar o buffer de cabeçalho: o tamanho descomprimido como inteiro MessagePack,
+    // com o marcador uint8 (204) quando cabe em um byte, como o MessagePack-CSharp grava
     let mut header_data = Vec::new();
-    header_data.push(204); // Tipo fixo
-    
-    // Codificar o tamanho descomprimido em big-endian
-    let size = buffer.len();
-    if size <= 0xFF {
-        header_data.push(size as u8);
-    } else if size <= 0xFFFF {
-        header_data.push((size >> 8) as u8);
-        header_data.push(size as u8);
-    } else if size <= 0xFFFFFF {
-        header_data.push((size >> 16) as u8);
-        header_data.push((size >> 8) as u8);
-        header_data.push(size as u8);
+    if buffer.len() <= 0xFF {
+        header_data.extend([204, buffer.len() as u8]);
     } else {
-        header_data.push((size >> 24) as u8);
-        header_data.push((size >> 16) as u8);
-        header_data.push((size >> 8) as u8);
-        header_data.push(size as u8);
+        write_value(&mut header_data, &Value::from(buffer.len())).unwrap();
     }
     
     // Criar o JSON com a estrutura LZ4BlockArray (par de blocos)
@@ -190,9 +174,9 @@ fn test_mixed_types_multi_blocks() {
     let block1 = Value::Array(vec![Value::Integer(1.into()), Value::Integer(2.into())]);
     all_blocks.extend(create_test_block(&block1, 98));
     
-    // Bloco com tipo 99 (tipo não suportado)
+    // Bloco com tipo 50 (tipo não suportado)
     let block2 = Value::String("Este bloco tem tipo não suportado".into());
-    all_blocks.extend(create_test_block(&block2, 99));
+    all_blocks.extend(create_test_block(&block2, 50));
     
     // Outro bloco com tipo 98
     let block3 = Value::Map(vec![(Value::String("key".into()), Value::String("value".into()))]);
@@ -206,9 +190,10 @@ fn test_mixed_types_multi_blocks() {
     assert!(result.is_err(), "Should fail with unsupported extension type");
     
     // Verificar a mensagem de erro específica
-    let error_message = result.unwrap_err();
-    assert!(error_message.contains("Unsupported extension type: 99"), 
-            "Error message should mention the unsupported type 99, got: {}", error_message);
+    let error = result.unwrap_err();
+    assert!(matches!(error, Error::UnsupportedExtension(50)),
+            "Error should mention the unsupported type 50, got: {}", error);
+    assert_eq!(error.code(), 7);
     
     // Tentar processar apenas o primeiro bloco ignorando erros
     let first_block_file = generate_test_file("first_block_only", &all_blocks[0..2]);
@@ -309,4 +294,124 @@ fn test_large_multi_blocks() {
     
     // Sucessso se chegamos até aqui
     println!("Todos os testes passaram com sucesso!");
-} 
\ No newline at end of file
+} 
+#[test]
+fn test_block_array_with_several_lz4_blocks() {
+    // Um único envelope ext 98 cujo cabeçalho declara dois blocos,
+    // seguido de um `bin` por bloco (formato do MessagePack-CSharp)
+    let items = (0..200).map(|i| Value::String(format!("item {}", i).into())).collect();
+    let value = Value::Map(vec![(Value::String("items".into()), Value::Array(items))]);
+    let mut buffer = Vec::new();
+    write_value(&mut buffer, &value).unwrap();
+    let (first, second) = buffer.split_at(buffer.len() / 2);
+    
+    let mut header_data = Vec::new();
+    write_value(&mut header_data, &Value::from(first.len())).unwrap();
+    write_value(&mut header_data, &Value::from(second.len())).unwrap();
+    
+    let blocks = vec![
+        json!({ "buffer": { "type": "Buffer", "data": header_data }, "type": 98 }),
+        json!({ "type": "Buffer", "data": compress(first, None, false).unwrap() }),
+        json!({ "type": "Buffer", "data": compress(second, None, false).unwrap() }),
+    ];
+    let file_path = generate_test_file("split_blocks", &blocks);
+    
+    // Os dois blocos devem ser concatenados em um único fluxo MessagePack
+    let result = LZ4MessagePackProcessor::process(Some(&file_path), OutputFormat::Human)
+        .expect("Failed to process split blocks");
+    let parsed: JsonValue = serde_json::from_str(&result).expect("Failed to parse output");
+    let items = parsed["items"].as_array().expect("Output should contain the original array");
+    assert_eq!(items.len(), 200);
+    assert_eq!(items[199], "item 199");
+}
+
+#[test]
+fn test_block_count_mismatch_is_an_error() {
+    // Cabeçalho declara dois blocos, mas apenas um `bin` o segue
+    let mut buffer = Vec::new();
+    write_value(&mut buffer, &Value::String("apenas um bloco".into())).unwrap();
+    
+    let mut header_data = Vec::new();
+    write_value(&mut header_data, &Value::from(buffer.len())).unwrap();
+    write_value(&mut header_data, &Value::from(buffer.len())).unwrap();
+    
+    let blocks = vec![
+        json!({ "buffer": { "type": "Buffer", "data": header_data }, "type": 98 }),
+        json!({ "type": "Buffer", "data": compress(&buffer, None, false).unwrap() }),
+    ];
+    let file_path = generate_test_file("block_count_mismatch", &blocks);
+    
+    let error = LZ4MessagePackProcessor::process(Some(&file_path), OutputFormat::Human).unwrap_err();
+    assert!(matches!(error, Error::BlockCountMismatch { declared: 2, found: 1 }), "Unexpected error: {}", error);
+    assert_eq!(error.code(), 4);
+}
+
+#[test]
+fn test_block_length_mismatch_is_an_error() {
+    // O tamanho declarado no cabeçalho não corresponde ao bloco descomprimido
+    let mut buffer = Vec::new();
+    write_value(&mut buffer, &Value::String("tamanho incorreto".into())).unwrap();
+    
+    let mut header_data = Vec::new();
+    write_value(&mut header_data, &Value::from(buffer.len() + 10)).unwrap();
+    
+    let blocks = vec![
+        json!({ "buffer": { "type": "Buffer", "data": header_data }, "type": 98 }),
+        json!({ "type": "Buffer", "data": compress(&buffer, None, false).unwrap() }),
+    ];
+    let file_path = generate_test_file("block_length_mismatch", &blocks);
+    
+    let error = LZ4MessagePackProcessor::process(Some(&file_path), OutputFormat::Human).unwrap_err();
+    assert!(
+        matches!(error, Error::BlockLengthMismatch { block: 0, .. } | Error::CorruptBlock { block: 0, .. }),
+        "Unexpected error: {}", error
+    );
+}
+
+// Função auxiliar para criar uma extensão LZ4Block (tipo 99): tamanho int32 + bloco LZ4
+fn create_lz4_block_ext(value: &Value) -> JsonValue {
+    let mut buffer = Vec::new();
+    write_value(&mut buffer, value).unwrap();
+    
+    let mut payload = vec![0xd2];
+    payload.extend_from_slice(&(buffer.len() as i32).to_be_bytes());
+    payload.extend_from_slice(&compress(&buffer, None, false).unwrap());
+    
+    json!({
+        "buffer": {
+            "type": "Buffer",
+            "data": payload
+        },
+        "type": 99
+    })
+}
+
+#[test]
+fn test_lz4_block_extension() {
+    let value = Value::Map(vec![
+        (Value::String("modo".into()), Value::String("Lz4Block".into())),
+        (Value::String("valor".into()), Value::Integer(42.into())),
+    ]);
+    
+    // Uma extensão 99 isolada, exatamente como o MessagePack-CSharp a produz
+    let test_dir = Path::new("tests/multi_blocks");
+    let file_path = test_dir.join("lz4_block.json");
+    fs::write(&file_path, serde_json::to_string_pretty(&create_lz4_block_ext(&value)).unwrap())
+        .expect("Failed to write test file");
+    
+    let result = LZ4MessagePackProcessor::process(Some(&file_path.to_string_lossy()), OutputFormat::Human)
+        .expect("Failed to process Lz4Block extension");
+    let parsed: JsonValue = serde_json::from_str(&result).expect("Failed to parse output");
+    assert_eq!(parsed["modo"], "Lz4Block");
+    assert_eq!(parsed["valor"], 42);
+    
+    // Também é aceita dentro de um array, mas sem blocos binários depois dela
+    let with_trailing_block = vec![
+        create_lz4_block_ext(&value),
+        json!({ "type": "Buffer", "data": [1, 2, 3] }),
+    ];
+    let file_path = generate_test_file("lz4_block_with_trailing_block", &with_trailing_block);
+    let error = LZ4MessagePackProcessor::process(Some(&file_path), OutputFormat::Human).unwrap_err();
+    assert!(matches!(error, Error::InvalidEnvelope { .. }), "Unexpected error: {}", error);
+    assert!(error.to_string().contains("Lz4Block extension must stand alone"), "Unexpected error: {}", error);
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "parallel")]
+
+use lz4_messagepack::{
+    decode, decode_to_json, to_vec_lz4, Compression, Envelope, Error, JsonOptions, LZ4MessagePackProcessor,
+    OutputFormat,
+};
+use rmpv::encode::write_value;
+use rmpv::Value;
+
+mod common;
+use common::rows;
+
+/// Envelope com os blocos indicados substituídos por bytes que não são LZ4 válido
+fn corrupt_blocks(envelope: &[u8], blocks: &[usize]) -> Vec<u8> {
+    let mut value = Envelope::parse_msgpack(envelope).unwrap().to_value();
+    let Value::Array(elements) = &mut value else { unreachable!() };
+    for &block in blocks {
+        // O elemento 0 é a extensão; o bloco i é o elemento i + 1
+        elements[block + 1] = Value::Binary(vec![0xff; 16]);
+    }
+    let mut output = Vec::new();
+    write_value(&mut output, &value).unwrap();
+    output
+}
+
+#[test]
+fn test_parallel_matches_sequential() {
+    let envelope = to_vec_lz4(&rows(50_000), Compression::Lz4BlockArray).unwrap();
+    let parsed = Envelope::parse_msgpack(&envelope).unwrap();
+    assert!(parsed.blocks().len() > 8);
+    assert_eq!(parsed.decompress_parallel().unwrap(), decode(&envelope).unwrap());
+
+    // Lz4Block tem um único bloco e segue o caminho sequencial
+    let lz4_block = to_vec_lz4(&rows(100), Compression::Lz4Block).unwrap();
+    assert_eq!(Envelope::parse_msgpack(&lz4_block).unwrap().decompress_parallel().unwrap(), decode(&lz4_block).unwrap());
+
+    let options = JsonOptions { parallel: true, ..Default::default() };
+    assert_eq!(decode_to_json(&envelope, &options).unwrap(), decode_to_json(&envelope, &JsonOptions::default()).unwrap());
+    assert_eq!(
+        LZ4MessagePackProcessor::process_bytes_with(&envelope, OutputFormat::Human, &options).unwrap(),
+        LZ4MessagePackProcessor::process_bytes(&envelope, OutputFormat::Human).unwrap()
+    );
+}
+
+#[test]
+fn test_parallel_errors_are_deterministic() {
+    let envelope = to_vec_lz4(&rows(50_000), Compression::Lz4BlockArray).unwrap();
+
+    // Com vários blocos corrompidos o erro é sempre o do primeiro, como no caminho sequencial
+    let corrupt = Envelope::parse_msgpack(&corrupt_blocks(&envelope, &[7, 3, 5])).unwrap();
+    let sequential = corrupt.decompress().unwrap_err();
+    assert!(matches!(sequential, Error::CorruptBlock { block: 3, .. }), "Unexpected error: {}", sequential);
+    for _ in 0..20 {
+        assert_eq!(corrupt.decompress_parallel().unwrap_err().to_string(), sequential.to_string());
+    }
+
+    // A contagem de blocos é verificada antes de descomprimir
+    let mut value = Envelope::parse_msgpack(&envelope).unwrap().to_value();
+    if let Value::Array(elements) = &mut value {
+        elements.pop();
+    }
+    let mut truncated = Vec::new();
+    write_value(&mut truncated, &value).unwrap();
+    let error = Envelope::parse_msgpack(&truncated).unwrap().decompress_parallel().unwrap_err();
+    assert!(matches!(error, Error::BlockCountMismatch { .. }), "Unexpected error: {}", error);
+}
@@ -0,0 +1,134 @@
+use lz4_messagepack::{json_to_value_with, value_to_json_with, JsonOptions, MsgpackOptions, Schema, Timestamp};
+use rmpv::Value;
+use serde_json::{json, Value as JsonValue};
+
+const GUID: &str = "0f8fad5b-d9cb-469f-a165-70867728950e";
+// Guid.ToByteArray(): os três primeiros grupos em little-endian
+const GUID_BYTES: [u8; 16] = [
+    0x5b, 0xad, 0x8f, 0x0f, 0xcb, 0xd9, 0x9f, 0x46, 0xa1, 0x65, 0x70, 0x86, 0x77, 0x28, 0x95, 0x0e,
+];
+
+fn schema(field_type: &str) -> Schema {
+    format!(r#"{{ "root": "Item", "types": {{ "Item": {{ "fields": [ {{ "key": 0, "name": "value", "type": "{}" }} ] }} }} }}"#, field_type)
+        .parse()
+        .unwrap()
+}
+
+fn decode_as(field_type: &str, value: Value) -> JsonValue {
+    let options = JsonOptions { schema: Some(schema(field_type)), ..Default::default() };
+    value_to_json_with(&Value::Array(vec![value]), &options)["value"].clone()
+}
+
+fn encode_as(field_type: &str, json: JsonValue) -> lz4_messagepack::Result<Value> {
+    let options = MsgpackOptions { schema: Some(schema(field_type)), ..Default::default() };
+    json_to_value_with(&json!({ "value": json }), &options).map(|value| value[0].clone())
+}
+
+/// Memória de um System.Decimal: flags, hi32, lo32 e mid32 em little-endian
+fn decimal_bytes(negative: bool, scale: u32, hi: u32, mid: u32, lo: u32) -> Vec<u8> {
+    let flags = (scale << 16) | if negative { 0x8000_0000 } else { 0 };
+    [flags, hi, lo, mid].iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+#[test]
+fn test_guid_fields() {
+    // GuidFormatter grava a string de 36 caracteres; NativeGuidResolver grava 16 bytes
+    assert_eq!(decode_as("guid", Value::from(GUID.to_uppercase())), json!(GUID));
+    assert_eq!(decode_as("guid", Value::Binary(GUID_BYTES.to_vec())), json!(GUID));
+
+    // Valores que não são Guid continuam como estão
+    assert_eq!(decode_as("guid", Value::from("abc")), json!("abc"));
+    assert_eq!(decode_as("guid", Value::Binary(vec![1, 2])), json!("AQI="));
+
+    assert_eq!(encode_as("guid", json!(GUID)).unwrap(), Value::from(GUID));
+    assert_eq!(encode_as("guid", json!("{0F8FAD5BD9CB469FA16570867728950E}")).unwrap(), Value::from(GUID));
+    let error = encode_as("guid", json!("0f8fad5b-d9cb")).unwrap_err();
+    assert!(error.to_string().contains("Invalid Guid"), "Unexpected error: {}", error);
+}
+
+#[test]
+fn test_decimal_fields() {
+    // DecimalFormatter grava a string invariante; o valor exato é mantido
+    assert_eq!(decode_as("decimal", Value::from("79228162514264337593543950335.5")), json!("79228162514264337593543950335.5"));
+    assert_eq!(decode_as("decimal", Value::Binary(decimal_bytes(true, 4, 0, 0, 1_234_500))), json!("-123.4500"));
+    assert_eq!(decode_as("decimal", Value::Binary(decimal_bytes(false, 3, 0, 0, 5))), json!("0.005"));
+    assert_eq!(
+        decode_as("decimal", Value::Binary(decimal_bytes(false, 0, u32::MAX, u32::MAX, u32::MAX))),
+        json!("79228162514264337593543950335")
+    );
+
+    assert_eq!(encode_as("decimal", json!("-0.10")).unwrap(), Value::from("-0.10"));
+    assert_eq!(encode_as("decimal", json!(2.5)).unwrap(), Value::from("2.5"));
+    assert!(encode_as("decimal", json!("1,5")).is_err());
+    assert!(encode_as("decimal", json!("1e5")).is_err());
+}
+
+#[test]
+fn test_timespan_fields() {
+    let cases = [
+        (0, "PT0S"),
+        (54_000_000_000, "PT1H30M"),
+        (1_296_002_500_000, "P1DT12H0.25S"),
+        (864_000_000_000, "P1D"),
+        (-5_000_000, "-PT0.5S"),
+        (1, "PT0.0000001S"),
+        (i64::MAX, "P10675199DT2H48M5.4775807S"),
+        (i64::MIN, "-P10675199DT2H48M5.4775808S"),
+    ];
+    for (ticks, iso) in cases {
+        assert_eq!(decode_as("timespan", Value::from(ticks)), json!(iso));
+        assert_eq!(encode_as("timespan", json!(iso)).unwrap(), Value::from(ticks), "{}", iso);
+    }
+
+    assert_eq!(encode_as("timespan", json!("PT90M")).unwrap(), Value::from(54_000_000_000i64));
+
+    // Só os segundos aceitam fração; meio dia ou uma hora e meia não viram meio segundo
+    assert_eq!(encode_as("timespan", json!("PT0.5S")).unwrap(), Value::from(5_000_000));
+    for fraction in ["P0.5D", "PT1.5H"] {
+        let error = encode_as("timespan", json!(fraction)).unwrap_err();
+        assert!(error.to_string().contains("Invalid ISO 8601 duration"), "{}: {}", fraction, error);
+    }
+    for invalid in ["P1Y", "P1M", "PT", "P", "1H", "PT1.5M", "PT1S1M", "P99999999D"] {
+        let error = encode_as("timespan", json!(invalid)).unwrap_err();
+        assert!(error.to_string().contains("Invalid ISO 8601 duration"), "{}: {}", invalid, error);
+    }
+
+    // Valores que estouram no cálculo dos ticks são recusados em vez de causar panic
+    for overflow in [
+        "P99999999999999999999999999999999999D",
+        "PT99999999999999999999999999999999999H",
+        "-P170141183460469231731687303715884105727D",
+    ] {
+        let error = encode_as("timespan", json!(overflow)).unwrap_err();
+        assert!(error.to_string().contains("Invalid ISO 8601 duration"), "{}: {}", overflow, error);
+    }
+}
+
+#[test]
+fn test_datetimeoffset_fields() {
+    // DateTimeOffsetFormatter grava [DateTime com o horário local, offset em minutos]
+    let local: Timestamp = "2024-05-01T14:30:00Z".parse().unwrap();
+    let value = Value::Array(vec![local.to_value(), Value::from(120)]);
+    assert_eq!(decode_as("datetimeoffset", value.clone()), json!("2024-05-01T14:30:00+02:00"));
+    assert_eq!(encode_as("datetimeoffset", json!("2024-05-01T14:30:00+02:00")).unwrap(), value);
+
+    // O mesmo instante em outro fuso mantém o offset
+    let value = encode_as("datetimeoffset", json!("2024-05-01T09:00:00.5-03:30")).unwrap();
+    assert_eq!(value[1], Value::from(-210));
+    assert_eq!(decode_as("datetimeoffset", value), json!("2024-05-01T09:00:00.500-03:30"));
+    assert_eq!(encode_as("datetimeoffset", json!("2024-05-01T12:30:00Z")).unwrap()[1], Value::from(0));
+
+    // Com NativeDateTimeResolver o DateTime é o inteiro de ToBinary()
+    let native = Value::Array(vec![Value::from(local.to_datetime_binary().unwrap()), Value::from(120)]);
+    assert_eq!(decode_as("datetimeoffset", native), json!("2024-05-01T14:30:00+02:00"));
+
+    assert_eq!(decode_as("datetimeoffset", Value::from(1)), json!(1));
+    assert!(encode_as("datetimeoffset", json!("amanhã")).is_err());
+
+    // Os extremos do intervalo não estouram ao somar o offset
+    let edge = encode_as("datetimeoffset", json!("9999-12-31T23:59:59-23:59")).unwrap();
+    assert_eq!(decode_as("datetimeoffset", edge), json!("9999-12-31T23:59:59-23:59"));
+    for overflow in ["9223372036854775807-12-31T23:59:59+23:59", "-9223372036854775808-01-01T00:00:00-23:59"] {
+        assert!(encode_as("datetimeoffset", json!(overflow)).is_err(), "{}", overflow);
+    }
+}
@@ -1,10 +1,8 @@
-#[path = "../src/models.rs"]
-mod models;
-use models::{RouteResponse, parse_route_json, route_to_msgpack, msgpack_to_route, route_to_json};
+use lz4_messagepack::models::{
+    parse_route_json, route_to_msgpack, route_to_msgpack_with, msgpack_to_route, route_to_json, StructEncoding,
+};
 
-#[path = "../src/main.rs"]
-mod main;
-use main::{LZ4MessagePackProcessor, OutputFormat};
+use lz4_messagepack::{LZ4MessagePackProcessor, OutputFormat};
 
 use std::fs::File;
 use std::io::{Read, Write};
@@ -112,6 +110,30 @@ fn test_route_serialization_deserialization() {
     assert_eq!(route_response.waypoints.len(), file_route.waypoints.len());
 }
 
+#[test]
+fn test_route_positional_and_map_encoding() {
+    let route_response = parse_route_json(TEST_JSON).expect("Failed to parse test JSON");
+
+    // Default layout matches C# [Key(n)] models: positional arrays in field order
+    let array_data = route_to_msgpack(&route_response).expect("Failed to serialize as arrays");
+    let value = rmpv::decode::read_value(&mut array_data.as_slice()).unwrap();
+    assert_eq!(value.as_array().map(Vec::len), Some(3));
+    assert_eq!(value[0].as_str(), Some("Ok"));
+
+    let step = &value[1][0][0][0][0][0];
+    assert_eq!(step.as_array().map(Vec::len), Some(10), "Step has ten keys");
+    assert_eq!(step[9].as_str(), Some("B96"));
+
+    // Map layout keyed by field name is still available
+    let map_data = route_to_msgpack_with(&route_response, StructEncoding::Map).expect("Failed to serialize as maps");
+    let value = rmpv::decode::read_value(&mut map_data.as_slice()).unwrap();
+    assert_eq!(value["code"].as_str(), Some("Ok"));
+
+    // Both layouts read back into the same model
+    assert_eq!(msgpack_to_route(&array_data).unwrap(), route_response);
+    assert_eq!(msgpack_to_route(&map_data).unwrap(), route_response);
+}
+
 #[test]
 fn test_route_from_lz4_compressed() {
     // Parse test JSON
@@ -127,16 +149,20 @@ fn test_route_from_lz4_compressed() {
     let mut lz4_file = File::create("test_route.lz4").expect("Failed to create LZ4 file");
     lz4_file.write_all(&compressed_data).expect("Failed to write LZ4 data");
     
-    // Create JSON wrapper for compressed data (similar to what processor would create)
+    // Create JSON wrapper for compressed data: the ext 98 header carries the
+    // uncompressed length as a MessagePack integer, followed by the LZ4 block
+    let mut header_data = Vec::new();
+    rmpv::encode::write_value(&mut header_data, &rmpv::Value::from(msgpack_data.len()))
+        .expect("Failed to write block length");
     let json_wrapper = json!([
         {
             "type": 98,
             "buffer": {
-                "data": compressed_data.iter().map(|&b| b as u64).collect::<Vec<_>>()
+                "data": header_data
             }
         },
         {
-            "data": msgpack_data.iter().map(|&b| b as u64).collect::<Vec<_>>()
+            "data": compressed_data.iter().map(|&b| b as u64).collect::<Vec<_>>()
         }
     ]);
     
@@ -146,7 +172,7 @@ fn test_route_from_lz4_compressed() {
     wrapper_file.write_all(wrapper_json.as_bytes()).expect("Failed to write wrapper JSON");
     
     // Test that the LZ4MessagePackProcessor can handle this data
-    let processor_result = LZ4MessagePackProcessor::process(Some("test_route_wrapper.json"), main::OutputFormat::Json)
+    let processor_result = LZ4MessagePackProcessor::process(Some("test_route_wrapper.json"), OutputFormat::Json)
         .expect("Failed to process test route data");
     
     // Parse the processor result
@@ -212,5 +238,4 @@ fn test_process_real_data() {
     }
     
     // This test should pass regardless of whether we could process the data
-    assert!(true);
 } 
\ No newline at end of file
@@ -0,0 +1,225 @@
+use lz4_messagepack::schema::{FieldKey, FieldType, TypeDef};
+use lz4_messagepack::{
+    decode, decode_any_to_json, encode_value, json_to_value, json_to_value_with, value_to_json_with, Envelope, Error,
+    JsonOptions, LZ4MessagePackProcessor, MsgpackOptions, OutputFormat, Schema,
+};
+use serde_json::{json, Value as JsonValue};
+
+fn route_schema() -> Schema {
+    Schema::from_file("tests/schemas/route.json").unwrap()
+}
+
+fn with_schema(schema: Schema) -> JsonOptions {
+    JsonOptions { schema: Some(schema), ..Default::default() }
+}
+
+fn convert(schema: Schema, value: JsonValue) -> JsonValue {
+    value_to_json_with(&json_to_value(&value).unwrap(), &with_schema(schema))
+}
+
+#[test]
+fn test_route_example_gets_named_fields() {
+    // Mesmo payload posicional gerado por test_csharp_model
+    let output = LZ4MessagePackProcessor::process_with(
+        Some("tests/csharp_model/route_example.json"),
+        OutputFormat::Human,
+        &with_schema(route_schema()),
+    ).unwrap();
+    let output: JsonValue = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(output["code"], "Ok");
+    assert_eq!(output["waypoints"][1]["name"], "End");
+
+    let step = &output["routes"][0]["legs"][0]["steps"][0];
+    assert_eq!(step["maneuver"]["type"], "depart");
+    assert_eq!(step["maneuver"]["location"], json!([13.388798, 52.517033]));
+    assert_eq!(step["intersections"][0]["entry"], json!([true, false]));
+    assert_eq!(step["ref"], "A1");
+}
+
+#[test]
+fn test_without_schema_arrays_stay_positional() {
+    let value = json!(["https://example.com/errors/required", "Field is required", 400, "detail", "/api"]);
+    assert_eq!(value_to_json_with(&json_to_value(&value).unwrap(), &JsonOptions::default()), value);
+
+    // ProblemDetails só quando pedido
+    assert_eq!(convert(Schema::problem_details(), value), json!({
+        "type": "https://example.com/errors/required",
+        "title": "Field is required",
+        "status": 400,
+        "detail": "detail",
+        "instance": "/api"
+    }));
+}
+
+#[test]
+fn test_gaps_nulls_and_unknown_positions() {
+    let schema: Schema = r#"{
+        "root": "Item",
+        "types": { "Item": { "fields": [
+            { "key": 0, "name": "id", "type": "int" },
+            { "key": 2, "name": "label", "type": "string?" }
+        ] } }
+    }"#.parse().unwrap();
+
+    // A posição 1 é um buraco (nil); a posição 3 não está no schema
+    assert_eq!(convert(schema.clone(), json!([7, null, null, "extra"])), json!({ "id": 7, "label": null, "3": "extra" }));
+    assert_eq!(convert(schema.clone(), json!([7, null, "seven"])), json!({ "id": 7, "label": "seven" }));
+
+    // A posição que não está no schema volta para o mesmo lugar
+    let value = rmpv::Value::Array(vec![7.into(), rmpv::Value::Nil, rmpv::Value::Nil, "extra".into()]);
+    let json = json!({ "id": 7, "label": null, "3": "extra" });
+    assert_eq!(encode_with_schema(schema.clone(), &json).unwrap(), value);
+
+    // Índices sem campo muito além das chaves declaradas são recusados em vez de alocar o array
+    for key in ["18446744073709551615", "100000000", "6"] {
+        let error = encode_with_schema(schema.clone(), &json!({ "id": 7, key: 1 })).unwrap_err();
+        assert!(error.to_string().contains("has no position"), "{}: {}", key, error);
+    }
+}
+
+#[test]
+fn test_map_encoded_contracts_and_maps() {
+    let schema: Schema = r#"{
+        "root": "Catalog",
+        "types": {
+            "Catalog": { "fields": [
+                { "key": "items", "type": "map<string, Item>" },
+                { "key": "owner", "name": "ownerName", "type": "string" }
+            ] },
+            "Item": { "fields": [ { "key": 0, "name": "id", "type": "int" } ] }
+        }
+    }"#.parse().unwrap();
+
+    let output = convert(schema, json!({ "items": { "a": [1], "b": [2] }, "owner": "me", "other": [3] }));
+    assert_eq!(output, json!({ "items": { "a": { "id": 1 }, "b": { "id": 2 } }, "ownerName": "me", "other": [3] }));
+
+    // Mapas com chaves inteiras também casam com [Key(n)]
+    let value = rmpv::Value::Map(vec![(rmpv::Value::from(0), rmpv::Value::from(5))]);
+    let schema: Schema = r#"{ "root": "Item", "types": { "Item": { "fields": [ { "key": 0, "name": "id" } ] } } }"#
+        .parse().unwrap();
+    assert_eq!(value_to_json_with(&value, &with_schema(schema)), json!({ "id": 5 }));
+}
+
+fn shapes_schema() -> Schema {
+    r#"{
+        "root": "Drawing",
+        "types": {
+            "Drawing": { "fields": [ { "key": 0, "name": "shapes", "type": "IShape[]" } ] },
+            "IShape": { "union": [ { "key": 0, "type": "Circle" }, { "key": 1, "type": "Square" } ] },
+            "Circle": { "fields": [ { "key": 0, "name": "radius", "type": "float" } ] },
+            "Square": { "fields": [ { "key": 0, "name": "side", "type": "float" } ] }
+        }
+    }"#.parse().unwrap()
+}
+
+fn encode_with_schema(schema: Schema, json: &JsonValue) -> lz4_messagepack::Result<rmpv::Value> {
+    json_to_value_with(json, &MsgpackOptions { schema: Some(schema), ..Default::default() })
+}
+
+#[test]
+fn test_union_bodies_use_the_case_type() {
+    // Casos conhecidos viram {"$type": ...}; chaves desconhecidas ficam como [key, body]
+    let output = convert(shapes_schema(), json!([[[0, [1.5]], [1, [2.5]], [9, [3.5]]]]));
+    assert_eq!(output, json!({ "shapes": [
+        { "$type": "Circle", "radius": 1.5 },
+        { "$type": "Square", "side": 2.5 },
+        [9, [3.5]]
+    ] }));
+}
+
+#[test]
+fn test_named_json_encodes_back_to_positional_messagepack() {
+    let value = json_to_value(&json!([[[0, [1.5]], [1, [2.5]], [9, [3.5]]]])).unwrap();
+    let json = value_to_json_with(&value, &with_schema(shapes_schema()));
+    assert_eq!(encode_with_schema(shapes_schema(), &json).unwrap(), value);
+
+    // Inteiros em campos float são gravados como float; $value guarda corpos que não são objetos
+    let json = json!({ "shapes": [{ "$type": "Circle", "radius": 2 }, { "$type": "Square", "$value": [4.5] }] });
+    assert_eq!(
+        encode_with_schema(shapes_schema(), &json).unwrap(),
+        json_to_value(&json!([[[0, [2.0]], [1, [4.5]]]])).unwrap()
+    );
+
+    let unknown_case = json!({ "shapes": [{ "$type": "Triangle" }] });
+    assert!(matches!(encode_with_schema(shapes_schema(), &unknown_case), Err(Error::InvalidInput(_))));
+    let unknown_field = json!({ "shapes": [], "color": "red" });
+    assert!(matches!(encode_with_schema(shapes_schema(), &unknown_field), Err(Error::InvalidInput(_))));
+}
+
+#[test]
+fn test_route_example_round_trips_through_named_json() {
+    let json: JsonValue = serde_json::from_str(&std::fs::read_to_string("tests/csharp_model/route_example.json").unwrap()).unwrap();
+    let original = Envelope::parse_json(&json).unwrap().remove(0).decompress().unwrap();
+
+    let value = rmpv::decode::read_value(&mut original.as_slice()).unwrap();
+    let named = value_to_json_with(&value, &with_schema(route_schema()));
+    assert_eq!(named["routes"][0]["legs"][0]["steps"][0]["ref"], "A1");
+    assert_eq!(encode_with_schema(route_schema(), &named).unwrap(), value);
+
+    // O processador codifica JSON nomeado com o schema, como a CLI com --schema
+    let path = std::env::temp_dir().join(format!("named_route_{}.json", std::process::id()));
+    std::fs::write(&path, serde_json::to_vec(&named).unwrap()).unwrap();
+    let envelopes = LZ4MessagePackProcessor::process_binary_with(path.to_str(), &with_schema(route_schema())).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(decode(&envelopes[0]).unwrap(), original);
+}
+
+#[test]
+fn test_schema_applies_to_decoded_envelopes() {
+    let envelope = encode_value(&json_to_value(&json!(["type", "title", 404, "detail", "/x"])).unwrap()).unwrap();
+    let json = decode_any_to_json(&envelope, &with_schema(Schema::problem_details())).unwrap();
+    assert_eq!(json["status"], 404);
+}
+
+#[test]
+fn test_field_types_parse_and_print() {
+    for text in ["int", "string?", "Leg[]", "int?[]", "Leg[]?", "map<string, Item[]>", "map<int, map<string, any>>"] {
+        let field_type: FieldType = text.parse().unwrap();
+        assert_eq!(field_type.to_string(), text);
+    }
+    assert_eq!(
+        "Leg[]?".parse::<FieldType>().unwrap(),
+        FieldType::Nullable(Box::new(FieldType::Array(Box::new(FieldType::Named("Leg".to_string())))))
+    );
+    assert!(matches!("map<int>".parse::<FieldType>(), Err(Error::InvalidSchema(_))));
+    assert!(matches!("List<int>".parse::<FieldType>(), Err(Error::InvalidSchema(_))));
+}
+
+#[test]
+fn test_schema_json_round_trip() {
+    let schema = route_schema();
+    assert_eq!(Schema::from_json(&schema.to_json()).unwrap(), schema);
+
+    match &schema.types["Step"] {
+        TypeDef::Object { fields } => {
+            assert_eq!(fields[9].key, FieldKey::Index(9));
+            assert_eq!(fields[9].name, "ref");
+        },
+        other => panic!("Unexpected type definition: {:?}", other),
+    }
+}
+
+#[test]
+fn test_invalid_schemas_are_rejected() {
+    let cases = [
+        r#"{ "types": { "A": { "fields": [ { "key": 0, "name": "b", "type": "Missing" } ] } } }"#,
+        r#"{ "root": "Missing", "types": {} }"#,
+        r#"{ "types": { "A": { "fields": [ { "key": 0, "name": "a" }, { "key": 0, "name": "b" } ] } } }"#,
+        r#"{ "types": { "A": { "fields": [ { "key": 0 } ] } } }"#,
+        r#"{ "types": { "A": { "union": [ { "key": 0, "type": "Missing" } ] } } }"#,
+        r#"{ "types": { "A": {} } }"#,
+        r#"{ "types": { "A[]": { "fields": [] } } }"#,
+        r#"{ "types": { "string": { "fields": [] } } }"#,
+        r#"{ "root": "A" }"#,
+        "not json",
+    ];
+    for case in cases {
+        let error = case.parse::<Schema>().unwrap_err();
+        assert!(matches!(error, Error::InvalidSchema(_)), "Unexpected error for {}: {}", case, error);
+        assert_eq!(error.code(), 2);
+    }
+
+    assert!(matches!(route_schema().with_root("Nope"), Err(Error::InvalidSchema(_))));
+    assert!(matches!(Schema::from_file("tests/schemas/missing.json"), Err(Error::Io { .. })));
+}
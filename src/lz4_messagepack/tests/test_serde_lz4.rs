@@ -0,0 +1,76 @@
+use lz4_messagepack::models::{route_to_msgpack, RouteResponse, Waypoint};
+use lz4_messagepack::{decode, from_slice_lz4, to_vec_lz4, Compression, Envelope, Error};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Item {
+    id: u32,
+    name: String,
+    tags: Vec<String>,
+}
+
+fn items(count: u32) -> Vec<Item> {
+    (0..count).map(|id| Item { id, name: format!("item {}", id), tags: vec!["a".to_string(); 3] }).collect()
+}
+
+#[test]
+fn test_round_trip_in_both_modes() {
+    let value = items(10);
+
+    for compression in [Compression::Lz4BlockArray, Compression::Lz4Block] {
+        let envelope = to_vec_lz4(&value, compression).unwrap();
+        let expected_ext = match compression {
+            Compression::Lz4BlockArray => 98,
+            Compression::Lz4Block => 99,
+        };
+        assert_eq!(Envelope::parse_msgpack(&envelope).unwrap().ext_type(), expected_ext);
+
+        let decoded: Vec<Item> = from_slice_lz4(&envelope).unwrap();
+        assert_eq!(decoded, value);
+    }
+}
+
+#[test]
+fn test_large_value_spans_several_blocks() {
+    let value = items(5000);
+    let envelope = to_vec_lz4(&value, Compression::Lz4BlockArray).unwrap();
+    assert!(Envelope::parse_msgpack(&envelope).unwrap().blocks().len() > 1);
+
+    let decoded: Vec<Item> = from_slice_lz4(&envelope).unwrap();
+    assert_eq!(decoded, value);
+}
+
+#[test]
+fn test_route_models_through_the_envelope() {
+    // Mesmo MessagePack que route_to_msgpack, só que comprimido
+    let route = RouteResponse {
+        code: "Ok".to_string(),
+        routes: Vec::new(),
+        waypoints: vec![Waypoint {
+            hint: "JQEAABQAAAAIAAAABAAAAGILRQCJDMMA".to_string(),
+            distance: 0.5,
+            name: "Friedrichstraße".to_string(),
+            location: [13.349, 52.515],
+        }],
+    };
+    let envelope = to_vec_lz4(&route, Compression::Lz4BlockArray).unwrap();
+    assert_eq!(decode(&envelope).unwrap(), route_to_msgpack(&route).unwrap());
+
+    let decoded: RouteResponse = from_slice_lz4(&envelope).unwrap();
+    assert_eq!(decoded, route);
+}
+
+#[test]
+fn test_uncompressed_messagepack_is_accepted() {
+    let value = items(2);
+    let msgpack = rmp_serde::to_vec(&value).unwrap();
+    let decoded: Vec<Item> = from_slice_lz4(&msgpack).unwrap();
+    assert_eq!(decoded, value);
+}
+
+#[test]
+fn test_type_mismatch_is_an_error() {
+    let envelope = to_vec_lz4(&vec![1, 2, 3], Compression::Lz4BlockArray).unwrap();
+    let error = from_slice_lz4::<Vec<Item>>(&envelope).unwrap_err();
+    assert!(matches!(error, Error::InvalidMessagePack { .. }), "Unexpected error: {}", error);
+}
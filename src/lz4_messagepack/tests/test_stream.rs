@@ -0,0 +1,194 @@
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+
+use lz4_messagepack::{
+    decode, encode, encode_with, to_vec_lz4, Compression, Envelope, Error, Lz4BlockArrayReader, Lz4BlockArrayWriter,
+    LZ4_BLOCK_SIZE,
+};
+use rmpv::decode::read_value;
+use rmpv::Value;
+use serde::Deserialize;
+
+mod common;
+use common::rows;
+
+/// Leitor que conta quantos bytes já foram consumidos
+struct Counting<'a> {
+    data: &'a [u8],
+    consumed: usize,
+}
+
+impl Read for Counting<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = (&self.data[self.consumed..]).read(buf)?;
+        self.consumed += len;
+        Ok(len)
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Row {
+    id: u32,
+    name: String,
+}
+
+#[test]
+fn test_reader_decompresses_blocks_lazily() {
+    let envelope = to_vec_lz4(&rows(20_000), Compression::Lz4BlockArray).unwrap();
+    let expected = decode(&envelope).unwrap();
+
+    let mut source = Counting { data: &envelope, consumed: 0 };
+    let mut reader = Lz4BlockArrayReader::new(&mut source).unwrap();
+    assert_eq!(reader.uncompressed_len(), expected.len());
+    assert!(reader.block_lengths().len() > 2);
+    assert!(reader.block_lengths().iter().all(|&len| len <= LZ4_BLOCK_SIZE));
+
+    // Ler o primeiro bloco não consome o resto do envelope
+    let mut first = vec![0; 100];
+    reader.read_exact(&mut first).unwrap();
+    assert_eq!(first, expected[..100]);
+    drop(reader);
+    assert!(source.consumed < envelope.len() / 2, "consumed {} of {}", source.consumed, envelope.len());
+
+    let mut output = Vec::new();
+    Lz4BlockArrayReader::new(envelope.as_slice()).unwrap().read_to_end(&mut output).unwrap();
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn test_reader_feeds_rmpv_and_rmp_serde() {
+    let envelope = to_vec_lz4(&rows(5_000), Compression::Lz4BlockArray).unwrap();
+
+    let mut reader = Lz4BlockArrayReader::new(envelope.as_slice()).unwrap();
+    let value = read_value(&mut reader).unwrap();
+    assert_eq!(value.as_array().unwrap().len(), 5_000);
+    assert_eq!(value[4_999][1], Value::from("linha número 4999"));
+
+    let reader = Lz4BlockArrayReader::new(envelope.as_slice()).unwrap();
+    let decoded: Vec<Row> = rmp_serde::from_read(reader).unwrap();
+    assert_eq!(decoded[42], Row { id: 42, name: "linha número 42".to_string() });
+
+    // Lz4Block também é aceito
+    let lz4_block = to_vec_lz4(&rows(10), Compression::Lz4Block).unwrap();
+    let decoded: Vec<Row> = rmp_serde::from_read(Lz4BlockArrayReader::new(lz4_block.as_slice()).unwrap()).unwrap();
+    assert_eq!(decoded.len(), 10);
+}
+
+#[test]
+fn test_reader_streams_node_json_captures() {
+    // Cada envelope do arquivo convertido para binário e lido em sequência do mesmo fluxo
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string("tests/multi_blocks/large_multi_blocks.json").unwrap()).unwrap();
+    let envelopes = Envelope::parse_json(&json).unwrap();
+    let mut stream = Vec::new();
+    for envelope in &envelopes {
+        stream.extend(envelope.to_msgpack().unwrap());
+    }
+
+    let mut input = Cursor::new(stream);
+    for envelope in &envelopes {
+        let mut reader = Lz4BlockArrayReader::new(&mut input).unwrap();
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+        assert_eq!(output, envelope.decompress().unwrap());
+    }
+    assert_eq!(input.position() as usize, input.get_ref().len());
+}
+
+#[test]
+fn test_reader_errors() {
+    let envelope = encode_with(&vec![0xc0; 100_000], Compression::Lz4BlockArray).unwrap();
+
+    let truncated = Lz4BlockArrayReader::new(&envelope[..envelope.len() - 10]).unwrap();
+    let error = io::read_to_string(truncated).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    let inner = error.get_ref().and_then(|e| e.downcast_ref::<Error>()).unwrap();
+    assert!(matches!(inner, Error::InvalidEnvelope { index: Some(_), .. }), "Unexpected error: {}", inner);
+
+    // Corromper o último bloco só falha quando ele é lido
+    let mut corrupt = envelope.clone();
+    let start = corrupt.len() - Envelope::parse_msgpack(&envelope).unwrap().blocks().last().unwrap().len();
+    corrupt[start..start + 4].fill(0xff);
+    let mut reader = Lz4BlockArrayReader::new(corrupt.as_slice()).unwrap();
+    let mut first = vec![0; LZ4_BLOCK_SIZE];
+    reader.read_exact(&mut first).unwrap();
+    let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+    let inner = error.get_ref().and_then(|e| e.downcast_ref::<Error>()).unwrap();
+    assert!(matches!(inner, Error::CorruptBlock { .. } | Error::BlockLengthMismatch { .. }), "Unexpected error: {}", inner);
+
+    // Erros do cabeçalho aparecem já em new()
+    let mut mismatch = envelope.clone();
+    mismatch[0] += 1;
+    assert!(matches!(Lz4BlockArrayReader::new(mismatch.as_slice()), Err(Error::BlockCountMismatch { .. })));
+    assert!(matches!(Lz4BlockArrayReader::new(&[0x91, 0xd4, 50, 0][..]), Err(Error::UnsupportedExtension(50))));
+    assert!(matches!(Lz4BlockArrayReader::new(&[0xa1, b'x'][..]), Err(Error::InvalidEnvelope { index: None, .. })));
+}
+
+/// Grava em pedaços de tamanhos variados, como um serializador faria
+fn write_in_pieces<W: Write>(writer: &mut W, data: &[u8]) {
+    let mut rest = data;
+    let mut size = 1;
+    while !rest.is_empty() {
+        let (piece, tail) = rest.split_at(size.min(rest.len()));
+        writer.write_all(piece).unwrap();
+        rest = tail;
+        size = size * 7 % 50_021 + 1;
+    }
+}
+
+#[test]
+fn test_writer_matches_encode() {
+    let msgpack = rmp_serde::to_vec(&rows(20_000)).unwrap();
+    let expected = encode(&msgpack).unwrap();
+
+    // Sem seek: os blocos ficam guardados até finish()
+    let mut writer = Lz4BlockArrayWriter::new(Vec::new());
+    write_in_pieces(&mut writer, &msgpack);
+    assert_eq!(writer.finish().unwrap(), expected);
+
+    // Com seek: o cabeçalho é inserido antes dos blocos já gravados, depois do que havia no destino
+    let mut sink = Cursor::new(b"prefixo".to_vec());
+    sink.seek(SeekFrom::End(0)).unwrap();
+    let mut writer = Lz4BlockArrayWriter::seekable(sink).unwrap();
+    write_in_pieces(&mut writer, &msgpack);
+    let sink = writer.finish().unwrap();
+    assert_eq!(sink.position() as usize, sink.get_ref().len());
+    assert_eq!(&sink.get_ref()[..7], b"prefixo");
+    assert_eq!(&sink.get_ref()[7..], expected.as_slice());
+
+    // Tamanhos exatos de bloco e fluxos pequenos
+    for len in [1, LZ4_BLOCK_SIZE, 2 * LZ4_BLOCK_SIZE, 2 * LZ4_BLOCK_SIZE + 1] {
+        let msgpack = vec![0xc0; len];
+        let mut writer = Lz4BlockArrayWriter::seekable(Cursor::new(Vec::new())).unwrap();
+        writer.write_all(&msgpack).unwrap();
+        assert_eq!(writer.finish().unwrap().into_inner(), encode(&msgpack).unwrap(), "{} bytes", len);
+    }
+}
+
+#[test]
+fn test_writer_round_trips_through_reader() {
+    let mut writer = Lz4BlockArrayWriter::new(Vec::new());
+    rmp_serde::encode::write(&mut writer, &rows(5_000)).unwrap();
+    let envelope = writer.finish().unwrap();
+
+    let decoded: Vec<Row> = rmp_serde::from_read(Lz4BlockArrayReader::new(envelope.as_slice()).unwrap()).unwrap();
+    assert_eq!(decoded.len(), 5_000);
+    assert_eq!(decoded[4_999], Row { id: 4_999, name: "linha número 4999".to_string() });
+}
+
+#[test]
+fn test_empty_stream_round_trips() {
+    // Um fluxo vazio vira um único bloco de tamanho zero, que o decodificador aceita
+    let envelope = encode(&[]).unwrap();
+    assert_eq!(decode(&envelope).unwrap(), Vec::<u8>::new());
+    assert_eq!(Envelope::parse_msgpack(&envelope).unwrap().block_lengths().unwrap(), vec![0]);
+
+    let writer = Lz4BlockArrayWriter::new(Vec::new());
+    assert_eq!(writer.finish().unwrap(), envelope);
+    let writer = Lz4BlockArrayWriter::seekable(Cursor::new(Vec::new())).unwrap();
+    assert_eq!(writer.finish().unwrap().into_inner(), envelope);
+
+    let mut reader = Lz4BlockArrayReader::new(envelope.as_slice()).unwrap();
+    let mut output = Vec::new();
+    reader.read_to_end(&mut output).unwrap();
+    assert!(output.is_empty());
+}
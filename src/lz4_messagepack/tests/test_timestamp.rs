@@ -0,0 +1,177 @@
+use lz4_messagepack::{
+    from_slice_lz4, json_to_value_with, to_vec_lz4, value_to_json, value_to_json_with, Compression, Error,
+    JsonOptions, MsgpackOptions, Schema, Timestamp, TimestampFormat, TIMESTAMP_EXT,
+};
+use rmpv::Value;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+// 2024-05-01T12:30:00Z
+const SECONDS: i64 = 1_714_566_600;
+// DateTime.ToBinary() de 2024-05-01T12:30:00.250Z com DateTimeKind.Utc
+const DATETIME_BINARY: i64 = 5_250_187_652_429_887_904;
+
+fn with_format(timestamps: TimestampFormat) -> JsonOptions {
+    JsonOptions { timestamps, ..Default::default() }
+}
+
+fn datetime_schema() -> Schema {
+    r#"{
+        "root": "Event",
+        "types": {
+            "Event": { "fields": [
+                { "key": 0, "name": "at", "type": "timestamp" },
+                { "key": 1, "name": "native", "type": "datetime?" }
+            ] }
+        }
+    }"#.parse().unwrap()
+}
+
+#[test]
+fn test_timestamp_ext_forms() {
+    // Formato de 32 bits: só segundos
+    let value = Value::Ext(TIMESTAMP_EXT, (SECONDS as u32).to_be_bytes().to_vec());
+    assert_eq!(value_to_json(&value), json!("2024-05-01T12:30:00Z"));
+
+    // Formato de 64 bits: 30 bits de nanossegundos e 34 bits de segundos
+    let packed = (250_000_000u64 << 34) | SECONDS as u64;
+    let value = Value::Ext(TIMESTAMP_EXT, packed.to_be_bytes().to_vec());
+    assert_eq!(value_to_json(&value), json!("2024-05-01T12:30:00.250Z"));
+
+    // Formato de 96 bits: nanossegundos em 32 bits e segundos com sinal em 64 bits
+    let mut data = 500_000u32.to_be_bytes().to_vec();
+    data.extend_from_slice(&(-1i64).to_be_bytes());
+    assert_eq!(value_to_json(&Value::Ext(TIMESTAMP_EXT, data)), json!("1969-12-31T23:59:59.000500Z"));
+
+    // O menor formato é usado na volta
+    for (seconds, nanos, len) in [(SECONDS, 0, 4), (SECONDS, 1, 8), (-1, 0, 12), (1 << 34, 0, 12)] {
+        let timestamp = Timestamp::new(seconds, nanos).unwrap();
+        assert_eq!(timestamp.to_ext_data().len(), len);
+        assert_eq!(Timestamp::from_ext_data(&timestamp.to_ext_data()), Some(timestamp));
+    }
+
+    // Nanossegundos inválidos ou tamanhos desconhecidos continuam como $ext
+    let invalid = Value::Ext(TIMESTAMP_EXT, [u32::MAX.to_be_bytes(), 0u32.to_be_bytes(), 0u32.to_be_bytes()].concat());
+    assert_eq!(value_to_json(&invalid)["$ext"], -1);
+    assert_eq!(value_to_json(&Value::Ext(TIMESTAMP_EXT, vec![1, 2]))["$ext"], -1);
+}
+
+#[test]
+fn test_timestamp_json_formats() {
+    let value = Timestamp::new(SECONDS, 250_000_000).unwrap().to_value();
+
+    assert_eq!(value_to_json_with(&value, &with_format(TimestampFormat::Tagged)),
+        json!({ "$timestamp": "2024-05-01T12:30:00.250Z" }));
+    assert_eq!(value_to_json_with(&value, &with_format(TimestampFormat::UnixSeconds)), json!(1_714_566_600.25));
+    assert_eq!(value_to_json_with(&value, &with_format(TimestampFormat::UnixMillis)), json!(1_714_566_600_250i64));
+    assert_eq!(value_to_json_with(&value, &with_format(TimestampFormat::Ext)),
+        json!({ "$ext": -1, "data": "O5rKAGYyNcg=" }));
+
+    // Números inteiros quando não há fração
+    let whole = Timestamp::new(SECONDS, 0).unwrap().to_value();
+    assert_eq!(value_to_json_with(&whole, &with_format(TimestampFormat::UnixSeconds)), json!(SECONDS));
+
+    assert_eq!("millis".parse::<TimestampFormat>().unwrap(), TimestampFormat::UnixMillis);
+    assert!(matches!("iso".parse::<TimestampFormat>(), Err(Error::InvalidInput(_))));
+}
+
+#[test]
+fn test_rfc3339_parsing() {
+    let expected = Timestamp::new(SECONDS, 250_000_000).unwrap();
+    for text in ["2024-05-01T12:30:00.250Z", "2024-05-01T14:30:00.25+02:00", "2024-05-01t09:00:00.250-03:30"] {
+        assert_eq!(text.parse::<Timestamp>().unwrap(), expected, "{}", text);
+    }
+
+    let leap_day: Timestamp = "2024-02-29T23:59:59.123456789Z".parse().unwrap();
+    assert_eq!(leap_day.to_string(), "2024-02-29T23:59:59.123456789Z");
+    let year_one: Timestamp = "0001-01-01T00:00:00Z".parse().unwrap();
+    assert_eq!(year_one.seconds(), -62_135_596_800);
+
+    for text in ["2023-02-29T00:00:00Z", "2024-05-01T12:30:00", "2024-05-01T24:00:00Z", "2024-05-01T12:30:00.Z", "agora"] {
+        assert!(matches!(text.parse::<Timestamp>(), Err(Error::InvalidInput(_))), "{}", text);
+    }
+
+    // Anos fora do intervalo de um DateTime do C# são recusados, sem estouro no cálculo
+    for text in [
+        "9223372036854775807-01-01T00:00:00Z",
+        "-9223372036854775808-01-01T00:00:00Z",
+        "-0001-01-01T00:00:00Z",
+        "0000-01-01T00:00:00Z",
+        "10000-01-01T00:00:00Z",
+    ] {
+        assert!(matches!(text.parse::<Timestamp>(), Err(Error::InvalidInput(_))), "{}", text);
+    }
+    assert!(serde_json::from_str::<Timestamp>("\"9223372036854775807-01-01T00:00:00Z\"").is_err());
+}
+
+#[test]
+fn test_tagged_json_encodes_timestamps() {
+    let options = MsgpackOptions { tagged_values: true, ..Default::default() };
+    let json = json!([
+        { "$timestamp": "2024-05-01T12:30:00.250Z" },
+        { "$timestamp": 1_714_566_600 },
+        { "$timestamp": "2024-05-01T12:30:00Z", "other": 1 }
+    ]);
+
+    let value = json_to_value_with(&json, &options).unwrap();
+    assert_eq!(value[0], Timestamp::new(SECONDS, 250_000_000).unwrap().to_value());
+    assert_eq!(value[1], Value::Ext(TIMESTAMP_EXT, (SECONDS as u32).to_be_bytes().to_vec()));
+    assert!(value[2].is_map());
+
+    // O formato tagged lido de volta reproduz o mesmo valor
+    let tagged = value_to_json_with(&value[0], &with_format(TimestampFormat::Tagged));
+    assert_eq!(json_to_value_with(&tagged, &options).unwrap(), value[0]);
+
+    let error = json_to_value_with(&json!({ "$timestamp": "ontem" }), &options).unwrap_err();
+    assert!(error.to_string().contains("Invalid RFC 3339 timestamp"), "Unexpected error: {}", error);
+}
+
+#[test]
+fn test_schema_reads_both_datetime_encodings() {
+    let schema = datetime_schema();
+    let options = JsonOptions { schema: Some(schema.clone()), ..Default::default() };
+
+    // DateTime como extensão de timestamp e como ToBinary() do NativeDateTimeResolver
+    let value = Value::Array(vec![Timestamp::new(SECONDS, 0).unwrap().to_value(), Value::from(DATETIME_BINARY)]);
+    let json = value_to_json_with(&value, &options);
+    assert_eq!(json, json!({ "at": "2024-05-01T12:30:00Z", "native": "2024-05-01T12:30:00.250Z" }));
+
+    // DateTimeKind.Local e Unspecified também são lidos
+    let local = Value::Array(vec![Value::Nil, Value::from(DATETIME_BINARY & 0x3FFF_FFFF_FFFF_FFFF)]);
+    assert_eq!(value_to_json_with(&local, &options)["native"], "2024-05-01T12:30:00.250Z");
+
+    // Cada campo volta para a sua forma
+    let msgpack_options = MsgpackOptions { schema: Some(schema), ..Default::default() };
+    assert_eq!(json_to_value_with(&json, &msgpack_options).unwrap(), value);
+    let from_seconds = json_to_value_with(&json!({ "at": SECONDS, "native": null }), &msgpack_options).unwrap();
+    assert_eq!(from_seconds, Value::Array(vec![value[0].clone(), Value::Nil]));
+
+    let error = json_to_value_with(&json!({ "native": "0001-01-01T00:00:00+01:00" }), &msgpack_options).unwrap_err();
+    assert!(error.to_string().contains("outside the range of a C# DateTime"), "Unexpected error: {}", error);
+}
+
+#[test]
+fn test_timestamp_serde() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Event {
+        name: String,
+        at: Timestamp,
+    }
+
+    let event = Event { name: "partida".to_string(), at: Timestamp::new(SECONDS, 250_000_000).unwrap() };
+
+    // MessagePack usa a extensão -1; JSON usa RFC 3339
+    let envelope = to_vec_lz4(&event, Compression::Lz4BlockArray).unwrap();
+    let value = rmpv::decode::read_value(&mut lz4_messagepack::decode(&envelope).unwrap().as_slice()).unwrap();
+    assert_eq!(value[1], event.at.to_value());
+    assert_eq!(from_slice_lz4::<Event>(&envelope).unwrap(), event);
+
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json, json!({ "name": "partida", "at": "2024-05-01T12:30:00.250Z" }));
+    assert_eq!(serde_json::from_value::<Event>(json).unwrap(), event);
+
+    // Um DateTime nativo também é aceito
+    let native = to_vec_lz4(&("partida", DATETIME_BINARY), Compression::Lz4Block).unwrap();
+    assert_eq!(from_slice_lz4::<Event>(&native).unwrap(), event);
+    assert_eq!(event.at.to_datetime_binary(), Some(DATETIME_BINARY));
+}
@@ -0,0 +1,102 @@
+use lz4_messagepack::{
+    decode_any_to_json, encode, json_to_value_with, value_to_json, Envelope, JsonOptions, LZ4MessagePackProcessor,
+    MsgpackOptions, OutputFormat, TYPELESS_EXT,
+};
+use rmpv::encode::write_value;
+use rmpv::Value;
+use serde_json::json;
+
+/// Extensão 100 como o TypelessFormatter grava: o nome do tipo seguido do corpo
+fn typeless(type_name: &str, body: Value) -> Value {
+    let mut data = Vec::new();
+    write_value(&mut data, &Value::from(type_name)).unwrap();
+    write_value(&mut data, &body).unwrap();
+    Value::Ext(TYPELESS_EXT, data)
+}
+
+fn to_msgpack(value: &Value) -> Vec<u8> {
+    let mut msgpack = Vec::new();
+    write_value(&mut msgpack, value).unwrap();
+    msgpack
+}
+
+fn order() -> Value {
+    typeless("MyApp.Contracts.Order, MyApp.Contracts", Value::Map(vec![
+        (Value::from("Id"), Value::from(42)),
+        (Value::from("Lines"), Value::Array(vec![
+            typeless("MyApp.Contracts.Line, MyApp.Contracts", Value::Map(vec![(Value::from("Qty"), Value::from(2))])),
+        ])),
+        (Value::from("Payload"), typeless("System.String, mscorlib", Value::from("texto"))),
+    ]))
+}
+
+fn order_json() -> serde_json::Value {
+    json!({
+        "$type": "MyApp.Contracts.Order, MyApp.Contracts",
+        "Id": 42,
+        "Payload": { "$type": "System.String, mscorlib", "$value": "texto" },
+        "Lines": [{ "$type": "MyApp.Contracts.Line, MyApp.Contracts", "Qty": 2 }]
+    })
+}
+
+#[test]
+fn test_typeless_values_become_type_objects() {
+    assert_eq!(value_to_json(&order()), order_json());
+
+    // Dados que não são um nome de tipo seguido de um corpo continuam como $ext
+    let invalid = Value::Ext(TYPELESS_EXT, vec![0x01, 0x02]);
+    assert_eq!(value_to_json(&invalid), json!({ "$ext": 100, "data": "AQI=" }));
+}
+
+#[test]
+fn test_typeless_inside_lz4_block_array() {
+    // O TypelessContractlessStandardResolver com LZ4 comprime o fluxo que contém a ext 100
+    let envelope = encode(&to_msgpack(&order())).unwrap();
+    assert_eq!(decode_any_to_json(&envelope, &JsonOptions::default()).unwrap(), order_json());
+
+    // Uma ext 100 solta na forma JSON do Node não é um envelope LZ4
+    let node_json = json!({ "type": 100, "buffer": { "type": "Buffer", "data": match order() {
+        Value::Ext(_, data) => data,
+        _ => unreachable!(),
+    } } });
+    let input = node_json.to_string();
+    assert_eq!(decode_any_to_json(input.as_bytes(), &JsonOptions::default()).unwrap(), order_json());
+
+    let output = LZ4MessagePackProcessor::process_bytes(input.as_bytes(), OutputFormat::Json).unwrap();
+    assert!(output.contains("MyApp.Contracts.Order, MyApp.Contracts"), "Unexpected output: {}", output);
+    assert_eq!(Envelope::parse_json(&node_json).unwrap()[0].ext_type(), TYPELESS_EXT);
+}
+
+#[test]
+fn test_type_objects_encode_back_to_typeless() {
+    let options = MsgpackOptions { tagged_values: true, ..Default::default() };
+    assert_eq!(json_to_value_with(&order_json(), &options).unwrap(), order());
+
+    // $type sem nome qualificado pelo assembly é de uma union e continua como mapa
+    let union = json_to_value_with(&json!({ "$type": "Circle", "radius": 1 }), &options).unwrap();
+    assert!(union.is_map());
+
+    // Sem tagged_values os objetos continuam como mapas
+    assert!(json_to_value_with(&order_json(), &MsgpackOptions::default()).unwrap().is_map());
+}
+
+#[test]
+fn test_nested_typeless_bodies_round_trip() {
+    // O corpo que é outra ext 100 fica em $value e mantém o próprio $type
+    let inner = typeless("MyApp.Inner, MyApp", Value::Map(vec![(Value::from("Id"), Value::from(7))]));
+    let nested = typeless("MyApp.Box, MyApp", inner);
+    let json = value_to_json(&nested);
+    assert_eq!(json, json!({
+        "$type": "MyApp.Box, MyApp",
+        "$value": { "$type": "MyApp.Inner, MyApp", "Id": 7 }
+    }));
+
+    let options = MsgpackOptions { tagged_values: true, ..Default::default() };
+    assert_eq!(json_to_value_with(&json, &options).unwrap(), nested);
+
+    // Vários níveis seguidos também voltam iguais
+    let deep = (0..50).fold(Value::Nil, |body, level| typeless(&format!("MyApp.Level{}, MyApp", level), body));
+    let envelope = encode(&to_msgpack(&deep)).unwrap();
+    let json = decode_any_to_json(&envelope, &JsonOptions::default()).unwrap();
+    assert_eq!(json_to_value_with(&json, &options).unwrap(), deep);
+}
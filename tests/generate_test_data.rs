@@ -148,7 +148,6 @@ fn generate_numeric_data(directory: &Path) -> io::Result<()> {
 // Gerar teste com dados mistos
 fn generate_mixed_data(directory: &Path) -> io::Result<()> {
     // Objetos aninhados
-    let mut buffer: Vec<u8> = Vec::new();
     let mut map = serde_json::Map::new();
     map.insert("name".to_string(), json!("Product"));
     map.insert("price".to_string(), json!(29.99));
@@ -0,0 +1,103 @@
+use lz4_messagepack::codec::Envelope;
+use lz4_messagepack::{lz4mp_decode, lz4mp_decode_into, lz4mp_encode, lz4mp_free_buffer};
+use rmpv::decode::read_value;
+use serde_json::json;
+use std::ptr;
+
+// Call one of the buffer functions and copy its output into a Vec
+fn call(
+    function: unsafe extern "C" fn(*const u8, usize, *mut *mut u8, *mut usize) -> i32,
+    input: &[u8],
+) -> Result<Vec<u8>, i32> {
+    let mut out = ptr::null_mut();
+    let mut out_len = 0;
+    unsafe {
+        let status = function(input.as_ptr(), input.len(), &mut out, &mut out_len);
+        if status != 0 {
+            assert!(out.is_null(), "Output must be untouched on failure");
+            return Err(status);
+        }
+        let bytes = std::slice::from_raw_parts(out, out_len).to_vec();
+        lz4mp_free_buffer(out, out_len);
+        Ok(bytes)
+    }
+}
+
+#[test]
+fn test_encode_returns_a_binary_envelope() {
+    let input = json!({ "name": "John Doe", "tags": ["a", "b"] });
+    let envelope = call(lz4mp_encode, input.to_string().as_bytes()).unwrap();
+
+    // Same layout MessagePack-CSharp writes: [ext 98 header, bin block]
+    assert_eq!(envelope[0], 0x92);
+    assert!(Envelope::detect(&envelope));
+
+    let msgpack = call(lz4mp_decode, &envelope).unwrap();
+    let value = read_value(&mut &msgpack[..]).unwrap();
+    assert_eq!(value["name"].as_str(), Some("John Doe"));
+    assert_eq!(value["tags"][1].as_str(), Some("b"));
+}
+
+#[test]
+fn test_large_payload_round_trips() {
+    let items: Vec<_> = (0..5000).map(|i| json!({ "id": i, "name": format!("item {}", i) })).collect();
+    let input = json!(items).to_string();
+
+    let envelope = call(lz4mp_encode, input.as_bytes()).unwrap();
+    let msgpack = call(lz4mp_decode, &envelope).unwrap();
+    assert!(msgpack.len() > 32 * 1024, "Payload should span several blocks");
+
+    let value = read_value(&mut &msgpack[..]).unwrap();
+    assert_eq!(value.as_array().unwrap().len(), 5000);
+}
+
+#[test]
+fn test_errors_return_their_code() {
+    assert_eq!(call(lz4mp_encode, b"{not json"), Err(2));
+    assert_eq!(call(lz4mp_encode, &[0xff, 0xfe]), Err(2));
+    assert_eq!(call(lz4mp_decode, &[0x92, 0xd4, 98]), Err(9));
+    assert_eq!(call(lz4mp_decode, &[0xc7, 0x01, 0x05, 0x00]), Err(7));
+}
+
+#[test]
+fn test_null_pointers_are_rejected() {
+    let mut out = ptr::null_mut();
+    let mut out_len = 0;
+    unsafe {
+        assert_eq!(lz4mp_encode(ptr::null(), 4, &mut out, &mut out_len), 2);
+        assert_eq!(lz4mp_encode(b"null".as_ptr(), 4, ptr::null_mut(), &mut out_len), 2);
+        assert_eq!(lz4mp_decode(b"null".as_ptr(), 4, &mut out, ptr::null_mut()), 2);
+        lz4mp_free_buffer(ptr::null_mut(), 0);
+    }
+}
+
+#[test]
+fn test_decode_into_reuses_the_callers_buffer() {
+    let items: Vec<_> = (0..5000).map(|i| json!({ "id": i, "name": format!("item {}", i) })).collect();
+    let envelope = call(lz4mp_encode, json!(items).to_string().as_bytes()).unwrap();
+    let expected = call(lz4mp_decode, &envelope).unwrap();
+
+    unsafe {
+        // A null buffer with no capacity just queries the size
+        let mut out_len = 0;
+        assert_eq!(lz4mp_decode_into(envelope.as_ptr(), envelope.len(), ptr::null_mut(), 0, &mut out_len), 12);
+        assert_eq!(out_len, expected.len());
+
+        // Too small: nothing is written and the exact size is reported
+        let mut buffer = vec![0xaa; 100];
+        out_len = 0;
+        assert_eq!(lz4mp_decode_into(envelope.as_ptr(), envelope.len(), buffer.as_mut_ptr(), 100, &mut out_len), 12);
+        assert_eq!(out_len, expected.len());
+        assert!(buffer.iter().all(|&b| b == 0xaa));
+
+        buffer.resize(out_len + 10, 0xaa);
+        let status = lz4mp_decode_into(envelope.as_ptr(), envelope.len(), buffer.as_mut_ptr(), buffer.len(), &mut out_len);
+        assert_eq!(status, 0);
+        assert_eq!(&buffer[..out_len], expected.as_slice());
+        assert_eq!(buffer[out_len..], [0xaa; 10]);
+
+        assert_eq!(lz4mp_decode_into(envelope.as_ptr(), envelope.len(), buffer.as_mut_ptr(), buffer.len(), ptr::null_mut()), 2);
+        assert_eq!(lz4mp_decode_into(envelope.as_ptr(), envelope.len(), ptr::null_mut(), 10, &mut out_len), 2);
+        assert_eq!(lz4mp_decode_into([0x92, 0xd4, 98].as_ptr(), 3, buffer.as_mut_ptr(), buffer.len(), &mut out_len), 9);
+    }
+}
@@ -0,0 +1,108 @@
+use lz4_messagepack::{
+    free_string, lz4mp_decode_to_json, lz4mp_encode, lz4mp_free_buffer, lz4mp_last_error_message,
+    process_lz4_messagepack,
+};
+use serde_json::{json, Value as JsonValue};
+use std::ffi::{CStr, CString};
+use std::ptr;
+
+// Call the FFI decoder and return its output, or the status and last error message
+fn call_decode(input: *const u8, len: usize) -> Result<String, (i32, String)> {
+    let mut out = ptr::null_mut();
+    unsafe {
+        let status = lz4mp_decode_to_json(input, len, &mut out);
+        if status != 0 {
+            assert!(out.is_null(), "Output must be untouched on failure");
+            return Err((status, CStr::from_ptr(lz4mp_last_error_message()).to_str().unwrap().to_string()));
+        }
+        let output = CStr::from_ptr(out).to_str().unwrap().to_string();
+        free_string(out);
+        Ok(output)
+    }
+}
+
+fn decode(input: &[u8]) -> Result<String, (i32, String)> {
+    call_decode(input.as_ptr(), input.len())
+}
+
+fn decode_json(input: &[u8]) -> JsonValue {
+    let output = decode(input).unwrap_or_else(|e| panic!("Decoder returned an error: {:?}", e));
+    serde_json::from_str(&output).unwrap()
+}
+
+// Binary envelope as the C# API sends it
+fn encode_binary(input: &JsonValue) -> Vec<u8> {
+    let input = input.to_string();
+    let mut out = ptr::null_mut();
+    let mut out_len = 0;
+    unsafe {
+        assert_eq!(lz4mp_encode(input.as_ptr(), input.len(), &mut out, &mut out_len), 0);
+        let bytes = std::slice::from_raw_parts(out, out_len).to_vec();
+        lz4mp_free_buffer(out, out_len);
+        bytes
+    }
+}
+
+// Node-style JSON envelope as produced by process_lz4_messagepack
+fn encode_node_json(input: &JsonValue) -> String {
+    let input = CString::new(input.to_string()).unwrap();
+    unsafe {
+        let output_ptr = process_lz4_messagepack(input.as_ptr());
+        let output = CStr::from_ptr(output_ptr).to_str().unwrap().to_string();
+        free_string(output_ptr);
+        output
+    }
+}
+
+#[test]
+fn test_decodes_binary_envelope() {
+    let input = json!({ "name": "John Doe", "age": 30, "tags": ["a", "b"] });
+    assert_eq!(decode_json(&encode_binary(&input)), input);
+}
+
+#[test]
+fn test_decodes_node_json_envelope() {
+    let input = json!([{ "id": 1 }, { "id": 2, "value": 2.5 }]);
+    assert_eq!(decode_json(encode_node_json(&input).as_bytes()), input);
+}
+
+#[test]
+fn test_decodes_a_lone_node_buffer() {
+    // Buffer.toJSON() of the raw response body, compressed or not
+    let input = json!({ "name": "John Doe", "tags": ["a", "b"] });
+    let buffer = json!({ "type": "Buffer", "data": encode_binary(&input) });
+    assert_eq!(decode_json(buffer.to_string().as_bytes()), input);
+
+    let buffer = json!({ "type": "Buffer", "data": [0x81, 0xa2, b'o', b'k', 0xc3] });
+    assert_eq!(decode_json(buffer.to_string().as_bytes()), json!({ "ok": true }));
+
+    let (status, message) = decode(br#"{"type": "Buffer", "data": [256]}"#).unwrap_err();
+    assert_eq!(status, 2);
+    assert!(message.contains("Invalid buffer"), "Unexpected message: {}", message);
+}
+
+#[test]
+fn test_decodes_large_payload() {
+    let items: Vec<_> = (0..5000).map(|i| json!({ "id": i, "name": format!("item {}", i) })).collect();
+    let input = json!(items);
+    assert_eq!(decode_json(&encode_binary(&input)), input);
+    assert_eq!(decode_json(encode_node_json(&input).as_bytes()), input);
+}
+
+#[test]
+fn test_decodes_uncompressed_messagepack() {
+    // MessagePack-CSharp skips compression for small payloads: {"ok": true}
+    assert_eq!(decode_json(&[0x81, 0xa2, b'o', b'k', 0xc3]), json!({ "ok": true }));
+}
+
+#[test]
+fn test_decode_errors_carry_their_code() {
+    let (status, message) = decode(&[0x92, 0xd4, 98]).unwrap_err();
+    assert_eq!(status, 9);
+    assert!(message.starts_with("Invalid MessagePack"), "Unexpected message: {}", message);
+
+    let (status, _) = decode(br#"{"hello": "world"}"#).unwrap_err();
+    assert_eq!(status, 2);
+
+    assert_eq!(call_decode(ptr::null(), 3), Err((2, "Null input".to_string())));
+}
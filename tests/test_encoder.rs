@@ -0,0 +1,209 @@
+use lz4_messagepack::{
+    free_string, process_lz4_messagepack, process_lz4_messagepack_with_compression,
+    process_lz4_messagepack_with_options, Compression, EncodeOptions,
+};
+use rmpv::decode::read_value;
+use rmpv::Value;
+use serde_json::{json, Value as JsonValue};
+use std::ffi::{CStr, CString};
+
+// Call the FFI encoder and parse its JSON envelope
+fn encode(input: &JsonValue) -> JsonValue {
+    let input = CString::new(input.to_string()).unwrap();
+    let output = unsafe {
+        let output_ptr = process_lz4_messagepack(input.as_ptr());
+        let output = CStr::from_ptr(output_ptr).to_str().unwrap().to_string();
+        free_string(output_ptr);
+        output
+    };
+    serde_json::from_str(&output).unwrap_or_else(|_| panic!("Encoder returned an error: {}", output))
+}
+
+fn encode_with(input: &JsonValue, compression: i32) -> String {
+    let input = CString::new(input.to_string()).unwrap();
+    unsafe {
+        let output_ptr = process_lz4_messagepack_with_compression(input.as_ptr(), compression);
+        let output = CStr::from_ptr(output_ptr).to_str().unwrap().to_string();
+        free_string(output_ptr);
+        output
+    }
+}
+
+fn encode_with_options(input: &JsonValue, options: Option<&EncodeOptions>) -> String {
+    let input = CString::new(input.to_string()).unwrap();
+    let options = options.map_or(std::ptr::null(), |o| o as *const EncodeOptions);
+    unsafe {
+        let output_ptr = process_lz4_messagepack_with_options(input.as_ptr(), options);
+        let output = CStr::from_ptr(output_ptr).to_str().unwrap().to_string();
+        free_string(output_ptr);
+        output
+    }
+}
+
+// Encode with the given options and return the decompressed MessagePack
+fn encode_to_msgpack(input: &JsonValue, options: &EncodeOptions) -> Vec<u8> {
+    let output = encode_with_options(input, Some(options));
+    let envelope: JsonValue =
+        serde_json::from_str(&output).unwrap_or_else(|_| panic!("Encoder returned an error: {}", output));
+    decode(&envelope).1
+}
+
+fn bytes(value: &JsonValue) -> Vec<u8> {
+    value.as_array().unwrap().iter().map(|b| b.as_u64().unwrap() as u8).collect()
+}
+
+// Decode the envelope the way MessagePack-CSharp does: one length per block in the ext header
+fn decode(envelope: &JsonValue) -> (Vec<usize>, Vec<u8>) {
+    let elements = envelope.as_array().unwrap();
+    assert_eq!(elements[0]["type"], 98);
+
+    let header = bytes(&elements[0]["buffer"]["data"]);
+    let mut cursor = &header[..];
+    let mut lengths = Vec::new();
+    while !cursor.is_empty() {
+        let value = read_value(&mut cursor).unwrap();
+        lengths.push(value.as_u64().unwrap() as usize);
+    }
+    assert_eq!(lengths.len(), elements.len() - 1, "One Buffer per declared block");
+
+    let mut msgpack = Vec::new();
+    for (length, block) in lengths.iter().zip(&elements[1..]) {
+        assert_eq!(block["type"], "Buffer");
+        let decompressed = lz4::block::decompress(&bytes(&block["data"]), Some(*length as i32)).unwrap();
+        assert_eq!(decompressed.len(), *length);
+        msgpack.extend_from_slice(&decompressed);
+    }
+    (lengths, msgpack)
+}
+
+#[test]
+fn test_small_payload_is_a_single_block() {
+    let input = json!({ "name": "John Doe", "age": 30 });
+    let (lengths, msgpack) = decode(&encode(&input));
+
+    assert_eq!(lengths.len(), 1);
+    let value = read_value(&mut &msgpack[..]).unwrap();
+    assert_eq!(value["name"], Value::from("John Doe"));
+    assert_eq!(value["age"], Value::from(30));
+}
+
+#[test]
+fn test_header_uses_messagepack_integers() {
+    let input = json!("x".repeat(300));
+    let envelope = encode(&input);
+
+    // 300 chars + str16 prefix = 303 bytes, written as MessagePack uint16
+    assert_eq!(bytes(&envelope[0]["buffer"]["data"]), vec![0xcd, 0x01, 0x2f]);
+}
+
+#[test]
+fn test_large_payload_is_split_into_blocks() {
+    let items: Vec<JsonValue> = (0..20_000).map(|i| json!({ "id": i, "label": format!("item {}", i) })).collect();
+    let input = JsonValue::Array(items);
+    let (lengths, msgpack) = decode(&encode(&input));
+
+    assert!(lengths.len() > 1, "Expected several blocks, got {:?}", lengths);
+    assert!(lengths[..lengths.len() - 1].iter().all(|&l| l == 32 * 1024));
+
+    let value = read_value(&mut &msgpack[..]).unwrap();
+    let decoded = value.as_array().unwrap();
+    assert_eq!(decoded.len(), 20_000);
+    assert_eq!(decoded[19_999]["label"], Value::from("item 19999"));
+}
+
+#[test]
+fn test_lz4_block_mode() {
+    let input = json!({ "mode": "Lz4Block", "values": [1, 2, 3] });
+    let output = encode_with(&input, Compression::Lz4Block as i32);
+    let envelope: JsonValue = serde_json::from_str(&output).unwrap();
+
+    // A lone ext 99: int32 length (0xd2) followed by the LZ4 block
+    assert_eq!(envelope["type"], 99);
+    let payload = bytes(&envelope["buffer"]["data"]);
+    assert_eq!(payload[0], 0xd2);
+    let length = i32::from_be_bytes(payload[1..5].try_into().unwrap());
+    let msgpack = lz4::block::decompress(&payload[5..], Some(length)).unwrap();
+    assert_eq!(msgpack.len(), length as usize);
+
+    let value = read_value(&mut &msgpack[..]).unwrap();
+    assert_eq!(value["mode"], Value::from("Lz4Block"));
+}
+
+#[test]
+fn test_unknown_compression_mode_is_an_error() {
+    let output = encode_with(&json!([1, 2, 3]), 7);
+    assert_eq!(output, "Error: Unsupported compression mode: 7");
+}
+
+#[test]
+fn test_errors_carry_their_code() {
+    let input = CString::new("{not json").unwrap();
+    let output = unsafe {
+        let output_ptr = process_lz4_messagepack(input.as_ptr());
+        let output = CStr::from_ptr(output_ptr).to_str().unwrap().to_string();
+        free_string(output_ptr);
+        output
+    };
+    assert!(output.starts_with("Error: Failed to parse JSON"), "Unexpected output: {}", output);
+}
+
+#[test]
+fn test_null_options_match_the_default_encoder() {
+    let input = json!({ "name": "John Doe", "scores": [1.5, 2.25] });
+    let output: JsonValue = serde_json::from_str(&encode_with_options(&input, None)).unwrap();
+    assert_eq!(output, encode(&input));
+}
+
+#[test]
+fn test_prefer_f32_when_lossless() {
+    let input = json!([1.5, 0.1]);
+    let options = EncodeOptions { prefer_f32: 1, ..EncodeOptions::default() };
+    let msgpack = encode_to_msgpack(&input, &options);
+
+    // 1.5 fits exactly in a float 32, 0.1 does not
+    let value = read_value(&mut &msgpack[..]).unwrap();
+    assert_eq!(value, Value::Array(vec![Value::F32(1.5), Value::F64(0.1)]));
+    assert_eq!(msgpack[1], 0xca);
+
+    let msgpack = encode_to_msgpack(&input, &EncodeOptions::default());
+    assert_eq!(read_value(&mut &msgpack[..]).unwrap(), Value::Array(vec![Value::F64(1.5), Value::F64(0.1)]));
+}
+
+#[test]
+fn test_tagged_values_become_bin_and_ext() {
+    let input = json!({
+        "bin": { "$bin": "AQID" },
+        "ext": { "$ext": 5, "data": [4, 5] },
+        "time": { "$timestamp": "1970-01-01T00:00:01.5Z" },
+        "plain": { "$bin": "AQID", "other": 1 }
+    });
+    let options = EncodeOptions { tagged_values: 1, ..EncodeOptions::default() };
+    let value = read_value(&mut &encode_to_msgpack(&input, &options)[..]).unwrap();
+
+    assert_eq!(value["bin"], Value::Binary(vec![1, 2, 3]));
+    assert_eq!(value["ext"], Value::Ext(5, vec![4, 5]));
+    assert_eq!(value["time"], Value::Ext(-1, vec![0x77, 0x35, 0x94, 0x00, 0, 0, 0, 1]));
+    assert!(value["plain"].is_map());
+
+    // Without the flag the tags are ordinary maps
+    let value = read_value(&mut &encode_to_msgpack(&input, &EncodeOptions::default())[..]).unwrap();
+    assert!(value["bin"].is_map());
+}
+
+#[test]
+fn test_malformed_tagged_value_is_an_error() {
+    let options = EncodeOptions { tagged_values: 1, ..EncodeOptions::default() };
+    let output = encode_with_options(&json!({ "$bin": "not base64!" }), Some(&options));
+    assert!(output.starts_with("Error: Invalid base64 in $bin value"), "Unexpected output: {}", output);
+
+    let output = encode_with_options(&json!({ "$ext": 300, "data": [] }), Some(&options));
+    assert!(output.starts_with("Error: Invalid $ext type: 300"), "Unexpected output: {}", output);
+}
+
+#[test]
+fn test_u64_keeps_its_full_range() {
+    let input = json!([u64::MAX, i64::MIN]);
+    let value = read_value(&mut &encode_to_msgpack(&input, &EncodeOptions::default())[..]).unwrap();
+    assert_eq!(value[0].as_u64(), Some(u64::MAX));
+    assert_eq!(value[1].as_i64(), Some(i64::MIN));
+}
@@ -0,0 +1,130 @@
+use lz4_messagepack::{
+    free_string, lz4mp_decode, lz4mp_decode_to_json, lz4mp_encode_to_json, lz4mp_last_error_code,
+    lz4mp_last_error_message, process_lz4_messagepack_with_compression, EncodeOptions, LZ4MP_OK,
+};
+use serde_json::Value as JsonValue;
+use std::ffi::{CStr, CString};
+use std::ptr;
+use std::thread;
+
+fn last_error() -> (i32, Option<String>) {
+    let message = lz4mp_last_error_message();
+    let message = (!message.is_null())
+        .then(|| unsafe { CStr::from_ptr(message) }.to_str().unwrap().to_string());
+    (lz4mp_last_error_code(), message)
+}
+
+fn encode_to_json(input: &str, options: Option<&EncodeOptions>) -> Result<String, i32> {
+    let input = CString::new(input).unwrap();
+    let options = options.map_or(ptr::null(), |o| o as *const EncodeOptions);
+    let mut out = ptr::null_mut();
+    unsafe {
+        match lz4mp_encode_to_json(input.as_ptr(), options, &mut out) {
+            LZ4MP_OK => {
+                let output = CStr::from_ptr(out).to_str().unwrap().to_string();
+                free_string(out);
+                Ok(output)
+            },
+            status => Err(status),
+        }
+    }
+}
+
+#[test]
+fn test_encode_to_json_returns_a_status() {
+    let output = encode_to_json(r#"{"name": "John Doe"}"#, None).unwrap();
+    let envelope: JsonValue = serde_json::from_str(&output).unwrap();
+    assert_eq!(envelope[0]["type"], 98);
+    assert_eq!(last_error(), (LZ4MP_OK, None));
+
+    let options = EncodeOptions { compression: 7, ..EncodeOptions::default() };
+    assert_eq!(encode_to_json("[1]", Some(&options)), Err(8));
+    assert_eq!(last_error(), (8, Some("Unsupported compression mode: 7".to_string())));
+}
+
+#[test]
+fn test_success_clears_the_last_error() {
+    assert_eq!(encode_to_json("{not json", None), Err(2));
+    let (code, message) = last_error();
+    assert_eq!(code, 2);
+    assert!(message.unwrap().starts_with("Failed to parse JSON"));
+
+    encode_to_json("[]", None).unwrap();
+    assert_eq!(last_error(), (LZ4MP_OK, None));
+}
+
+#[test]
+fn test_legacy_functions_set_the_last_error() {
+    let input = CString::new("[1, 2, 3]").unwrap();
+    let output = unsafe {
+        let output_ptr = process_lz4_messagepack_with_compression(input.as_ptr(), 7);
+        let output = CStr::from_ptr(output_ptr).to_str().unwrap().to_string();
+        free_string(output_ptr);
+        output
+    };
+    assert_eq!(output, "Error: Unsupported compression mode: 7");
+    assert_eq!(last_error(), (8, Some("Unsupported compression mode: 7".to_string())));
+}
+
+#[test]
+fn test_last_error_is_per_thread() {
+    assert_eq!(encode_to_json("{not json", None), Err(2));
+
+    let other = thread::spawn(|| {
+        let before = last_error();
+        encode_to_json("[]", None).unwrap();
+        before
+    }).join().unwrap();
+
+    assert_eq!(other, (LZ4MP_OK, None));
+    assert_eq!(last_error().0, 2);
+}
+
+#[test]
+fn test_null_output_pointers_are_errors() {
+    let input = CString::new("[]").unwrap();
+    unsafe {
+        assert_eq!(lz4mp_encode_to_json(input.as_ptr(), ptr::null(), ptr::null_mut()), 2);
+        assert_eq!(last_error(), (2, Some("Null output pointer".to_string())));
+
+        let mut out_len = 0;
+        assert_eq!(lz4mp_decode([0x90].as_ptr(), 1, ptr::null_mut(), &mut out_len), 2);
+    }
+}
+
+#[test]
+fn test_decoding_limits_apply_by_default() {
+    // An Lz4BlockArray whose header declares a 2 GiB block, in 13 bytes
+    let bomb = [0x92, 0xc7, 0x05, 98, 0xd2, 0x7f, 0xff, 0xff, 0xff, 0xc4, 0x02, 0x10, 0xc0];
+    let mut out = ptr::null_mut();
+    let mut out_len = 0;
+    unsafe {
+        assert_eq!(lz4mp_decode(bomb.as_ptr(), bomb.len(), &mut out, &mut out_len), 13);
+    }
+    assert!(out.is_null());
+    assert_eq!(
+        last_error(),
+        (13, Some("Input exceeds the block length limit: 2147483647 is more than the allowed 16777216".to_string()))
+    );
+}
+
+#[test]
+fn test_nested_typeless_values_hit_the_depth_limit() {
+    // 100,000 Typeless (ext 100) values, each the body of the one before, about 1 MB in all
+    let mut input = Vec::new();
+    for level in 0..100_000u32 {
+        input.push(0xc9);
+        input.extend(((100_000 - level) * 11 - 5).to_be_bytes());
+        input.extend([100, 0xa4, b'T', b',', b' ', b'a']);
+    }
+    input.push(0xc0);
+
+    let mut out = ptr::null_mut();
+    unsafe {
+        assert_eq!(lz4mp_decode_to_json(input.as_ptr(), input.len(), &mut out), 13);
+    }
+    assert!(out.is_null());
+    let (code, message) = last_error();
+    assert_eq!(code, 13);
+    assert!(message.unwrap().contains("nesting depth"));
+}